@@ -0,0 +1,113 @@
+//! gradient of a chunk through `backprop_batch`, and through `backprop_batch_into` with
+//! buffers reused as `Trainer::build` does, against the per-item loop they replaced
+//!
+//! `cargo bench --bench batch`
+
+extern crate simple_nn as nn;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use nn::{derive_layers, func::*, model::*};
+
+const BATCH: usize = 64;
+const ROUNDS: usize = 200;
+
+#[derive_layers(3, activation(ReLU, ReLU, Identity), init(HeNormal, HeNormal, XavierNormal))]
+struct Net{}
+
+type Mlp = Net<64, 128, 128, 10>;
+
+fn d_out(labels: &Batch<f64, 10>, outputs: &Batch<f64, 10>) -> Batch<f64, 10> {
+    let columns = labels.column_iter()
+        .zip(outputs.column_iter())
+        .map(|(l, o)| DistanceFunc::d(&l.into_owned(), &o.into_owned()))
+        .collect::<Vec<SVector<f64, 10>>>();
+    Batch::from_columns(&columns)
+}
+
+/// gradient of the whole batch, `_actvt` is only for its type
+fn batched<A, Cal>(layers: &Mlp, inputs: &Batch<f64, 64>, labels: &Batch<f64, 10>, seeds: &[u64], _actvt: A) -> Mlp
+where
+    Mlp: Layers<A, Cal, 64, 10, f64>,
+    Cal: Calculation<10, f64>
+{
+    layers.backprop_batch(inputs, seeds, |out| d_out(labels, out)).1
+}
+
+/// what `Trainer::build` keeps across chunks
+struct Reused {
+    grad: Mlp,
+    outputs: Batch<f64, 10>,
+    buffers: Buffers<f64>,
+}
+
+/// gradient of the whole batch added to `reused.grad`, on matrices of warm buffers
+fn batched_into<A, Cal>(layers: &Mlp, inputs: &Batch<f64, 64>, labels: &Batch<f64, 10>, seeds: &[u64], reused: &mut Reused, _actvt: A)
+where
+    Mlp: Layers<A, Cal, 64, 10, f64>,
+    Cal: Calculation<10, f64>
+{
+    layers.backprop_batch_into(&mut reused.grad, inputs, seeds, &mut reused.outputs, |out, k| {
+        for ((mut k, l), o) in k.column_iter_mut().zip(labels.column_iter()).zip(out.column_iter()) {
+            k.copy_from(&DistanceFunc::d(&l.into_owned(), &o.into_owned()));
+        }
+    }, &mut reused.buffers);
+}
+
+/// the loop of `Trainer::build` before batching, summing the gradient of each item,
+/// `_actvt` is only for its type
+fn per_item<A, Cal>(layers: &Mlp, inputs: &Batch<f64, 64>, labels: &Batch<f64, 10>, seeds: &[u64], _actvt: A) -> Mlp
+where
+    Mlp: Layers<A, Cal, 64, 10, f64>,
+    Cal: Calculation<10, f64>
+{
+    let mut sum = Mlp::default();
+    for ((x, l), &seed) in inputs.column_iter().zip(labels.column_iter()).zip(seeds) {
+        let x = x.into_owned();
+        let calc = layers.forward_train( &x, &mut StdRng::seed_from_u64(seed));
+        let out = calc.out().to_owned();
+        let grad = layers.backward( &x, DistanceFunc::d(&l.into_owned(), &out), calc);
+        for ((sw, sb), (w, b)) in sum.params_mut().into_iter().zip(grad.params()) {
+            sw.iter_mut().chain(sb.iter_mut()).zip(w.iter().chain(b)).for_each(|(s, &g)| *s += g);
+        }
+    }
+    sum
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    // warm up
+    for _ in 0..ROUNDS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS as u32
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let layers = Mlp::random_with(&mut rng);
+    let inputs = Batch::<f64, 64>::from_fn(BATCH, |_, _| rng.gen_range(-1.0..1.0));
+    let labels = Batch::<f64, 10>::from_fn(BATCH, |_, _| rng.gen_range(0.0..1.0));
+    let seeds = (0..BATCH).map(|_| rng.gen()).collect::<Vec<u64>>();
+
+    let looped = time(|| {
+        black_box(per_item(black_box(&layers), &inputs, &labels, &seeds, ReLU));
+    });
+    let batched = time(|| {
+        black_box(batched(black_box(&layers), &inputs, &labels, &seeds, ReLU));
+    });
+    let mut state = Reused { grad: Mlp::default(), outputs: Batch::zeros(BATCH), buffers: Buffers::new() };
+    let reused = time(|| {
+        batched_into(black_box(&layers), &inputs, &labels, &seeds, &mut state, ReLU);
+        black_box(&state.grad);
+    });
+
+    println!("{} items through 64-128-128-10", BATCH);
+    println!("per item:  {:?}", looped);
+    println!("batched:   {:?} ({:.2}x)", batched, looped.as_secs_f64() / batched.as_secs_f64());
+    println!("reused:    {:?} ({:.2}x)", reused, looped.as_secs_f64() / reused.as_secs_f64());
+}
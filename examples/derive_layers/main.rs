@@ -2,7 +2,7 @@
 
 extern crate simple_nn as nn;
 
-use nn::{Config, derive_layers, Network, func::{ActivitionFunc, LossFunc, DistanceFunc, Tanh}, model::*};
+use nn::{Config, derive_layers, Network, func::{ActivitionFunc, DistanceFunc, Tanh}, model::*, metric::Mse, optim::Sgd, schedule::StepDecay};
 
 use tools::*;
 
@@ -20,6 +20,11 @@ fn main() {
         learn_rate: 0.1,
         batch_size: 5,
         iter_num: 20_000,
+        optimizer: Sgd,
+        scheduler: StepDecay::default(),
+        seed: None,
+        regularization: Regularization::new(Penalty::l2(1e-4)),
+        class_weights: None,
     };
 
     let net = Network::cfg(layers, config);
@@ -27,20 +32,23 @@ fn main() {
     let data = load_data();
 
     let train = &data[0..30];
-    let test = &data[30..];
-
-    let trainner = net.train(train);
-
-    let model = trainner.build();
-
-    // test
-    let mut dis_sum = 0.;
-    for label in test.iter().map(|i| &i.label) {
-        let out = model.test(label);
-        dis_sum += DistanceFunc::f(label, &out);
+    let validation = &data[30..60];
+    let test = &data[60..];
+
+    let mut trainner = net.train(train);
+    trainner.validate_with(validation);
+    trainner.early_stop(1_000);
+    trainner.metric(Mse);
+
+    let (model, history) = trainner.build();
+    println!("Trained {} iters, best at {:?}", history.epochs, history.best_epoch);
+    if let (Some(best), Some(mse)) = (history.best_epoch, history.val_metric("mse")) {
+        println!("Validation mse at best: {}", mse[best - 1]);
     }
 
-    let avg_cost = dis_sum / test.len() as f64;
-    println!("Avg Distance: {:.4}", avg_cost);
+    // test
+    let eval = model.evaluate(test, DistanceFunc, &[&Mse]);
+    println!("Avg Distance: {:.4}", eval.loss);
+    println!("Mse: {:.4}", eval.metric("mse").unwrap());
 }
 
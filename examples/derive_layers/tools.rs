@@ -6,10 +6,10 @@ use crate::data::IRIS;
 pub fn load_data<const I:usize>() -> Vec<Item<I, I>> {
     let mut data = Vec::with_capacity(150);
     for line in IRIS.lines(){
-        if line == "" {
+        if line.is_empty() {
             break;
         }
-        let segments = parse_line::<I>(&line);
+        let segments = parse_line::<I>(line);
         data.push(segments);
     }
     data.shuffle(&mut rand::thread_rng());
@@ -37,7 +37,7 @@ fn minmax_scale<const I: usize>(mut data: Vec<SVector<f64,I>>) -> Vec<Item<I,I>>
     }
 
     data.into_iter().map(|v| {
-        Item { data: v.clone(), label: v }
+        Item::new(v, v)
     }).collect()
 }
 
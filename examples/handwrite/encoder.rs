@@ -11,7 +11,7 @@ extern crate neural_network as nn;
 
 use nn::{func::*, model::*};
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct EncoderLayers<
     const L0: usize,
     const L1: usize,
@@ -34,9 +34,13 @@ pub struct EncoderLayersCal<
 
 impl<const L0: usize,const L1: usize,const L2: usize> EncoderLayers<L0,L1,L2> {
     pub fn random() -> Self {
+        Self::random_with(&mut StdRng::from_entropy())
+    }
+
+    pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Self {
-            layer_1: Layer::random(),
-            layer_2: Layer::random(),
+            layer_1: Layer::random_with(rng),
+            layer_2: Layer::random_with(rng),
         }
     }
 }
@@ -65,14 +69,14 @@ impl<F: ActivitionFunc, const L0: usize,const L1: usize,const L2: usize> Layers<
     fn backward(&self, input: &SVector<f64,L0>, pE_pOut: SVector<f64, L2>, calc: EncoderLayersCal<L0,L1,L2>) -> Self {
         
         let k = &pE_pOut;
-        let delta = calc.a_2.zip_map(k, |y, k| { k * F::d_from_y(y) });
+        let delta = calc.a_2.zip_zip_map(&calc.z_2, k, |y, z, k| { k * F::d(z, y) });
         let layer_2 = Layer {
             w: delta * calc.a_1.transpose(),
             b: delta,
         };
 
         let k = self.layer_2.w.transpose() * delta;
-        let delta = calc.a_1.zip_map(&k, |y, k| { k * F::d_from_y(y) });
+        let delta = calc.a_1.zip_zip_map(&calc.z_1, &k, |y, z, k| { k * F::d(z, y) });
         let layer_1 = Layer {
             w: delta * input.transpose(),
             b: delta,
@@ -84,7 +88,14 @@ impl<F: ActivitionFunc, const L0: usize,const L1: usize,const L2: usize> Layers<
         }
     }
 
-    fn update(& mut self, rate: f64, gradients: impl IntoIterator<Item = Self>) {
+    fn update<Opt: Optimizer>(
+        &mut self,
+        rate: f64,
+        optimizer: &Opt,
+        moments: &mut Moments<Self>,
+        regularization: &Regularization,
+        gradients: impl IntoIterator<Item = Self>
+    ) {
         let mut iter = gradients.into_iter().enumerate();
         let (mut len, mut f) = iter.next().unwrap();
         
@@ -98,13 +109,16 @@ impl<F: ActivitionFunc, const L0: usize,const L1: usize,const L2: usize> Layers<
         // average
         let len = (len + 1) as f64;
         f.layer_1 /= len;
-        f.layer_1 *= rate;
-        
         f.layer_2 /= len;
-        f.layer_2 *= rate;
-        
-        self.layer_1 -= &f.layer_1;
-        self.layer_2 -= &f.layer_2
+
+        // penalty
+        f.layer_1.penalize(&self.layer_1, regularization.layer(1), regularization.include_bias);
+        f.layer_2.penalize(&self.layer_2, regularization.layer(2), regularization.include_bias);
+
+        // apply
+        let t = moments.next_step();
+        self.layer_1.optimize(optimizer, t, rate, &f.layer_1, &mut moments.m.layer_1, &mut moments.v.layer_1);
+        self.layer_2.optimize(optimizer, t, rate, &f.layer_2, &mut moments.m.layer_2, &mut moments.v.layer_2);
     }
 }
 
@@ -0,0 +1,461 @@
+//! [`derive_layers`](crate::derive_layers) on a struct whose fields are the layers,
+//! each of a type implementing `Module`, chained in the order they are declared.
+
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+
+use quote::{ToTokens, format_ident, quote};
+use syn::{Attribute, Expr, GenericParam, ItemStruct, LitFloat, Type, parse_quote, spanned::Spanned};
+
+use crate::{Args, DropRate, impl_params};
+
+/// a field of the struct with the settings of its attributes
+struct Field {
+    ident: Ident,
+    ty: Type,
+    /// `#[activation(..)]`, whose `_` is `F` of the impl, or `None` without activation
+    activation: Option<Option<Type>>,
+    /// `#[init(..)]`, `Module::random_with` is used if `None`
+    init: Option<Ident>,
+    /// `#[dropout(..)]`
+    dropout: Option<LitFloat>,
+}
+
+impl Field {
+    /// tokens of the activation function applied to the output
+    fn activation(&self) -> TokenStream {
+        match &self.activation {
+            Some(Some(ty)) => quote! {<#ty as ActivitionFunc>},
+            Some(None) => quote! {<F as ActivitionFunc>},
+            None => quote! {<Identity as ActivitionFunc>},
+        }
+    }
+}
+
+/// the struct with the attributes of its fields removed and the scalar type `T` appended,
+/// the fields with their settings, and sizes of the input and the output
+struct Declared {
+    strct: ItemStruct,
+    fields: Vec<Field>,
+    input: TokenStream,
+    output: TokenStream,
+    softmax: bool,
+}
+
+pub(crate) fn derive(strct: ItemStruct, args: Args) -> syn::Result<TokenStream> {
+    let declared = parse(strct, args)?;
+    let strct = &declared.strct;
+    let impl_layers = impl_layers(&declared);
+    let impl_random = impl_random(&declared);
+    let impl_params = impl_params(strct);
+
+    Ok(quote! {
+        #[derive(Clone)]
+        #strct
+        #impl_random
+        #impl_layers
+        #impl_params
+    })
+}
+
+fn parse(mut strct: ItemStruct, args: Args) -> syn::Result<Declared> {
+    if args.layer_count != 0 {
+        return Err(syn::Error::new(Span::call_site(), "the layer count should not be given with declared fields"));
+    }
+    let size = |size: Option<Expr>, key: &str| match size {
+        Some(size) => Ok(quote! {{#size}}),
+        None => Err(syn::Error::new(Span::call_site(), format!("`{}(..)` is required with declared fields", key))),
+    };
+    let input = size(args.input, "input")?;
+    let output = size(args.output, "output")?;
+
+    let mut fields = Vec::new();
+    for field in strct.fields.iter_mut() {
+        let ident = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => return Err(syn::Error::new_spanned(field, "fields of layers should be named")),
+        };
+        let mut parsed = Field { ident, ty: field.ty.clone(), activation: None, init: None, dropout: None };
+        let mut kept = Vec::new();
+        for attr in field.attrs.drain(..) {
+            if parse_attr(&attr, &mut parsed)? {
+                kept.push(attr);
+            }
+        }
+        field.attrs = kept;
+        fields.push(parsed);
+    }
+
+    let last = fields.last().unwrap();
+    if let (true, Some(ty)) = (args.softmax, &last.activation) {
+        let span = ty.as_ref().map_or(last.ident.span(), Spanned::span);
+        return Err(syn::Error::new(span, "the softmax output layer should have no activation"));
+    }
+
+    if !fields.iter().any(|f| uses_scalar(f.ty.to_token_stream())) {
+        let msg = "field types should take the scalar type `T`, e.g. `Layer<3, 4, T>`";
+        return Err(syn::Error::new_spanned(&fields[0].ty, msg));
+    }
+    if !strct.generics.type_params().any(|p| p.ident == "T") {
+        strct.generics.params.push(parse_quote!(T = f64));
+    }
+
+    Ok(Declared { strct, fields, input, output, softmax: args.softmax })
+}
+
+/// whether `tokens` has the ident `T`
+fn uses_scalar(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|t| match t {
+        TokenTree::Ident(ident) => ident == "T",
+        TokenTree::Group(group) => uses_scalar(group.stream()),
+        _ => false,
+    })
+}
+
+/// take the settings of `attr` to `field`, returns true if `attr` is not one of them
+fn parse_attr(attr: &Attribute, field: &mut Field) -> syn::Result<bool> {
+    if attr.path.is_ident("activation") {
+        field.activation = Some(match attr.parse_args::<Type>()? {
+            Type::Infer(_) => None,
+            ty => Some(ty),
+        });
+    } else if attr.path.is_ident("init") {
+        field.init = Some(attr.parse_args::<Ident>()
+            .map_err(|e| syn::Error::new(e.span(), "expected a variant of `Init`"))?);
+    } else if attr.path.is_ident("dropout") {
+        field.dropout = attr.parse_args::<DropRate>()?.0;
+    } else {
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// impl and where generics of the impls, with `F` prepended if `activation`
+fn impl_generics(declared: &Declared, activation: bool) -> (TokenStream, TokenStream) {
+    let mut generics = declared.strct.generics.clone();
+    if activation {
+        generics.params.insert(0, parse_quote!(F));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    // no bounds on the field types, which would hide the `Input` and `Output` of their impls
+    let activation = if activation { quote!(F: ActivitionFunc,) } else { TokenStream::new() };
+    let where_clause = quote! {
+        where
+            #activation
+            T: Real,
+            #predicates
+    };
+    (quote!(#impl_generics), where_clause)
+}
+
+fn impl_layers(declared: &Declared) -> TokenStream {
+    let name = &declared.strct.ident;
+    let calc_name = format_ident!("{}Cal", name);
+    let (_, type_generics, _) = declared.strct.generics.split_for_impl();
+    let (impl_generics, where_clause) = impl_generics(declared, true);
+    let calc = quote! {#calc_name #type_generics};
+    let (input, output) = (&declared.input, &declared.output);
+
+    let [forward, forward_train, test] = impl_forward(declared);
+    let backward_into = impl_backward_into(declared);
+    let update = impl_update(declared);
+    let proba = if declared.softmax {
+        quote! {
+            const SOFTMAX: bool = true;
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    let mut impletation = quote! {
+        impl #impl_generics Layers<F, #calc, #input, #output, T> for #name #type_generics
+        #where_clause
+        {
+            fn forward(&self, item: &SVector<T, #input>) -> #calc {
+                #forward
+            }
+            fn forward_train<R: Rng + ?Sized>(&self, item: &SVector<T, #input>, rng: &mut R) -> #calc {
+                #forward_train
+            }
+            fn backward(&self, input: &SVector<T, #input>, pE_pOut: SVector<T, #output>, calc: #calc) -> Self {
+                let mut grad = Self::default();
+                <Self as Layers<F, #calc, #input, #output, T>>::backward_into(self, &mut grad, input, pE_pOut, calc);
+                grad
+            }
+            fn backward_into(&self, grad: &mut Self, input: &SVector<T, #input>, pE_pOut: SVector<T, #output>, calc: #calc) {
+                #backward_into
+            }
+            fn update<Opt: Optimizer>(
+                &mut self,
+                rate: f64,
+                optimizer: &Opt,
+                moments: &mut Moments<Self>,
+                regularization: &Regularization,
+                gradients: impl IntoIterator<Item = Self>
+            ) {
+                #update
+            }
+            fn test(&self, item: &SVector<T, #input>) -> SVector<T, #output> {
+                #test
+                z
+            }
+            #proba
+        }
+    };
+
+    if declared.softmax {
+        impletation.extend(quote! {
+            impl #impl_generics SoftmaxLayers<F, #calc, #input, #output, T> for #name #type_generics
+            #where_clause
+            {}
+        });
+    }
+    impletation.extend(gen_calc(declared, &calc_name));
+    impletation
+}
+
+fn impl_forward(declared: &Declared) -> [TokenStream; 3] {
+    let mut forward = TokenStream::new();
+    let mut forward_train = TokenStream::new();
+    let mut test = TokenStream::new();
+    let mut calc_fields = TokenStream::new();
+
+    for (i, field) in declared.fields.iter().enumerate() {
+        let cur = i + 1;
+        let act = field.activation();
+        let layer = &field.ident;
+        let a_pre = if cur == 1 { quote!(item) } else {
+            let ident = format_ident!("a_{}", cur - 1);
+            quote!(&#ident)
+        };
+        let z_pre = if cur == 1 { quote!(item) } else { quote!(&z) };
+        let (z, a, c) = (format_ident!("z_{}", cur), format_ident!("a_{}", cur), format_ident!("c_{}", cur));
+
+        forward.extend(quote! {
+            let (#z, #c) = self.#layer.forward(#a_pre);
+            let #a = #z.map(#act::f);
+        });
+        forward_train.extend(quote! {
+            let (#z, #c) = self.#layer.forward_train(#a_pre, rng);
+            let #a = #z.map(#act::f);
+        });
+        test.extend(quote! {
+            let mut z = self.#layer.test(#z_pre);
+            z.iter_mut().for_each(|z| *z = #act::f(*z));
+        });
+        calc_fields.extend(quote! {
+            #z, #a, #c,
+        });
+
+        if let Some(rate) = &field.dropout {
+            let m = format_ident!("m_{}", cur);
+            forward.extend(quote! {
+                let #m = #a.map(|_| T::one());
+            });
+            forward_train.extend(quote! {
+                let #m = dropout_mask(#rate, rng);
+                let #a = #a.component_mul(&#m);
+            });
+            calc_fields.extend(quote! {
+                #m,
+            });
+        }
+    }
+
+    let calc_name = format_ident!("{}Cal", declared.strct.ident);
+    let forward = quote! {
+        #forward
+        #calc_name {
+            #calc_fields
+        }
+    };
+    let forward_train = quote! {
+        #forward_train
+        #calc_name {
+            #calc_fields
+        }
+    };
+    [forward, forward_train, test]
+}
+
+/// gradient of the output of each layer through its activation, then through the layer
+/// by `Module::backward_into`, which adds the gradient of its parameters to `grad`
+fn impl_backward_into(declared: &Declared) -> TokenStream {
+    let mut backward = TokenStream::new();
+    let mut k = quote! {pE_pOut};
+
+    for (i, field) in declared.fields.iter().enumerate().rev() {
+        let cur = i + 1;
+        let act = field.activation();
+        let layer = &field.ident;
+        let (z, a, c) = (format_ident!("z_{}", cur), format_ident!("a_{}", cur), format_ident!("c_{}", cur));
+        let a_pre = if cur == 1 { quote!(input) } else {
+            let ident = format_ident!("a_{}", cur - 1);
+            quote!(&calc.#ident)
+        };
+        backward.extend(match &field.dropout {
+            Some(_) => {
+                let m = format_ident!("m_{}", cur);
+                quote! {
+                    let delta = calc.#z.zip_zip_map(&calc.#m, &#k, |z, m, k| { k * m * #act::d(z, #act::f(z)) });
+                }
+            }
+            None => quote! {
+                let delta = calc.#a.zip_zip_map(&calc.#z, &#k, |y, z, k| { k * #act::d(z, y) });
+            },
+        });
+        let back = quote! {
+            self.#layer.backward_into(&mut grad.#layer, #a_pre, &calc.#c, &delta)
+        };
+        backward.extend(if cur == 1 { quote!(#back;) } else { quote!(let k = #back;) });
+        k = quote! {k};
+    }
+    backward
+}
+
+/// average `gradients` through `Params`, then penalize and apply the gradient of each layer
+fn impl_update(declared: &Declared) -> TokenStream {
+    let mut update = TokenStream::new();
+    for (i, field) in declared.fields.iter().enumerate() {
+        let layer = &field.ident;
+        let i = i + 1;
+        update.extend(quote! {
+            f.#layer.penalize(&self.#layer, regularization.layer(#i), regularization.include_bias);
+            self.#layer.optimize(optimizer, t, rate, &f.#layer, &mut moments.m.#layer, &mut moments.v.#layer);
+        });
+    }
+    quote! {
+        let mut iter = gradients.into_iter();
+        let mut f = iter.next().unwrap();
+        let mut len = 1;
+
+        for g in iter {
+            len += 1;
+            for ((fw, fb), (gw, gb)) in f.params_mut().into_iter().zip(g.params()) {
+                fw.iter_mut().chain(fb.iter_mut()).zip(gw.iter().chain(gb)).for_each(|(f, &g)| *f += g);
+            }
+        }
+        let len = real::<T>(len as f64);
+        for (w, b) in f.params_mut() {
+            w.iter_mut().chain(b.iter_mut()).for_each(|f| *f /= len);
+        }
+        let t = moments.next_step();
+        #update
+    }
+}
+
+/// impl layers constructors with random, zero init params and `cast`
+fn impl_random(declared: &Declared) -> TokenStream {
+    let name = &declared.strct.ident;
+    let (_, type_generics, _) = declared.strct.generics.split_for_impl();
+    let (impl_generics, where_clause) = impl_generics(declared, false);
+
+    let mut random_fields = TokenStream::new();
+    let mut default_fields = TokenStream::new();
+    for field in &declared.fields {
+        let (f, ty) = (&field.ident, &field.ty);
+        random_fields.extend(match &field.init {
+            Some(init) => quote! { #f: <#ty as Module<T>>::init_with(Init::#init, rng), },
+            None => quote! { #f: <#ty as Module<T>>::random_with(rng), },
+        });
+        default_fields.extend(quote! { #f: Default::default(), });
+    }
+    let cast_generics = declared.strct.generics.params.iter().map(|p| match p {
+        GenericParam::Type(t) if t.ident == "T" => quote!(U),
+        GenericParam::Type(t) => {
+            let ident = &t.ident;
+            quote!(#ident)
+        }
+        GenericParam::Const(c) => {
+            let ident = &c.ident;
+            quote!(#ident)
+        }
+        GenericParam::Lifetime(l) => {
+            let lifetime = &l.lifetime;
+            quote!(#lifetime)
+        }
+    }).collect::<Vec<_>>();
+
+    quote! {
+        impl #impl_generics #name #type_generics
+        #where_clause
+        {
+            pub fn random() -> Self {
+                Self::random_with(&mut StdRng::from_entropy())
+            }
+
+            pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+                Self {
+                    #random_fields
+                }
+            }
+
+            /// convert parameters to scalar type `U`, e.g. `f32` for deployment
+            pub fn cast<U: Real>(&self) -> #name<#(#cast_generics),*>
+            where
+                #name<#(#cast_generics),*>: Params<U> + Default
+            {
+                let mut cast = #name::default();
+                for ((cw, cb), (w, b)) in Params::<U>::params_mut(&mut cast).into_iter().zip(self.params()) {
+                    cw.iter_mut().chain(cb.iter_mut()).zip(w.iter().chain(b)).for_each(|(c, &v)| *c = real(to_f64(v)));
+                }
+                cast
+            }
+        }
+
+        impl #impl_generics Default for #name #type_generics
+        #where_clause
+        {
+            fn default() -> Self {
+                Self {
+                    #default_fields
+                }
+            }
+        }
+    }
+}
+
+/// the `...Cal` struct keeping the output `z_i` of each layer, its activation `a_i`,
+/// the cache `c_i` of the layer and the dropout mask `m_i`
+fn gen_calc(declared: &Declared, calc_name: &Ident) -> TokenStream {
+    let generics = &declared.strct.generics;
+    let (_, type_generics, _) = generics.split_for_impl();
+    let (impl_generics, where_clause) = impl_generics(declared, false);
+
+    let mut fields = TokenStream::new();
+    for (i, field) in declared.fields.iter().enumerate() {
+        let cur = i + 1;
+        let ty = &field.ty;
+        let (z, a, c) = (format_ident!("z_{}", cur), format_ident!("a_{}", cur), format_ident!("c_{}", cur));
+        fields.extend(quote! {
+            pub #z: <#ty as Module<T>>::Output,
+            pub #a: <#ty as Module<T>>::Output,
+            pub #c: <#ty as Module<T>>::Cache,
+        });
+        if field.dropout.is_some() {
+            let m = format_ident!("m_{}", cur);
+            fields.extend(quote! {
+                pub #m: <#ty as Module<T>>::Output,
+            });
+        }
+    }
+    let out = format_ident!("a_{}", declared.fields.len());
+    let output = &declared.output;
+
+    quote! {
+        #[derive(Clone)]
+        struct #calc_name #generics
+        #where_clause
+        {
+            #fields
+        }
+
+        impl #impl_generics Calculation<#output, T> for #calc_name #type_generics
+        #where_clause
+        {
+            fn out(&self) -> &SVector<T, #output> {
+                &self.#out
+            }
+        }
+    }
+}
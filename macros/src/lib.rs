@@ -1,83 +1,331 @@
 extern crate proc_macro;
 
+mod declared;
+
 use proc_macro2::{self, Ident, TokenStream};
 
 use quote::{format_ident, quote};
-use syn::{ConstParam, Fields, Generics, ImplGenerics, ItemStruct, TypeGenerics, parse2, parse_macro_input, parse_quote};
+use syn::{Expr, Fields, ImplGenerics, ItemStruct, LitFloat, LitInt, Token, Type, TypeGenerics, parenthesized, parse::{Parse, ParseStream}, parse2, parse_macro_input, parse_quote, punctuated::Punctuated};
 
 /// Derive [`Layers`] with given number of layers for the aimed struct, assuming it is `T`.
 /// 
 /// A [`Calculation`] impl will also be generated, which has generics same as `T` .
 /// 
 /// Besides the [`Layers`] trait, the struct `T` will also implement a function
-/// `random()` to create an instance with all parameters random value, as well as
-/// the `Default` trait to create an instance with all parameters zero value.
+/// `random()` to create an instance with all parameters random value, `random_with(rng)`
+/// doing the same with a given `Rng` for reproducible initialization, as well as
+/// `Clone` and the `Default` trait to create an instance with all parameters zero value,
+/// and `Params` to access the parameters of all layers, e.g. for saving.
 /// 
 /// The generated struct will have `layer_count + 1` const generics,
-/// for the first generic represents the input layer.
+/// for the first generic represents the input layer, followed by the scalar type `T = f64`.
+/// `cast::<U>()` converts an instance to another scalar type, e.g. `f32`.
 /// 
-/// # Example
+/// # Activation
+/// 
+/// By default every layer uses the activation function `F` of the [`Layers`] impl,
+/// which is the one given in `Config`. An `activation(..)` argument with one entry
+/// per layer overrides it, where `_` keeps `F` for that layer:
+/// 
+/// ```ignore
+/// #[derive_layers(3, activation(Tanh, _, Identity))]
+/// struct EmampleLayers{}
+/// ```
+/// 
+/// `backward` passes both `z_i` and `a_i` to `ActivitionFunc::d`, so activations whose
+/// derivative needs the input, like `GELU`, work as well as those using the output.
+/// 
+/// # Initialization
+/// 
+/// `random()` fills all parameters uniformly in `[0, 1)` by default. An `init(..)` argument
+/// with a variant of `Init` per layer initializes weights by that scheme and biases with zero,
+/// where `_` keeps the default for that layer:
+/// 
+/// ```ignore
+/// #[derive_layers(3, activation(Tanh, Tanh, _), init(XavierNormal, XavierNormal, XavierUniform))]
+/// struct EmampleLayers{}
+/// ```
+/// 
+/// # Dropout
+/// 
+/// A `dropout(..)` argument with a rate in `[0, 1)` per layer drops outputs of that layer
+/// with the rate in `forward_train`, which is used by the trainer, where `_` means no dropout.
+/// The mask `m_i` of layer `i` is kept in the generated `...Cal` for `backward`.
+/// Kept outputs are scaled by `1 / (1 - rate)`, so `forward` and `test` just skip dropout:
+/// 
+/// ```ignore
+/// #[derive_layers(3, dropout(0.5, 0.2, _))]
+/// struct EmampleLayers{}
+/// ```
+/// 
+/// # Softmax
+/// 
+/// With the `softmax` argument the last layer has no activation and outputs logits,
+/// which should be trained with `CrossEntroy`, whose gradient is the fused gradient of
+/// softmax and cross entropy. `Network::cfg` rejects `CrossEntroy` for layers without
+/// `softmax` at compile time. The layers then implement `SoftmaxLayers`, whose
+/// `predict_proba` applies softmax to the logits:
+/// 
+/// ```ignore
+/// #[derive_layers(3, activation(Tanh, Tanh, _), softmax)]
+/// struct EmampleLayers{}
+/// ```
+/// 
+/// # Declared fields
 /// 
+/// Instead of a layer count, a struct with fields declares its own layers, each of a type
+/// implementing `Module` like `Layer`, the layers in `conv`, `LayerNorm` in `norm` or
+/// `PReLU` in `func`, chained in the order they are declared. `input(..)` and `output(..)` give the sizes of the network, field
+/// types take the scalar type `T`, which is appended to the generics of the struct:
+/// 
+/// ```ignore
+/// #[derive_layers(input(64), output(3), softmax)]
+/// struct Cnn {
+///     #[activation(ReLU)]
+///     #[init(HeNormal)]
+///     conv: Conv2d<1, 4, 8, 8, 3, 1, 1, 64, 256, T>,
+///     pool: MaxPool2d<4, 8, 8, 2, 2, 256, 64, T>,
+///     flat: Flatten<64, T>,
+///     #[activation(_)]
+///     #[dropout(0.5)]
+///     hidden: Layer<16, 64, T>,
+///     out: Layer<3, 16, T>,
+/// }
 /// ```
+/// 
+/// Settings are attributes of the fields: a field without `#[activation(..)]` has no
+/// activation, since pooling and flatten only move their input, `_` is `F` of the impl.
+/// `#[init(..)]` goes to `Module::init_with` and `#[dropout(..)]` works as above.
+/// Besides `z_i`, `a_i` and `m_i`, the `...Cal` keeps the `Module::Cache` of each layer
+/// as `c_i`, `backward` goes through `Module::backward_into` of each layer, and
+/// penalties of `Regularization::layer(i)` apply to field `i` counting from 1, in the
+/// gradient as in the reported loss, which finds the field by `Params::layers`.
+/// 
+/// Declared layers keep the default `Layers::backprop_batch_into`, which goes item by item,
+/// the matrix products over whole batches are only generated for a layer count.
+/// 
+/// # Example
+/// 
+/// ```ignore
 /// #[derive_layers(3)]
 /// struct EmampleLayers{}
 /// ```
 /// 
 /// Above will be generated into:
 /// 
-/// ```
+/// ```ignore
 /// struct EmampleLayers<
 ///     const L0: usize,
 ///     const L1: usize,
 ///     const L2: usize,
 ///     const L3: usize,
+///     T = f64,
 /// > {
-///     pub layer_1: Layer<L1,L0>,
-///     pub layer_2: Layer<L2,L1>,
-///     pub layer_3: Layer<L3,L2>,
+///     pub layer_1: Layer<L1,L0,T>,
+///     pub layer_2: Layer<L2,L1,T>,
+///     pub layer_3: Layer<L3,L2,T>,
 /// }
 /// ```
 /// 
-/// where `EmampleLayers` impl `Layers<EmampleLayersCal<L0,L1,L2,L3>, L0>`.
+/// where `EmampleLayers` impl `Layers<F, EmampleLayersCal<L0,L1,L2,L3,T>, L0, L3, T>`.
 /// 
-/// so call `forward` on `EmampleLayers` will produce a `EmampleLayersCal<L0,L1,L2,L3>`,
-/// which impl `Calculation<EmampleLayers<L0,L1,L2,L3>>`:
+/// so call `forward` on `EmampleLayers` will produce a `EmampleLayersCal<L0,L1,L2,L3,T>`,
+/// which impl `Calculation<L3, T>`:
 /// 
-/// ```
+/// ```ignore
 /// struct EmampleLayersCal<
 ///     const L0: usize,
 ///     const L1: usize,
 ///     const L2: usize,
 ///     const L3: usize,
+///     T = f64,
 /// >{
-///     pub z_1: SVector<f64,L1>,
-///     pub a_1: SVector<f64,L1>,
-///     pub z_2: SVector<f64,L2>,
-///     pub a_2: SVector<f64,L2>,
-///     pub z_3: SVector<f64,L3>,
-///     pub a_3: SVector<f64,L3>,
+///     pub z_1: SVector<T,L1>,
+///     pub a_1: SVector<T,L1>,
+///     pub z_2: SVector<T,L2>,
+///     pub a_2: SVector<T,L2>,
+///     pub z_3: SVector<T,L3>,
+///     pub a_3: SVector<T,L3>,
 /// }
 /// ```
 /// 
 #[proc_macro_attribute]
-pub fn derive_layers(layer_count: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+pub fn derive_layers(args: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(item as syn::ItemStruct);
-    let layer_count = parse_macro_input!(
-        layer_count as syn::LitInt
-    ).base10_parse::<usize>().unwrap();
+    let args = parse_macro_input!(args as Args);
 
-    let strct = gen_struct(input, layer_count);
-    let impl_layers = impl_layers(&strct);
-    let impl_random = impl_random(&strct);
+    if !input.fields.is_empty() {
+        return declared::derive(input, args)
+            .unwrap_or_else(|e| e.to_compile_error())
+            .into();
+    }
+    if args.layer_count == 0 {
+        let msg = "expected the layer count, or fields of layers with `input(..)` and `output(..)`";
+        return syn::Error::new(proc_macro2::Span::call_site(), msg).to_compile_error().into();
+    }
+    if args.input.is_some() || args.output.is_some() {
+        let msg = "`input(..)` and `output(..)` are only for declared fields";
+        return syn::Error::new(proc_macro2::Span::call_site(), msg).to_compile_error().into();
+    }
+
+    let strct = gen_struct(input, args.layer_count);
+    let impl_layers = impl_layers(&strct, &args);
+    let impl_random = impl_random(&strct, &args);
+    let impl_params = impl_params(&strct);
     
     (quote! {
-        #[derive(Default)]
+        #[derive(Clone)]
         #strct
         #impl_random
         #impl_layers
+        #impl_params
     }).into()
 }
 
+/// arguments of [`derive_layers`]: `layer_count` followed by optional settings,
+/// or `input(..)` and `output(..)` of declared fields.
+struct Args {
+    /// zero if not given, for declared fields
+    layer_count: usize,
+    /// input size of declared fields
+    input: Option<Expr>,
+    /// output size of declared fields
+    output: Option<Expr>,
+    /// activation type of each layer, `F` of the impl is used if `None`
+    activations: Vec<Option<Type>>,
+    /// `Init` variant of each layer used by `random()`, `Layer::random_with` is used if `None`
+    inits: Vec<Option<Ident>>,
+    /// dropout rate of the output of each layer, no dropout if `None`
+    dropouts: Vec<Option<LitFloat>>,
+    /// the last layer outputs logits of softmax
+    softmax: bool,
+}
+
+impl Args {
+    /// tokens of the activation function used by layer `i`, which starts from 1
+    fn activation(&self, i: usize) -> TokenStream {
+        if self.softmax && i == self.layer_count {
+            return quote! {<Identity as ActivitionFunc>};
+        }
+        match &self.activations[i - 1] {
+            Some(ty) => quote! {<#ty as ActivitionFunc>},
+            None => quote! {<F as ActivitionFunc>},
+        }
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut layer_count = 0;
+        if input.peek(LitInt) {
+            let count: LitInt = input.parse()?;
+            layer_count = count.base10_parse::<usize>()?;
+            if layer_count == 0 {
+                return Err(syn::Error::new(count.span(), "at least one layer is required"));
+            }
+        }
+        let mut activations = vec![None; layer_count];
+        let mut inits = vec![None; layer_count];
+        let mut dropouts = vec![None; layer_count];
+        let mut softmax = false;
+        let mut sizes = (None, None);
+
+        let mut first = layer_count == 0;
+        while !input.is_empty() {
+            if !first {
+                input.parse::<Token![,]>()?;
+            }
+            first = false;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key == "softmax" {
+                softmax = true;
+                continue;
+            }
+            let content;
+            parenthesized!(content in input);
+            let per_layer = ["activation", "init", "dropout"].iter().any(|k| key == k);
+            if per_layer && layer_count == 0 {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("`{0}(..)` needs the layer count, declared fields take `#[{0}(..)]` instead", key)
+                ));
+            }
+            match key.to_string().as_str() {
+                "input" => sizes.0 = Some(content.parse()?),
+                "output" => sizes.1 = Some(content.parse()?),
+                "activation" => activations = parse_per_layer(&key, &content, layer_count)?,
+                "init" => {
+                    inits = parse_per_layer(&key, &content, layer_count)?
+                        .into_iter()
+                        .map(|ty| match ty {
+                            Some(Type::Path(p)) if p.path.get_ident().is_some() => Ok(p.path.get_ident().cloned()),
+                            Some(ty) => Err(syn::Error::new_spanned(ty, "expected a variant of `Init` or `_`")),
+                            None => Ok(None),
+                        })
+                        .collect::<syn::Result<_>>()?;
+                }
+                "dropout" => dropouts = parse_dropouts(&key, &content, layer_count)?,
+                _ => return Err(syn::Error::new(key.span(), format!("unknown argument `{}`", key))),
+            }
+        }
+
+        if let (true, Some(Some(ty))) = (softmax, activations.last()) {
+            return Err(syn::Error::new_spanned(ty, "activation of the softmax output layer should be `_`"));
+        }
+
+        let (input, output) = sizes;
+        Ok(Args { layer_count, input, output, activations, inits, dropouts, softmax })
+    }
+}
+
+/// parse `content` as one type per layer, where `_` is `None`
+fn parse_per_layer(key: &Ident, content: ParseStream, layer_count: usize) -> syn::Result<Vec<Option<Type>>> {
+    let types = Punctuated::<Type, Token![,]>::parse_terminated(content)?;
+    if types.len() != layer_count {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("expected {} values of `{}`, found {}", layer_count, key, types.len())
+        ));
+    }
+    Ok(types.into_iter().map(|ty| match ty {
+        Type::Infer(_) => None,
+        ty => Some(ty),
+    }).collect())
+}
+
+/// parse `content` as one dropout rate in `[0, 1)` per layer, where `_` is `None`
+fn parse_dropouts(key: &Ident, content: ParseStream, layer_count: usize) -> syn::Result<Vec<Option<LitFloat>>> {
+    let rates = Punctuated::<DropRate, Token![,]>::parse_terminated(content)?;
+    if rates.len() != layer_count {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("expected {} values of `{}`, found {}", layer_count, key, rates.len())
+        ));
+    }
+    Ok(rates.into_iter().map(|r| r.0).collect())
+}
+
+/// a dropout rate or `_`
+struct DropRate(Option<LitFloat>);
+
+impl Parse for DropRate {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            return Ok(DropRate(None));
+        }
+        let rate: LitFloat = input.parse()?;
+        let value = rate.base10_parse::<f64>()?;
+        if !(0. ..1.).contains(&value) {
+            return Err(syn::Error::new(rate.span(), "dropout rate should be in [0, 1)"));
+        }
+        Ok(DropRate(Some(rate)))
+    }
+}
+
 /// generate generics params and fields.
 fn gen_struct(strct: ItemStruct, layer_count: usize) -> ItemStruct {
     let mut fields = TokenStream::new();  
@@ -87,10 +335,11 @@ fn gen_struct(strct: ItemStruct, layer_count: usize) -> ItemStruct {
     for i in 1..=layer_count {
         let layer = format_ident!("layer_{}", i);
         let cur = format_ident!("L{}", i);
-        fields.extend(quote! {pub #layer: Layer<#cur, #pre>,});
+        fields.extend(quote! {pub #layer: Layer<#cur, #pre, T>,});
         generics.extend(quote! {const #cur: usize, });
         pre = cur;
     }
+    generics.extend(quote! {T = f64});
     
     let vis = &strct.vis;
     let name = &strct.ident;
@@ -104,7 +353,7 @@ fn gen_struct(strct: ItemStruct, layer_count: usize) -> ItemStruct {
     parse2(strct).unwrap()
 }
 
-fn impl_layers(strct: &ItemStruct) -> TokenStream {
+fn impl_layers(strct: &ItemStruct, args: &Args) -> TokenStream {
     let name = &strct.ident;
     let calc_name = format_ident!("{}Cal", name);
     let generics = &strct.generics;
@@ -119,67 +368,127 @@ fn impl_layers(strct: &ItemStruct) -> TokenStream {
     for c in generics.const_params() {
         impl_generics.extend(quote!{#c,});
     }
-
-    let [impl_forward, impl_test] = impl_forward(&calc_name, generics.const_params());
-    let impl_backward = impl_backward(generics);
-    let impl_update = impl_update(generics);
+    impl_generics.extend(quote! {T});
+
+    let [impl_forward, impl_forward_train, impl_test] = impl_forward(&calc_name, args);
+    let impl_backward = impl_backward(args);
+    let impl_update = impl_update(args);
+    let impl_backward_into = impl_backward_into(args);
+    let impl_backprop_batch = impl_backprop_batch(args);
+    let impl_proba = if args.softmax {
+        quote! {
+            const SOFTMAX: bool = true;
+        }
+    } else {
+        TokenStream::new()
+    };
 
     let mut impletation = quote!{
-        impl<#impl_generics> Layers<F, #calc_name #type_generics, #input_size, #output_size> for #name #type_generics
+        impl<#impl_generics> Layers<F, #calc_name #type_generics, #input_size, #output_size, T> for #name #type_generics
         where
-            F: ActivitionFunc
+            F: ActivitionFunc,
+            T: Real
         {
-            fn forward(&self, item: &SVector<f64,#input_size>) -> #calc_name #type_generics {
+            fn forward(&self, item: &SVector<T,#input_size>) -> #calc_name #type_generics {
                 #impl_forward
             }
-            fn backward(&self, input: &SVector<f64,#input_size>, pE_pOut: SVector<f64, #output_size>, calc: #calc_name #type_generics) 
+            fn forward_train<R: Rng + ?Sized>(&self, item: &SVector<T,#input_size>, rng: &mut R) -> #calc_name #type_generics {
+                #impl_forward_train
+            }
+            fn backward(&self, input: &SVector<T,#input_size>, pE_pOut: SVector<T, #output_size>, calc: #calc_name #type_generics) 
                 -> Self {
                 #impl_backward
             }
-            fn update(&mut self, rate: f64, gradients: impl IntoIterator<Item = Self>) {
+            fn backward_into(&self, grad: &mut Self, input: &SVector<T,#input_size>, pE_pOut: SVector<T, #output_size>, calc: #calc_name #type_generics) {
+                #impl_backward_into
+            }
+            fn backprop_batch_into(
+                &self,
+                grad: &mut Self,
+                inputs: &Batch<T, #input_size>,
+                seeds: &[u64],
+                outputs: &mut Batch<T, #output_size>,
+                d_out: impl FnOnce(&Batch<T, #output_size>, &mut Batch<T, #output_size>),
+                buffers: &mut Buffers<T>
+            ) {
+                #impl_backprop_batch
+            }
+            fn update<Opt: Optimizer>(
+                &mut self,
+                rate: f64,
+                optimizer: &Opt,
+                moments: &mut Moments<Self>,
+                regularization: &Regularization,
+                gradients: impl IntoIterator<Item = Self>
+            ) {
                 #impl_update
             }
-            fn test(&self, item: &SVector<f64, #input_size>) -> SVector<f64, #output_size> {
+            fn test(&self, item: &SVector<T, #input_size>) -> SVector<T, #output_size> {
                 #impl_test
                 z
             }
+            #impl_proba
         }
     };
 
-    impletation.extend(gen_calc(&calc_name, &strct));
+    if args.softmax {
+        impletation.extend(quote! {
+            impl<#impl_generics> SoftmaxLayers<F, #calc_name #type_generics, #input_size, #output_size, T> for #name #type_generics
+            where
+                F: ActivitionFunc,
+                T: Real
+            {}
+        });
+    }
+    impletation.extend(gen_calc(&calc_name, strct, args));
 
     impletation
 }
 
-fn impl_forward<'a>(calc_name: &Ident, iter: impl Iterator<Item = &'a ConstParam>) -> [TokenStream;2] {
-    let mut impl_forward = quote! {
-        let z_1 = self.layer_1.calc(item);
-        let a_1 = z_1.map(F::f);
-    };
-    let mut impl_test = quote! {
-        let mut z = self.layer_1.calc(item);
-        z.iter_mut().for_each(|z| *z = F::f(*z));
-    };
-    let mut calc_fields = quote! {z_1, a_1,};
-
-    // impl forward & update
-    for (cur, _) in iter.map(|c| &c.ident).enumerate().skip(2) {
-        let pre = cur - 1;
-        let a_pre = format_ident!("a_{}", pre);
+fn impl_forward(calc_name: &Ident, args: &Args) -> [TokenStream;3] {
+    let mut impl_forward = TokenStream::new();
+    let mut impl_forward_train = TokenStream::new();
+    let mut impl_test = TokenStream::new();
+    let mut calc_fields = TokenStream::new();
+
+    for cur in 1..=args.layer_count {
+        let act = args.activation(cur);
+        let a_pre = if cur == 1 { quote!(item) } else {
+            let ident = format_ident!("a_{}", cur - 1);
+            quote!(&#ident)
+        };
+        let z_pre = if cur == 1 { quote!(item) } else { quote!(&z) };
         let a = format_ident!("a_{}", cur);
         let z = format_ident!("z_{}", cur);
         let layer = format_ident!("layer_{}", cur);
-        impl_forward.extend(quote! {
-            let #z = self.#layer.calc(&#a_pre);
-            let #a = #z.map(F::f);
-        });
+        let calc = quote! {
+            let #z = self.#layer.calc(#a_pre);
+            let #a = #z.map(#act::f);
+        };
+        impl_forward.extend(calc.clone());
+        impl_forward_train.extend(calc);
         calc_fields.extend(quote! {
             #z, #a,
         });
         impl_test.extend(quote! {
-            let mut z = self.#layer.calc(&z);
-            z.iter_mut().for_each(|z| *z = F::f(*z));
+            let mut z = self.#layer.calc(#z_pre);
+            z.iter_mut().for_each(|z| *z = #act::f(*z));
         });
+
+        // outputs are scaled by the mask in training, so nothing to do in test
+        if let Some(rate) = &args.dropouts[cur - 1] {
+            let m = format_ident!("m_{}", cur);
+            impl_forward.extend(quote! {
+                let #m = SVector::repeat(T::one());
+            });
+            impl_forward_train.extend(quote! {
+                let #m = dropout_mask(#rate, rng);
+                let #a = #a.component_mul(&#m);
+            });
+            calc_fields.extend(quote! {
+                #m,
+            });
+        }
     }
     let impl_forward = quote! {
         #impl_forward
@@ -187,27 +496,43 @@ fn impl_forward<'a>(calc_name: &Ident, iter: impl Iterator<Item = &'a ConstParam
             #calc_fields
         }
     };
-    [impl_forward, impl_test]
+    let impl_forward_train = quote! {
+        #impl_forward_train
+        #calc_name {
+            #calc_fields
+        }
+    };
+    [impl_forward, impl_forward_train, impl_test]
 }
 
-fn impl_backward(generics: &Generics) -> TokenStream {
-
-    let iter = generics.params.iter().enumerate().skip(1).rev();
-    // let (last,_) = iter.next().unwrap();
-
+fn impl_backward(args: &Args) -> TokenStream {
     let mut impl_backward = quote! {};
     let mut k = quote! {pE_pOut};
     let mut fields = TokenStream::new();
 
-    for (cur, _) in iter {
+    for cur in (1..=args.layer_count).rev() {
+        let act = args.activation(cur);
         let a = format_ident!("a_{}", cur);
         let a_pre = if cur == 1 { quote!(input) } else {
             let ident = format_ident!("a_{}", cur-1);
             quote! {calc.#ident}
         };
         let layer = format_ident!("layer_{}", cur);
+        let z = format_ident!("z_{}", cur);
+        // `d` gets both the input `z` and the output `y`, so it works for either form
+        let delta = match &args.dropouts[cur - 1] {
+            Some(_) => {
+                let m = format_ident!("m_{}", cur);
+                quote! {
+                    let delta = calc.#z.zip_zip_map(&calc.#m, &(#k), |z, m, k| { k * m * #act::d(z, #act::f(z)) });
+                }
+            }
+            None => quote! {
+                let delta = calc.#a.zip_zip_map(&calc.#z, &(#k), |y, z, k| { k * #act::d(z, y) });
+            },
+        };
         impl_backward.extend(quote! {
-            let delta = calc.#a.zip_map(&(#k), |z, k| { k * F::d_from_y(z) });
+            #delta
             let #layer = Layer {
                 w: delta * #a_pre.transpose(),
                 b: delta,
@@ -225,18 +550,144 @@ fn impl_backward(generics: &Generics) -> TokenStream {
     }
 }
 
-fn impl_update(generics: &Generics) -> TokenStream {
+/// same as `backward`, with the gradient of each layer added to `grad` in place
+fn impl_backward_into(args: &Args) -> TokenStream {
+    let mut impl_backward = quote! {};
+    let mut k = quote! {pE_pOut};
+
+    for cur in (1..=args.layer_count).rev() {
+        let act = args.activation(cur);
+        let a = format_ident!("a_{}", cur);
+        let a_pre = if cur == 1 { quote!(input) } else {
+            let ident = format_ident!("a_{}", cur-1);
+            quote! {&calc.#ident}
+        };
+        let layer = format_ident!("layer_{}", cur);
+        let z = format_ident!("z_{}", cur);
+        let delta = match &args.dropouts[cur - 1] {
+            Some(_) => {
+                let m = format_ident!("m_{}", cur);
+                quote! {
+                    let delta = calc.#z.zip_zip_map(&calc.#m, &(#k), |z, m, k| { k * m * #act::d(z, #act::f(z)) });
+                }
+            }
+            None => quote! {
+                let delta = calc.#a.zip_zip_map(&calc.#z, &(#k), |y, z, k| { k * #act::d(z, y) });
+            },
+        };
+        impl_backward.extend(quote! {
+            #delta
+            grad.#layer.w.ger(T::one(), &delta, #a_pre, T::one());
+            grad.#layer.b += delta;
+        });
+        k = quote! {self.#layer.w.tr_mul(&delta)};
+    }
+    impl_backward
+}
+
+/// same as `forward_train` followed by `backward_into`, with a column per item, so that
+/// each layer is a single pass over the batch, on matrices taken from and given back to `buffers`
+fn impl_backprop_batch(args: &Args) -> TokenStream {
+    let mut forward = TokenStream::new();
+    if args.dropouts.iter().any(Option::is_some) {
+        forward.extend(quote! {
+            buffers.seed_rngs(seeds);
+        });
+    }
+    for cur in 1..=args.layer_count {
+        let act = args.activation(cur);
+        let a_pre = if cur == 1 { quote!(inputs) } else {
+            let ident = format_ident!("a_{}", cur - 1);
+            quote!(&#ident)
+        };
+        let size = format_ident!("L{}", cur);
+        let a = format_ident!("a_{}", cur);
+        let z = format_ident!("z_{}", cur);
+        let layer = format_ident!("layer_{}", cur);
+        forward.extend(quote! {
+            let mut #z = buffers.batch::<#size>(len);
+            self.#layer.calc_batch_to(#a_pre, &mut #z);
+            let mut #a = buffers.batch::<#size>(len);
+            #a.zip_apply(&#z, |a, z| *a = #act::f(z));
+        });
+        if let Some(rate) = &args.dropouts[cur - 1] {
+            let m = format_ident!("m_{}", cur);
+            forward.extend(quote! {
+                let mut #m = buffers.batch::<#size>(len);
+                dropout_mask_batch_to(#rate, buffers.rngs(), &mut #m);
+                #a.component_mul_assign(&#m);
+            });
+        }
+    }
+
+    let out = format_ident!("a_{}", args.layer_count);
+    let out_size = format_ident!("L{}", args.layer_count);
+    let mut backward = quote! {
+        outputs.copy_from(&#out);
+        let mut k = buffers.batch::<#out_size>(len);
+        d_out(outputs, &mut k);
+    };
+    for cur in (1..=args.layer_count).rev() {
+        let act = args.activation(cur);
+        let a = format_ident!("a_{}", cur);
+        let z = format_ident!("z_{}", cur);
+        let a_pre = if cur == 1 { quote!(inputs) } else {
+            let ident = format_ident!("a_{}", cur - 1);
+            quote!(&#ident)
+        };
+        let layer = format_ident!("layer_{}", cur);
+        // `z` is not needed after `delta`, so `delta` takes its place
+        backward.extend(match &args.dropouts[cur - 1] {
+            Some(_) => {
+                let m = format_ident!("m_{}", cur);
+                quote! {
+                    #z.zip_zip_apply(&#m, &k, |z, m, k| *z = k * m * #act::d(*z, #act::f(*z)));
+                    buffers.recycle(#m);
+                }
+            }
+            None => quote! {
+                #z.zip_zip_apply(&#a, &k, |z, y, k| *z = k * #act::d(*z, y));
+            },
+        });
+        backward.extend(quote! {
+            let delta = #z;
+            buffers.recycle(#a);
+            grad.#layer.accumulate_batch(&delta, #a_pre);
+        });
+        if cur > 1 {
+            let pre_size = format_ident!("L{}", cur - 1);
+            backward.extend(quote! {
+                let mut k_pre = buffers.batch::<#pre_size>(len);
+                self.#layer.back_batch_to(&delta, &mut k_pre);
+                buffers.recycle(k);
+                let k = k_pre;
+            });
+        }
+        backward.extend(quote! {
+            buffers.recycle(delta);
+        });
+    }
+
+    quote! {
+        let len = inputs.ncols();
+        #forward
+        #backward
+        buffers.recycle(k);
+    }
+}
+
+fn impl_update(args: &Args) -> TokenStream {
     let mut sum = TokenStream::new();
     let mut update = TokenStream::new();
-    for (i,_) in generics.params.iter().enumerate().skip(1) {
+    for i in 1..=args.layer_count {
         let layer = format_ident!("layer_{}", i);
         sum.extend(quote! {
             f.#layer += g.#layer.as_ref();
         });
         update.extend(quote! {
             f.#layer /= len;
-            f.#layer *= rate;
-            self.#layer -= f.#layer.as_ref();
+            f.#layer.penalize(&self.#layer, regularization.layer(#i), regularization.include_bias);
+            self.#layer.optimize(optimizer, t, rate, &f.#layer, &mut moments.m.#layer, &mut moments.v.#layer);
         });
     }
     quote! {
@@ -247,35 +698,125 @@ fn impl_update(generics: &Generics) -> TokenStream {
             len = i;
             #sum
         }
-        let len = (len + 1) as f64;
+        let len = real::<T>((len + 1) as f64);
+        let t = moments.next_step();
         #update
     }
 }
 
-/// impl layers constructor with random init params
-fn impl_random(strct: &ItemStruct) -> TokenStream {
+/// impl layers constructors with random, zero init params and `cast`
+fn impl_random(strct: &ItemStruct, args: &Args) -> TokenStream {
     let name = &strct.ident;
     let (impl_generics, type_generics, _) = strct.generics.split_for_impl();
 
     let mut random_fields = TokenStream::new();
-    for f in strct.fields.iter().map(|f| f.ident.as_ref().unwrap()) {
-        random_fields.extend(quote! {
-            #f: Layer::random(),
+    let mut cast_fields = TokenStream::new();
+    let mut default_fields = TokenStream::new();
+    let fields = strct.fields.iter().map(|f| f.ident.as_ref().unwrap());
+    for (f, init) in fields.zip(args.inits.iter()) {
+        random_fields.extend(match init {
+            Some(init) => quote! { #f: Layer::init_with(Init::#init, rng), },
+            None => quote! { #f: Layer::random_with(rng), },
         });
+        cast_fields.extend(quote! { #f: self.#f.cast(), });
+        default_fields.extend(quote! { #f: Layer::default(), });
     }
+    let sizes = strct.generics.const_params().map(|c| &c.ident);
 
     quote! {
-        impl #impl_generics #name #type_generics {
+        impl #impl_generics #name #type_generics
+        where
+            T: Real
+        {
             pub fn random() -> Self {
+                Self::random_with(&mut StdRng::from_entropy())
+            }
+
+            pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
                 Self {
                     #random_fields
                 }
             }
+
+            /// convert parameters to scalar type `U`, e.g. `f32` for deployment
+            pub fn cast<U: Real>(&self) -> #name<#(#sizes,)* U> {
+                #name {
+                    #cast_fields
+                }
+            }
+        }
+
+        impl #impl_generics Default for #name #type_generics
+        where
+            T: Real
+        {
+            fn default() -> Self {
+                Self {
+                    #default_fields
+                }
+            }
+        }
+    }
+}
+
+/// impl `Params` by chaining the params of all layers, each entry belongs to its field
+fn impl_params(strct: &ItemStruct) -> TokenStream {
+    let name = &strct.ident;
+    let (impl_generics, type_generics, _) = strct.generics.split_for_impl();
+
+    let mut shapes = TokenStream::new();
+    let mut params = TokenStream::new();
+    let mut params_mut = TokenStream::new();
+    let mut layers = TokenStream::new();
+    for (i, f) in strct.fields.iter().enumerate() {
+        let ident = f.ident.as_ref().unwrap();
+        let ty = &f.ty;
+        let i = i + 1;
+        shapes.extend(quote! {
+            shapes.extend(<#ty as Params<T>>::shapes());
+        });
+        // every entry of a field is of the field, which pooling and flatten have none of
+        layers.extend(quote! {
+            layers.extend(std::iter::repeat(#i).take(<#ty as Params<T>>::shapes().len()));
+        });
+        params.extend(quote! {
+            params.extend(self.#ident.params());
+        });
+        params_mut.extend(quote! {
+            params.extend(self.#ident.params_mut());
+        });
+    }
+
+    quote! {
+        impl #impl_generics Params<T> for #name #type_generics
+        where
+            T: Real
+        {
+            fn shapes() -> Vec<(usize, usize)> {
+                let mut shapes = Vec::new();
+                #shapes
+                shapes
+            }
+            fn params(&self) -> Vec<(&[T], &[T])> {
+                let mut params = Vec::new();
+                #params
+                params
+            }
+            fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+                let mut params = Vec::new();
+                #params_mut
+                params
+            }
+            fn layers() -> Vec<usize> {
+                let mut layers = Vec::new();
+                #layers
+                layers
+            }
         }
     }
 }
 
-fn gen_calc(calc_name: &Ident, layers: &ItemStruct) -> TokenStream {
+fn gen_calc(calc_name: &Ident, layers: &ItemStruct, args: &Args) -> TokenStream {
     let mut fields = TokenStream::new();  
 
     let generics = layers.generics.const_params().skip(1);
@@ -283,16 +824,22 @@ fn gen_calc(calc_name: &Ident, layers: &ItemStruct) -> TokenStream {
         let a = format_ident!("a_{}", i+1);
         let z = format_ident!("z_{}", i+1);
         let len = &const_param.ident;
-        fields.extend((quote! {
-            pub #z: SVector<f64, #len>,
-            pub #a: SVector<f64, #len>,
-        }).into_iter());
+        fields.extend(quote! {
+            pub #z: SVector<T, #len>,
+            pub #a: SVector<T, #len>,
+        });
+        if args.dropouts[i].is_some() {
+            let m = format_ident!("m_{}", i+1);
+            fields.extend(quote! {
+                pub #m: SVector<T, #len>,
+            });
+        }
     }
 
-    let (impl_generics,_,_) = layers.generics.split_for_impl();
+    let generics = &layers.generics;
 
     let calc: ItemStruct = parse_quote!{
-        struct #calc_name #impl_generics {
+        struct #calc_name #generics {
             #fields
         }
     };
@@ -328,8 +875,11 @@ fn impl_calc(calc: &ItemStruct) -> TokenStream {
     quote! {
         #impl_default
 
-        impl #impl_generics Calculation<#E> for #calc_name #type_generics {
-            fn out(&self) -> &SVector<f64, #E> {
+        impl #impl_generics Calculation<#E, T> for #calc_name #type_generics
+        where
+            T: Real
+        {
+            fn out(&self) -> &SVector<T, #E> {
                 &self.#out
             }
         }
@@ -345,11 +895,14 @@ fn impl_calc_default(
     let mut default_fields = TokenStream::new();
 
     for field  in fields.iter().map(|f| f.ident.as_ref().unwrap()) {
-        default_fields.extend(quote! { #field: SVector::repeat(0f64), });
+        default_fields.extend(quote! { #field: SVector::repeat(T::zero()), });
     }
 
     quote! {
-        impl #impl_generics Default for #calc_name #type_generics {
+        impl #impl_generics Default for #calc_name #type_generics
+        where
+            T: Real
+        {
             fn default() -> Self {
                 Self {
                     #default_fields
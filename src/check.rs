@@ -0,0 +1,93 @@
+//! Checking of analytic gradients against finite differences.
+
+use crate::{Item, func::LossFunc, model::*};
+
+/// seed of the dropout rng of the item in `backprop_batch_into`
+const SEED: u64 = 0;
+
+/// Compare the gradients of `backward`, `backward_into` and `backprop_batch_into` on `item`
+/// with central differences of loss `C` on every weight and bias, where `eps` is the step
+/// of the differences.
+///
+/// `backward` and `backward_into` go through `forward`, so they are compared with the loss
+/// of `test`. `backprop_batch_into` goes through `forward_train` with dropout, so it is
+/// compared with the loss of `forward_train` under the same dropout rng.
+///
+/// Returns the max relative error `|a - n| / max(|a| + |n|, 1e-12)` of each layer over the
+/// three passes, where `a` is the analytic and `n` the numeric gradient of a parameter.
+/// Values above about `1e-5` with `f64` usually mean a wrong backward pass.
+///
+/// `_actvt` and `_loss` are only for their types:
+///
+/// ```ignore
+/// let errors = gradient_check(&layers, &item, Tanh, DistanceFunc, 1e-6);
+/// ```
+pub fn gradient_check<A, C, L, Cal, const I: usize, const O: usize, T>(
+    layers: &L,
+    item: &Item<I, O, T>,
+    _actvt: A,
+    _loss: C,
+    eps: f64
+) -> Vec<f64>
+where
+    C: LossFunc,
+    L: Layers<A, Cal, I, O, T> + Params<T> + Clone + Default,
+    Cal: Calculation<O, T>,
+    T: Real
+{
+    let loss = |layers: &L| to_f64(C::f(&item.label, &layers.test(&item.data)));
+    let train_loss = |layers: &L| {
+        let calc = layers.forward_train(&item.data, &mut StdRng::seed_from_u64(SEED));
+        to_f64(C::f(&item.label, &calc.out().to_owned()))
+    };
+
+    let calc = layers.forward(&item.data);
+    let d_out = C::d(&item.label, &calc.out().to_owned());
+    let backward = layers.backward(&item.data, d_out, calc);
+    let mut backward_into = L::default();
+    layers.backward_into(&mut backward_into, &item.data, d_out, layers.forward(&item.data));
+
+    let mut batch = L::default();
+    let inputs = Batch::from_column_slice(item.data.as_slice());
+    let mut outputs = Batch::zeros(1);
+    layers.backprop_batch_into(&mut batch, &inputs, &[SEED], &mut outputs, |out, k| {
+        k.set_column(0, &C::d(&item.label, &out.column(0).into_owned()));
+    }, &mut Buffers::new());
+
+    let mut probe = layers.clone();
+    let mut errors = Vec::new();
+    let grads = backward.params().into_iter().zip(backward_into.params()).zip(batch.params());
+    for (layer, (((w, b), (into_w, into_b)), (batch_w, batch_b))) in grads.enumerate() {
+        let mut max = 0f64;
+        let analytic = w.iter().chain(b)
+            .zip(into_w.iter().chain(into_b))
+            .zip(batch_w.iter().chain(batch_b));
+        for (i, ((&a, &a_into), &a_batch)) in analytic.enumerate() {
+            let origin = *param_mut(&mut probe, layer, i);
+            let mut numeric = |loss: &dyn Fn(&L) -> f64| {
+                let mut loss_at = |delta: f64| {
+                    *param_mut(&mut probe, layer, i) = real(to_f64(origin) + delta);
+                    loss(&probe)
+                };
+                let numeric = (loss_at(eps) - loss_at(-eps)) / (2. * eps);
+                *param_mut(&mut probe, layer, i) = origin;
+                numeric
+            };
+            let (numeric, train_numeric) = (numeric(&loss), numeric(&train_loss));
+
+            for (a, n) in [(a, numeric), (a_into, numeric), (a_batch, train_numeric)] {
+                let a = to_f64(a);
+                max = max.max((a - n).abs() / (a.abs() + n.abs()).max(1e-12));
+            }
+        }
+        errors.push(max);
+    }
+    errors
+}
+
+/// parameter `i` of layer `layer`, counting weights first and then biases
+#[inline]
+fn param_mut<L: Params<T>, T>(layers: &mut L, layer: usize, i: usize) -> &mut T {
+    let (w, b) = layers.params_mut().swap_remove(layer);
+    if i < w.len() { &mut w[i] } else { &mut b[i - w.len()] }
+}
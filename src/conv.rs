@@ -0,0 +1,422 @@
+//! Convolution, pooling and flatten layers, which implement [`Module`].
+//!
+//! An image of `C` channels of `H x W` is an `SVector` of size `C * H * W`, laid out channel
+//! by channel and each channel row by row, a 1D signal of `C` channels of length `L` likewise.
+//! Sizes can not be computed in types, so each layer takes the sizes `IN` and `OUT` of its
+//! flattened input and output after its geometry, which are checked at compile time when
+//! the layer is used. With kernel size `K`, `OUT` is `C_OUT * OH * OW` where
+//! `OH = (H + 2 * PAD - K) / STRIDE + 1` and `OW` likewise.
+//!
+//! They are fields of networks declared with [`derive_layers`](crate::derive_layers):
+//!
+//! ```ignore
+//! #[derive_layers(input(64), output(3), softmax)]
+//! struct Cnn {
+//!     #[activation(ReLU)]
+//!     #[init(HeNormal)]
+//!     conv: Conv2d<1, 4, 8, 8, 3, 1, 1, 64, 256, T>,
+//!     pool: MaxPool2d<4, 8, 8, 2, 2, 256, 64, T>,
+//!     flat: Flatten<64, T>,
+//!     #[init(XavierNormal)]
+//!     out: Layer<3, 64, T>,
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use na::{DMatrix, DVector};
+
+use crate::model::{Init, Module, Params, Real, SVector, real};
+
+/// Geometry of a window sliding over planes of `h x w`, 1D layers have `h = 1`.
+#[derive(Clone, Copy, Debug)]
+struct Window {
+    h: usize,
+    w: usize,
+    kh: usize,
+    kw: usize,
+    stride_h: usize,
+    stride_w: usize,
+    pad_h: usize,
+    pad_w: usize,
+    oh: usize,
+    ow: usize,
+}
+
+impl Window {
+    /// square kernel of size `k` over `h x w`, panics at compile time in a const if it does not fit
+    const fn d2(h: usize, w: usize, k: usize, stride: usize, pad: usize) -> Self {
+        assert!(stride > 0, "stride should be positive");
+        assert!(k > 0 && k <= h + 2 * pad && k <= w + 2 * pad, "kernel should fit in the padded input");
+        Self {
+            h, w,
+            kh: k, kw: k,
+            stride_h: stride, stride_w: stride,
+            pad_h: pad, pad_w: pad,
+            oh: (h + 2 * pad - k) / stride + 1,
+            ow: (w + 2 * pad - k) / stride + 1,
+        }
+    }
+
+    /// kernel of size `k` over length `l`, panics at compile time in a const if it does not fit
+    const fn d1(l: usize, k: usize, stride: usize, pad: usize) -> Self {
+        assert!(stride > 0, "stride should be positive");
+        assert!(k > 0 && k <= l + 2 * pad, "kernel should fit in the padded input");
+        Self {
+            h: 1, w: l,
+            kh: 1, kw: k,
+            stride_h: 1, stride_w: stride,
+            pad_h: 0, pad_w: pad,
+            oh: 1,
+            ow: (l + 2 * pad - k) / stride + 1,
+        }
+    }
+
+    /// check the flattened sizes `input` and `output` of `c_in` and `c_out` channels
+    const fn sized(self, c_in: usize, c_out: usize, input: usize, output: usize) -> Self {
+        assert!(c_in > 0 && c_out > 0, "channels should be positive");
+        assert!(input == c_in * self.h * self.w, "`IN` should be the input channels times the input size");
+        assert!(output == c_out * self.oh * self.ow, "`OUT` should be the output channels times the output size");
+        self
+    }
+
+    #[inline]
+    fn kernel(&self) -> usize {
+        self.kh * self.kw
+    }
+
+    #[inline]
+    fn plane(&self) -> usize {
+        self.h * self.w
+    }
+
+    #[inline]
+    fn out_plane(&self) -> usize {
+        self.oh * self.ow
+    }
+
+    /// call `f` with the index in the kernel and in the plane of each pixel in the window
+    /// of output `o`, pixels in padding are skipped
+    #[inline]
+    fn each(&self, o: usize, mut f: impl FnMut(usize, usize)) {
+        let (oy, ox) = (o / self.ow, o % self.ow);
+        for ky in 0..self.kh {
+            let iy = match (oy * self.stride_h + ky).checked_sub(self.pad_h) {
+                Some(iy) if iy < self.h => iy,
+                _ => continue,
+            };
+            for kx in 0..self.kw {
+                let ix = match (ox * self.stride_w + kx).checked_sub(self.pad_w) {
+                    Some(ix) if ix < self.w => ix,
+                    _ => continue,
+                };
+                f(ky * self.kw + kx, iy * self.w + ix);
+            }
+        }
+    }
+}
+
+/// output of a convolution over `x`, with a row of kernels in `w` per output channel
+fn conv_forward<T: Real>(win: &Window, w: &DMatrix<T>, b: &DVector<T>, x: &[T], z: &mut [T]) {
+    let (k, plane) = (win.kernel(), win.plane());
+    for (co, z) in z.chunks_exact_mut(win.out_plane()).enumerate() {
+        for (o, z) in z.iter_mut().enumerate() {
+            let mut acc = b[co];
+            for (ci, x) in x.chunks_exact(plane).enumerate() {
+                win.each(o, |j, i| acc += w[(co, ci * k + j)] * x[i]);
+            }
+            *z = acc;
+        }
+    }
+}
+
+/// add the gradients of `w` and `b` to `grad`, and return the gradient of `x` in `dx`,
+/// where `d` is the gradient of the output
+fn conv_backward<T: Real>(win: &Window, w: &DMatrix<T>, x: &[T], d: &[T], grad: (&mut DMatrix<T>, &mut DVector<T>), dx: &mut [T]) {
+    let (k, plane) = (win.kernel(), win.plane());
+    let (gw, gb) = grad;
+    for (co, d) in d.chunks_exact(win.out_plane()).enumerate() {
+        for (o, &d) in d.iter().enumerate() {
+            gb[co] += d;
+            for (ci, (x, dx)) in x.chunks_exact(plane).zip(dx.chunks_exact_mut(plane)).enumerate() {
+                win.each(o, |j, i| {
+                    gw[(co, ci * k + j)] += d * x[i];
+                    dx[i] += d * w[(co, ci * k + j)];
+                });
+            }
+        }
+    }
+}
+
+/// max or average of each window of each channel of `x`
+fn pool_forward<T: Real>(win: &Window, max: bool, x: &[T], z: &mut [T]) {
+    let k = real::<T>(win.kernel() as f64);
+    for (x, z) in x.chunks_exact(win.plane()).zip(z.chunks_exact_mut(win.out_plane())) {
+        for (o, z) in z.iter_mut().enumerate() {
+            *z = if max { x[argmax(win, o, x)] } else {
+                let mut sum = T::zero();
+                win.each(o, |_, i| sum += x[i]);
+                sum / k
+            };
+        }
+    }
+}
+
+/// gradient of `x` in `dx` of [`pool_forward`], where `d` is the gradient of the output
+fn pool_backward<T: Real>(win: &Window, max: bool, x: &[T], d: &[T], dx: &mut [T]) {
+    let k = real::<T>(win.kernel() as f64);
+    let windows = x.chunks_exact(win.plane()).zip(dx.chunks_exact_mut(win.plane()));
+    for ((x, dx), d) in windows.zip(d.chunks_exact(win.out_plane())) {
+        for (o, &d) in d.iter().enumerate() {
+            if max {
+                dx[argmax(win, o, x)] += d;
+            } else {
+                win.each(o, |_, i| dx[i] += d / k);
+            }
+        }
+    }
+}
+
+/// index in the plane `x` of the first max of the window of output `o`
+#[inline]
+fn argmax<T: Real>(win: &Window, o: usize, x: &[T]) -> usize {
+    let mut max = None;
+    win.each(o, |_, i| {
+        if max.map_or(true, |m: usize| x[i] > x[m]) {
+            max = Some(i);
+        }
+    });
+    max.expect("pooling windows are not empty")
+}
+
+/// define a convolution layer with const generics `$size`, whose window is `$win`
+/// and whose kernels have `$k` weights per input channel
+macro_rules! conv {
+    ($(#[$doc:meta])* $name:ident<$($size:ident),*>, $win:expr, $k:expr) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug)]
+        pub struct $name<$(const $size: usize,)* const IN: usize, const OUT: usize, T = f64> {
+            /// a row of kernels per output channel, a kernel per input channel laid out as the input
+            pub w: DMatrix<T>,
+            pub b: DVector<T>,
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T: Real> $name<$($size,)* IN, OUT, T> {
+            const WINDOW: Window = $win.sized(C_IN, C_OUT, IN, OUT);
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T: Real> Default for $name<$($size,)* IN, OUT, T> {
+            fn default() -> Self {
+                Self {
+                    w: DMatrix::zeros(C_OUT, C_IN * $k),
+                    b: DVector::zeros(C_OUT),
+                }
+            }
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T: Real> Params<T> for $name<$($size,)* IN, OUT, T> {
+            #[inline]
+            fn shapes() -> Vec<(usize, usize)> {
+                vec![(C_OUT, C_IN * $k)]
+            }
+
+            #[inline]
+            fn params(&self) -> Vec<(&[T], &[T])> {
+                vec![(self.w.as_slice(), self.b.as_slice())]
+            }
+
+            #[inline]
+            fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+                vec![(self.w.as_mut_slice(), self.b.as_mut_slice())]
+            }
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T: Real> Module<T> for $name<$($size,)* IN, OUT, T> {
+            type Input = SVector<T, IN>;
+            type Output = SVector<T, OUT>;
+            type Cache = ();
+
+            fn forward(&self, input: &SVector<T, IN>) -> (SVector<T, OUT>, ()) {
+                let mut z = SVector::<T, OUT>::zeros();
+                conv_forward(&Self::WINDOW, &self.w, &self.b, input.as_slice(), z.as_mut_slice());
+                (z, ())
+            }
+
+            fn backward_into(&self, grad: &mut Self, input: &SVector<T, IN>, _: &(), d_out: &SVector<T, OUT>) -> SVector<T, IN> {
+                let mut dx = SVector::<T, IN>::zeros();
+                let grad = (&mut grad.w, &mut grad.b);
+                conv_backward(&Self::WINDOW, &self.w, input.as_slice(), d_out.as_slice(), grad, dx.as_mut_slice());
+                dx
+            }
+
+            /// `fan_in` is the size of a kernel over all input channels, `fan_out` is `C_OUT`
+            fn init_with<R: rand::Rng + ?Sized>(init: Init, rng: &mut R) -> Self {
+                Self {
+                    w: init.weights_dyn(C_OUT, C_IN * $k, rng).map(real),
+                    b: DVector::zeros(C_OUT),
+                }
+            }
+        }
+    };
+}
+
+conv!(
+    /// 1D convolution of `C_IN` channels of length `L` by `C_OUT` kernels of size `K`,
+    /// moving by `STRIDE` over the input zero padded by `PAD` on each side.
+    Conv1d<C_IN, C_OUT, L, K, STRIDE, PAD>,
+    Window::d1(L, K, STRIDE, PAD),
+    K
+);
+
+conv!(
+    /// 2D convolution of `C_IN` channels of `H x W` by `C_OUT` square kernels of size `K`,
+    /// moving by `STRIDE` over the input zero padded by `PAD` on each side.
+    Conv2d<C_IN, C_OUT, H, W, K, STRIDE, PAD>,
+    Window::d2(H, W, K, STRIDE, PAD),
+    K * K
+);
+
+/// define a pooling layer without parameters, with const generics `$size` and window `$win`,
+/// taking the max of each window if `$max`, otherwise the average
+macro_rules! pool {
+    ($(#[$doc:meta])* $name:ident<$($size:ident),*>, $win:expr, $max:expr) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name<$(const $size: usize,)* const IN: usize, const OUT: usize, T = f64> {
+            _marker: PhantomData<T>,
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T> $name<$($size,)* IN, OUT, T> {
+            const WINDOW: Window = $win.sized(C, C, IN, OUT);
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T> Default for $name<$($size,)* IN, OUT, T> {
+            fn default() -> Self {
+                Self { _marker: PhantomData }
+            }
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T> Params<T> for $name<$($size,)* IN, OUT, T> {
+            #[inline]
+            fn shapes() -> Vec<(usize, usize)> {
+                Vec::new()
+            }
+
+            #[inline]
+            fn params(&self) -> Vec<(&[T], &[T])> {
+                Vec::new()
+            }
+
+            #[inline]
+            fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+                Vec::new()
+            }
+        }
+
+        impl<$(const $size: usize,)* const IN: usize, const OUT: usize, T: Real> Module<T> for $name<$($size,)* IN, OUT, T> {
+            type Input = SVector<T, IN>;
+            type Output = SVector<T, OUT>;
+            type Cache = ();
+
+            fn forward(&self, input: &SVector<T, IN>) -> (SVector<T, OUT>, ()) {
+                let mut z = SVector::<T, OUT>::zeros();
+                pool_forward(&Self::WINDOW, $max, input.as_slice(), z.as_mut_slice());
+                (z, ())
+            }
+
+            fn backward_into(&self, _: &mut Self, input: &SVector<T, IN>, _: &(), d_out: &SVector<T, OUT>) -> SVector<T, IN> {
+                let mut dx = SVector::<T, IN>::zeros();
+                pool_backward(&Self::WINDOW, $max, input.as_slice(), d_out.as_slice(), dx.as_mut_slice());
+                dx
+            }
+
+            #[inline]
+            fn init_with<R: rand::Rng + ?Sized>(_: Init, _: &mut R) -> Self {
+                Self::default()
+            }
+        }
+    };
+}
+
+pool!(
+    /// Max of windows of size `K` moving by `STRIDE` over `C` channels of length `L`,
+    /// the gradient goes to the first max of each window.
+    MaxPool1d<C, L, K, STRIDE>,
+    Window::d1(L, K, STRIDE, 0),
+    true
+);
+
+pool!(
+    /// Average of windows of size `K` moving by `STRIDE` over `C` channels of length `L`.
+    AvgPool1d<C, L, K, STRIDE>,
+    Window::d1(L, K, STRIDE, 0),
+    false
+);
+
+pool!(
+    /// Max of `K x K` windows moving by `STRIDE` over `C` channels of `H x W`,
+    /// the gradient goes to the first max of each window.
+    MaxPool2d<C, H, W, K, STRIDE>,
+    Window::d2(H, W, K, STRIDE, 0),
+    true
+);
+
+pool!(
+    /// Average of `K x K` windows moving by `STRIDE` over `C` channels of `H x W`.
+    AvgPool2d<C, H, W, K, STRIDE>,
+    Window::d2(H, W, K, STRIDE, 0),
+    false
+);
+
+/// Flattened channels of size `N` as the input of dense layers.
+///
+/// Images are already flat, so it passes its input through, and only marks where
+/// a network turns from convolutions to dense layers.
+#[derive(Clone, Copy, Debug)]
+pub struct Flatten<const N: usize, T = f64> {
+    _marker: PhantomData<T>,
+}
+
+impl<const N: usize, T> Default for Flatten<N, T> {
+    fn default() -> Self {
+        Self { _marker: PhantomData }
+    }
+}
+
+impl<const N: usize, T> Params<T> for Flatten<N, T> {
+    #[inline]
+    fn shapes() -> Vec<(usize, usize)> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(&[T], &[T])> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        Vec::new()
+    }
+}
+
+impl<const N: usize, T: Real> Module<T> for Flatten<N, T> {
+    type Input = SVector<T, N>;
+    type Output = SVector<T, N>;
+    type Cache = ();
+
+    #[inline]
+    fn forward(&self, input: &SVector<T, N>) -> (SVector<T, N>, ()) {
+        (*input, ())
+    }
+
+    #[inline]
+    fn backward_into(&self, _: &mut Self, _: &SVector<T, N>, _: &(), d_out: &SVector<T, N>) -> SVector<T, N> {
+        *d_out
+    }
+
+    #[inline]
+    fn init_with<R: rand::Rng + ?Sized>(_: Init, _: &mut R) -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,909 @@
+//! Networks whose layer sizes and activations are chosen at runtime.
+//!
+//! [`DynNetwork`] is the runtime counterpart of layers derived by `derive_layers`: weights are
+//! `DMatrix`/`DVector`, and the activation of each layer is an [`Activation`] value instead of
+//! a type, so a topology can be read from a config file or searched without recompiling.
+//! It is trained by the same [`Config`] and converts from and to derived layers of the same shapes.
+//!
+//! ```ignore
+//! let mut rng = StdRng::seed_from_u64(1);
+//! let net = DynNetwork::init_with(&[4, 16, 3], &[Activation::ReLU, Activation::Identity], Init::HeNormal, &mut rng)?
+//!     .with_softmax();
+//! let (net, history) = net.train(&items, config).build();
+//! ```
+
+use std::{fmt, fs::File, io::{BufReader, BufWriter, Read, Write}, path::Path, str::FromStr};
+
+use itertools::Itertools;
+pub use na::{DMatrix, DVector};
+
+use crate::{Config, Item, History, func::{self, ActivitionFunc, LossFunc}, metric, model::*, persist, schedule::LrScheduler, train::{self, MaybeSync}};
+
+/// Activation of a [`DynNetwork`] layer, one variant for each activation of [`func`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Activation {
+    Tanh,
+    Sigmoid,
+    Identity,
+    ReLU,
+    LeakyReLU,
+    ELU,
+    SELU,
+    Softplus,
+    HardTanh,
+    HardSigmoid,
+    GELU,
+    Swish,
+    Mish,
+}
+
+macro_rules! impl_activation {
+    ($($name:ident),* $(,)?) => {
+        impl Activation {
+            /// all variants, in order of declaration
+            pub const ALL: &'static [Activation] = &[$(Activation::$name),*];
+
+            #[inline]
+            pub fn f<T: Real>(self, x: T) -> T {
+                match self {
+                    $(Activation::$name => func::$name::f(x),)*
+                }
+            }
+
+            /// derivative at input `x`, where `y = f(x)`
+            #[inline]
+            pub fn d<T: Real>(self, x: T, y: T) -> T {
+                match self {
+                    $(Activation::$name => func::$name::d(x, y),)*
+                }
+            }
+
+            /// name of the variant, which is also the name of the type in [`func`]
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Activation::$name => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+impl_activation!(Tanh, Sigmoid, Identity, ReLU, LeakyReLU, ELU, SELU, Softplus, HardTanh, HardSigmoid, GELU, Swish, Mish);
+
+impl fmt::Display for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// parse the name of a variant, ignoring ASCII case
+impl FromStr for Activation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Activation::ALL.iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown activation `{}`", s))
+    }
+}
+
+/// Fully connected layer of runtime size, the counterpart of [`Layer`].
+#[derive(Clone, Debug)]
+pub struct DynLayer<T = f64> {
+    pub w: DMatrix<T>,
+    pub b: DVector<T>,
+}
+
+impl<T: Real> DynLayer<T> {
+    /// zero layer of size `size` with pre layer size `pre`
+    #[inline]
+    pub fn zeros(size: usize, pre: usize) -> Self {
+        Self {
+            w: DMatrix::zeros(size, pre),
+            b: DVector::zeros(size),
+        }
+    }
+
+    /// uniform in `[0, 1)`, same as [`Layer::random_with`]
+    pub fn random_with<R: Rng + ?Sized>(size: usize, pre: usize, rng: &mut R) -> Self {
+        let w = DMatrix::from_fn(size, pre, |_, _| real(rng.gen::<f64>()));
+        let b = DVector::from_fn(size, |_, _| real(rng.gen::<f64>()));
+        Self { w, b }
+    }
+
+    /// weights initialized by `init`, biases are zero
+    pub fn init_with<R: Rng + ?Sized>(init: Init, size: usize, pre: usize, rng: &mut R) -> Self {
+        Self {
+            w: init.weights_dyn(size, pre, rng).map(real),
+            b: DVector::zeros(size),
+        }
+    }
+
+    /// `(rows, cols)` of the weights
+    #[inline]
+    pub fn shape(&self) -> (usize, usize) {
+        self.w.shape()
+    }
+
+    #[inline]
+    pub fn calc(&self, input: &DVector<T>) -> DVector<T> {
+        &self.w * input + &self.b
+    }
+
+    /// apply `grad` to all parameters with `optimizer`, `m` and `v` are the moment buffers
+    pub fn optimize<Opt: Optimizer>(
+        &mut self,
+        optimizer: &Opt,
+        t: usize,
+        rate: f64,
+        grad: &Self,
+        m: &mut Self,
+        v: &mut Self
+    ) {
+        let rate = real(rate);
+        let params = self.w.iter_mut().chain(self.b.iter_mut());
+        let grads = grad.w.iter().chain(grad.b.iter());
+        let ms = m.w.iter_mut().chain(m.b.iter_mut());
+        let vs = v.w.iter_mut().chain(v.b.iter_mut());
+        for (((p, g), m), v) in params.zip(grads).zip(ms).zip(vs) {
+            optimizer.step(t, rate, p, *g, m, v);
+        }
+    }
+
+    /// add the gradient of `penalty` on `params` to this gradient
+    pub fn penalize(&mut self, params: &Self, penalty: Penalty, include_bias: bool) {
+        if penalty.is_none() {
+            return;
+        }
+        self.w.zip_apply(&params.w, |g, p| *g += penalty.d(p));
+        if include_bias {
+            self.b.zip_apply(&params.b, |g, p| *g += penalty.d(p));
+        }
+    }
+
+    /// convert parameters to scalar type `U`
+    pub fn cast<U: Real>(&self) -> DynLayer<U> {
+        DynLayer {
+            w: self.w.map(|v| real(to_f64(v))),
+            b: self.b.map(|v| real(to_f64(v))),
+        }
+    }
+}
+
+impl<const S: usize, const P: usize, T: Real> From<&Layer<S, P, T>> for DynLayer<T> {
+    fn from(layer: &Layer<S, P, T>) -> Self {
+        Self {
+            w: DMatrix::from_column_slice(S, P, layer.w.as_slice()),
+            b: DVector::from_column_slice(layer.b.as_slice()),
+        }
+    }
+}
+
+/// Counterpart of [`Item`] with runtime sizes.
+#[derive(Clone, Debug)]
+pub struct DynItem<T = f64> {
+    pub data: DVector<T>,
+    pub label: DVector<T>,
+    /// scale of the loss and gradient of this item, `1` if `None`
+    pub weight: Option<f64>,
+}
+
+impl<T: Real> DynItem<T> {
+    #[inline]
+    pub fn new(data: DVector<T>, label: DVector<T>) -> Self {
+        Self { data, label, weight: None }
+    }
+
+    #[inline]
+    pub fn with_weight(self, weight: f64) -> Self {
+        Self { weight: Some(weight), ..self }
+    }
+
+    /// class of `label` by [`metric::class_of`]
+    #[inline]
+    pub fn class(&self) -> usize {
+        metric::class_of(&self.label)
+    }
+}
+
+impl<const I: usize, const O: usize, T: Real> From<&Item<I, O, T>> for DynItem<T> {
+    fn from(item: &Item<I, O, T>) -> Self {
+        Self {
+            data: DVector::from_column_slice(item.data.as_slice()),
+            label: DVector::from_column_slice(item.label.as_slice()),
+            weight: item.weight,
+        }
+    }
+}
+
+/// Result of [`DynNetwork::forward`], the counterpart of the `Calculation` of derived layers.
+#[derive(Clone, Debug)]
+pub struct DynCalculation<T = f64> {
+    /// `w * a + b` of each layer
+    pub z: Vec<DVector<T>>,
+    /// activation of `z` of each layer
+    pub a: Vec<DVector<T>>,
+}
+
+impl<T> DynCalculation<T> {
+    #[inline]
+    pub fn out(&self) -> &DVector<T> {
+        self.a.last().unwrap()
+    }
+}
+
+/// Why sizes and activations do not make a [`DynNetwork`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// sizes have no layer after the input size
+    NoLayers,
+    /// count of activations differs from the count of layers
+    ActivationCount { expected: usize, found: usize },
+    /// params of layer `layer` are not of a dense layer: a field without params such as
+    /// pooling is before it, or their lengths differ from its shape
+    NotDense { layer: usize },
+    /// shape of layer `layer` does not take the size of the layer before
+    ShapeMismatch { layer: usize, expected: (usize, usize), found: (usize, usize) },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::NoLayers => write!(f, "at least one layer is required"),
+            BuildError::ActivationCount { expected, found } =>
+                write!(f, "expected {} activations, found {}", expected, found),
+            BuildError::NotDense { layer } => write!(f, "layer {} is not a dense layer", layer),
+            BuildError::ShapeMismatch { layer, expected, found } =>
+                write!(f, "layer {}: expected shape {:?}, found {:?}", layer, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+#[inline]
+fn check_activations(layers: usize, activations: &[Activation]) -> Result<(), BuildError> {
+    if activations.len() == layers {
+        Ok(())
+    } else {
+        Err(BuildError::ActivationCount { expected: layers, found: activations.len() })
+    }
+}
+
+/// Layers of runtime sizes, each with its own [`Activation`].
+///
+/// `T`: scalar type
+#[derive(Clone, Debug)]
+pub struct DynNetwork<T = f64> {
+    /// from input to output, the pre layer size of each layer is the size of the one before
+    pub layers: Vec<DynLayer<T>>,
+    /// activation of each layer
+    pub activations: Vec<Activation>,
+    /// outputs are logits and `predict_proba` applies softmax, like the `softmax` option
+    /// of `derive_layers`, the output activation should be `Identity`
+    pub softmax: bool,
+}
+
+impl<T: Real> DynNetwork<T> {
+    /// layers built by `layer(size, pre)`
+    ///
+    /// `sizes`: input size followed by the size of each layer
+    ///
+    /// `activations`: activation of each layer
+    fn build(
+        sizes: &[usize],
+        activations: &[Activation],
+        mut layer: impl FnMut(usize, usize) -> DynLayer<T>
+    ) -> Result<Self, BuildError> {
+        if sizes.len() < 2 {
+            return Err(BuildError::NoLayers);
+        }
+        check_activations(sizes.len() - 1, activations)?;
+        Ok(Self {
+            layers: sizes.iter().tuple_windows().map(|(&pre, &size)| layer(size, pre)).collect(),
+            activations: activations.to_vec(),
+            softmax: false,
+        })
+    }
+
+    /// zero layers
+    ///
+    /// `sizes`: input size followed by the size of each layer
+    ///
+    /// `activations`: activation of each layer
+    ///
+    /// fails if `sizes` has no layer, or the count of `activations` is not the count of layers
+    pub fn zeros(sizes: &[usize], activations: &[Activation]) -> Result<Self, BuildError> {
+        Self::build(sizes, activations, DynLayer::zeros)
+    }
+
+    /// layers uniform in `[0, 1)`, see [`zeros`](Self::zeros) for arguments
+    pub fn random(sizes: &[usize], activations: &[Activation]) -> Result<Self, BuildError> {
+        Self::random_with(sizes, activations, &mut rand::thread_rng())
+    }
+
+    /// same as [`random`](Self::random), drawing values from `rng`
+    pub fn random_with<R: Rng + ?Sized>(sizes: &[usize], activations: &[Activation], rng: &mut R) -> Result<Self, BuildError> {
+        Self::build(sizes, activations, |size, pre| DynLayer::random_with(size, pre, rng))
+    }
+
+    /// weights of all layers initialized by `init`, see [`zeros`](Self::zeros) for arguments
+    pub fn init_with<R: Rng + ?Sized>(
+        sizes: &[usize],
+        activations: &[Activation],
+        init: Init,
+        rng: &mut R
+    ) -> Result<Self, BuildError> {
+        Self::build(sizes, activations, |size, pre| DynLayer::init_with(init, size, pre, rng))
+    }
+
+    /// apply softmax in `predict_proba`, the output activation is set to `Identity`
+    pub fn with_softmax(mut self) -> Self {
+        self.softmax = true;
+        *self.activations.last_mut().unwrap() = Activation::Identity;
+        self
+    }
+
+    /// layers with the params of `layers`, e.g. layers derived by `derive_layers`
+    ///
+    /// `activations`: activation of each layer, which are types in `layers`
+    ///
+    /// fails if `layers` are not dense layers one after another, such as convolutions or
+    /// normalization, or the count of `activations` is not the count of layers
+    pub fn from_layers<L: Params<T>>(layers: &L, activations: &[Activation]) -> Result<Self, BuildError> {
+        let shapes = L::shapes();
+        if shapes.is_empty() {
+            return Err(BuildError::NoLayers);
+        }
+        let params = layers.params();
+        for (i, ((&(rows, cols), (w, b)), layer)) in shapes.iter().zip(&params).zip(L::layers()).enumerate() {
+            if layer != i + 1 || rows * cols == 0 || w.len() != rows * cols || b.len() != rows {
+                return Err(BuildError::NotDense { layer: i + 1 });
+            }
+        }
+        for (i, (pre, cur)) in shapes.iter().tuple_windows().enumerate() {
+            if cur.1 != pre.0 {
+                return Err(BuildError::ShapeMismatch { layer: i + 2, expected: (cur.0, pre.0), found: *cur });
+            }
+        }
+        check_activations(shapes.len(), activations)?;
+        let layers = shapes.into_iter()
+            .zip(params)
+            .map(|((rows, cols), (w, b))| DynLayer {
+                w: DMatrix::from_column_slice(rows, cols, w),
+                b: DVector::from_column_slice(b),
+            })
+            .collect();
+        Ok(Self { layers, activations: activations.to_vec(), softmax: false })
+    }
+
+    /// layers `L` with the same params, fails if shapes or lengths of params differ
+    ///
+    /// activations are not checked, since they are types of `L`
+    pub fn to_layers<L: Params<T> + Default>(&self) -> persist::Result<L> {
+        persist::check_shapes::<L, T>(&self.shapes())?;
+        let mut layers = L::default();
+        persist::copy_params(&mut layers, self.params())?;
+        Ok(layers)
+    }
+
+    /// input size followed by the size of each layer
+    pub fn sizes(&self) -> Vec<usize> {
+        std::iter::once(self.input_size())
+            .chain(self.layers.iter().map(|l| l.b.len()))
+            .collect()
+    }
+
+    #[inline]
+    pub fn input_size(&self) -> usize {
+        self.layers[0].w.ncols()
+    }
+
+    #[inline]
+    pub fn output_size(&self) -> usize {
+        self.layers.last().unwrap().b.len()
+    }
+
+    /// `(rows, cols)` of the weight matrix of each layer
+    pub fn shapes(&self) -> Vec<(usize, usize)> {
+        self.layers.iter().map(DynLayer::shape).collect()
+    }
+
+    /// `(w, b)` of each layer, weights are column-major
+    pub fn params(&self) -> Vec<(&[T], &[T])> {
+        self.layers.iter().map(|l| (l.w.as_slice(), l.b.as_slice())).collect()
+    }
+
+    /// `(w, b)` of each layer, weights are column-major
+    pub fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        self.layers.iter_mut().map(|l| (l.w.as_mut_slice(), l.b.as_mut_slice())).collect()
+    }
+
+    /// go forward and get calculation result of all layers
+    pub fn forward(&self, item: &DVector<T>) -> DynCalculation<T> {
+        let mut calc = DynCalculation {
+            z: Vec::with_capacity(self.layers.len()),
+            a: Vec::with_capacity(self.layers.len()),
+        };
+        for (i, (layer, &act)) in self.layers.iter().zip(&self.activations).enumerate() {
+            let z = layer.calc(if i == 0 { item } else { &calc.a[i - 1] });
+            calc.a.push(z.map(|z| act.f(z)));
+            calc.z.push(z);
+        }
+        calc
+    }
+
+    /// backward and get the gradient of each layer
+    ///
+    /// `gradient`: Partial derivative of `E` with respect to `out`, where E is loss function
+    pub fn backward(&self, input: &DVector<T>, gradient: DVector<T>, calc: DynCalculation<T>) -> Vec<DynLayer<T>> {
+        let mut grads = Vec::with_capacity(self.layers.len());
+        let mut delta = gradient;
+        for i in (0..self.layers.len()).rev() {
+            let act = self.activations[i];
+            let dz = calc.z[i].zip_map(&calc.a[i], |z, a| act.d(z, a)).component_mul(&delta);
+            let pre = if i == 0 { input } else { &calc.a[i - 1] };
+            if i > 0 {
+                delta = self.layers[i].w.tr_mul(&dz);
+            }
+            grads.push(DynLayer { w: &dz * pre.transpose(), b: dz });
+        }
+        grads.reverse();
+        grads
+    }
+
+    /// average `gradients` and apply them with `optimizer`, see [`Layers::update`]
+    ///
+    /// empty `moments` are shaped as the layers on the first update
+    pub fn update<Opt: Optimizer>(
+        &mut self,
+        rate: f64,
+        optimizer: &Opt,
+        moments: &mut Moments<Vec<DynLayer<T>>>,
+        regularization: &Regularization,
+        gradients: impl IntoIterator<Item = Vec<DynLayer<T>>>
+    ) {
+        let mut iter = gradients.into_iter();
+        let mut sum = iter.next().unwrap();
+        let mut len = 1;
+        for g in iter {
+            for (s, g) in sum.iter_mut().zip(g) {
+                s.w += g.w;
+                s.b += g.b;
+            }
+            len += 1;
+        }
+        if moments.m.is_empty() {
+            let zeros = self.layers.iter().map(|l| DynLayer::zeros(l.b.len(), l.w.ncols())).collect_vec();
+            moments.m = zeros.clone();
+            moments.v = zeros;
+        }
+
+        let len = real::<T>(len as f64);
+        let t = moments.next_step();
+        for (i, (layer, mut grad)) in self.layers.iter_mut().zip(sum).enumerate() {
+            grad.w /= len;
+            grad.b /= len;
+            grad.penalize(layer, regularization.layer(i + 1), regularization.include_bias);
+            layer.optimize(optimizer, t, rate, &grad, &mut moments.m[i], &mut moments.v[i]);
+        }
+    }
+
+    pub fn test(&self, item: &DVector<T>) -> DVector<T> {
+        self.layers.iter()
+            .zip(&self.activations)
+            .fold(item.clone(), |a, (layer, &act)| layer.calc(&a).map(|z| act.f(z)))
+    }
+
+    /// probability of each class, softmax of the logits of `test`, `None` without
+    /// [`softmax`](Self::softmax)
+    pub fn predict_proba(&self, item: &DVector<T>) -> Option<DVector<T>> {
+        self.softmax.then(|| func::softmax(&self.test(item)))
+    }
+
+    /// outputs of all `inputs` in order, on multiple threads with feature `parallel`
+    pub fn predict_batch<'i, D>(&self, inputs: D) -> Vec<DVector<T>>
+    where
+        D: IntoIterator<Item = &'i DVector<T>>,
+        T: MaybeSync + 'i
+    {
+        let inputs = inputs.into_iter().collect_vec();
+        map_items(&inputs, |x| self.test(x))
+    }
+
+    /// average loss `Loss` of `items`, item weights are ignored, `0` without items
+    ///
+    /// `_loss` is only for its type, e.g. `net.loss(items, DistanceFunc)`
+    pub fn loss<Loss: LossFunc>(&self, items: &[DynItem<T>], _loss: Loss) -> f64
+    where
+        T: MaybeSync
+    {
+        let outputs = self.predict_batch(items.iter().map(|item| &item.data));
+        let sum = items.iter()
+            .zip(outputs)
+            .map(|(item, out)| to_f64(Loss::f(&item.label, &out)))
+            .sum::<f64>();
+        metric::average(sum, items.len())
+    }
+
+    /// ratio of `items` whose output class equals the label class, see [`metric::class_of`],
+    /// `0` without items
+    pub fn accuracy(&self, items: &[DynItem<T>]) -> f64
+    where
+        T: MaybeSync
+    {
+        let outputs = self.predict_batch(items.iter().map(|item| &item.data));
+        let hits = items.iter()
+            .zip(outputs)
+            .filter(|(item, out)| item.class() == metric::class_of(out))
+            .count();
+        metric::ratio(hits, items.len())
+    }
+
+    /// convert parameters to scalar type `U`
+    pub fn cast<U: Real>(&self) -> DynNetwork<U> {
+        DynNetwork {
+            layers: self.layers.iter().map(DynLayer::cast).collect(),
+            activations: self.activations.clone(),
+            softmax: self.softmax,
+        }
+    }
+
+    /// train on `data` with `config`, where `config.actvt_func` is not used
+    /// since each layer has its own activation
+    #[inline]
+    pub fn train<'a, A, C, Opt, Sch, D>(self, data: D, config: Config<A, C, Opt, Sch>) -> DynTrainer<'a, A, C, Opt, Sch, T>
+    where
+        C: LossFunc,
+        Opt: Optimizer,
+        Sch: LrScheduler,
+        D: IntoIterator<Item = &'a DynItem<T>>
+    {
+        DynTrainer::new(self, data, config)
+    }
+}
+
+/// Saving and loading in the formats of [`persist`], along with activations.
+///
+/// Files saved here load into derived layers of the same shapes by [`persist`],
+/// while files of derived layers lack activations, convert by [`DynNetwork::from_layers`] instead.
+impl<T: Real> DynNetwork<T> {
+    fn activations_meta(&self) -> persist::Activations {
+        persist::Activations {
+            names: self.activations.iter().map(|a| a.name().to_owned()).collect(),
+            softmax: self.softmax,
+        }
+    }
+
+    fn from_raw(raw: persist::Raw<T>) -> persist::Result<Self> {
+        let malformed = |msg: String| persist::Error::Malformed(msg);
+        let meta = raw.activations
+            .ok_or_else(|| malformed("activations are not stored".to_owned()))?;
+        if raw.shapes.is_empty() {
+            return Err(malformed("at least one layer is required".to_owned()));
+        }
+        for (i, (pre, cur)) in raw.shapes.iter().tuple_windows().enumerate() {
+            if cur.1 != pre.0 {
+                return Err(persist::Error::ShapeMismatch { layer: i + 2, expected: (cur.0, pre.0), found: *cur });
+            }
+        }
+        for (i, (&(rows, cols), (w, b))) in raw.shapes.iter().zip(&raw.params).enumerate() {
+            if (w.len(), b.len()) != (rows * cols, rows) {
+                return Err(persist::Error::ParamCount { layer: i + 1, expected: (rows * cols, rows), found: (w.len(), b.len()) });
+            }
+        }
+        if meta.names.len() != raw.shapes.len() {
+            let msg = format!("expected {} activations, found {}", raw.shapes.len(), meta.names.len());
+            return Err(malformed(msg));
+        }
+        let activations = meta.names.iter()
+            .map(|name| name.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(malformed)?;
+        let layers = raw.shapes.into_iter()
+            .zip(raw.params)
+            .map(|((rows, cols), (w, b))| DynLayer {
+                w: DMatrix::from_vec(rows, cols, w),
+                b: DVector::from_vec(b),
+            })
+            .collect();
+        Ok(Self { layers, activations, softmax: meta.softmax })
+    }
+
+    pub fn write_binary<W: Write>(&self, w: W) -> persist::Result<()> {
+        persist::write_raw(&self.shapes(), self.params(), Some(&self.activations_meta()), None, w)
+    }
+
+    pub fn read_binary<R: Read>(r: R) -> persist::Result<Self> {
+        Self::from_raw(persist::read_raw(r)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        persist::to_json_raw(&self.shapes(), self.params(), Some(&self.activations_meta()), None)
+    }
+
+    pub fn from_json(s: &str) -> persist::Result<Self> {
+        Self::from_raw(persist::from_json_raw(s)?)
+    }
+
+    /// save to `path` in binary format
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> persist::Result<()> {
+        self.write_binary(BufWriter::new(File::create(path)?))
+    }
+
+    /// save to `path` in JSON format
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> persist::Result<()> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// load from binary file `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> persist::Result<Self> {
+        Self::read_binary(BufReader::new(File::open(path)?))
+    }
+
+    /// load from JSON file `path`
+    pub fn load_json<P: AsRef<Path>>(path: P) -> persist::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// [`Metric`](metric::Metric) of the runtime sized outputs of a [`DynNetwork`].
+pub trait DynMetric<T = f64> {
+    /// key of the metric in [`History`]
+    fn name(&self) -> String;
+
+    /// `labels` and `outputs` have the same length and order
+    fn compute(&self, labels: &[DVector<T>], outputs: &[DVector<T>]) -> f64;
+}
+
+macro_rules! impl_dyn_metric {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl<T: Real> DynMetric<T> for metric::$name {
+                #[inline]
+                fn name(&self) -> String {
+                    metric::Metric::<1, T>::name(self)
+                }
+
+                #[inline]
+                fn compute(&self, labels: &[DVector<T>], outputs: &[DVector<T>]) -> f64 {
+                    self.of(labels, outputs)
+                }
+            }
+        )*
+    };
+}
+
+impl_dyn_metric!(Accuracy, TopK, Precision, Recall, F1, Mse, Mae, R2);
+
+type DynItemLstn<'a, A, C, Opt, Sch, T> =
+    Box<dyn 'a + Fn(&DynNetwork<T>, &Config<A, C, Opt, Sch>, &DynItem<T>, &DVector<T>)>;
+type DynChunkLstn<'a, A, C, Opt, Sch, T> =
+    Box<dyn 'a + Fn(&DynNetwork<T>, &Config<A, C, Opt, Sch>, &[&DynItem<T>], &[DVector<T>])>;
+type DynIterLstn<'a, A, C, Opt, Sch, T> = Box<dyn 'a + Fn(usize, &DynNetwork<T>, &Config<A, C, Opt, Sch>)>;
+
+/// Training loop of [`DynNetwork`], the same as the one of derived layers.
+pub struct DynTrainer<'a, A, C, Opt, Sch, T> {
+    data: Vec<&'a DynItem<T>>,
+    validation: Vec<&'a DynItem<T>>,
+    patience: Option<usize>,
+    net: DynNetwork<T>,
+    config: Config<A, C, Opt, Sch>,
+    item_lstn: Option<DynItemLstn<'a, A, C, Opt, Sch, T>>,
+    chunk_lstn: Option<DynChunkLstn<'a, A, C, Opt, Sch, T>>,
+    iter_lstn: Option<DynIterLstn<'a, A, C, Opt, Sch, T>>,
+    metrics: Vec<Box<dyn 'a + DynMetric<T>>>,
+}
+
+impl<'a, A, C, Opt, Sch, T> DynTrainer<'a, A, C, Opt, Sch, T>
+where
+    C: LossFunc,
+    Opt: Optimizer,
+    Sch: LrScheduler,
+    T: Real
+{
+    #[inline]
+    pub fn new<D>(net: DynNetwork<T>, items: D, config: Config<A, C, Opt, Sch>) -> Self
+    where
+        D: IntoIterator<Item = &'a DynItem<T>>
+    {
+        Self {
+            data: items.into_iter().collect(),
+            validation: Vec::new(),
+            patience: None,
+            net,
+            config,
+            item_lstn: None,
+            chunk_lstn: None,
+            iter_lstn: None,
+            metrics: Vec::new(),
+        }
+    }
+
+    /// evaluate loss on `items` after each iter, weights with the lowest loss are kept
+    #[inline]
+    pub fn validate_with<D>(&mut self, items: D)
+    where
+        D: IntoIterator<Item = &'a DynItem<T>>
+    {
+        self.validation = items.into_iter().collect();
+    }
+
+    /// stop training when validation loss has not improved for `patience` iters,
+    /// has no effect without [`validate_with`](Self::validate_with)
+    #[inline]
+    pub fn early_stop(&mut self, patience: usize) {
+        self.patience = Some(patience);
+    }
+
+    /// record `metric` of training items, and of validation items if any, after each iter
+    #[inline]
+    pub fn metric<M>(&mut self, metric: M)
+    where
+        M: 'a + DynMetric<T>
+    {
+        self.metrics.push(Box::new(metric));
+    }
+
+    /// called with the network before the update of the chunk of the item
+    #[inline]
+    pub fn after_each_item<F>(&mut self, f: F)
+    where
+        F: 'a + Fn(&DynNetwork<T>, &Config<A, C, Opt, Sch>, &DynItem<T>, &DVector<T>),
+    {
+        self.item_lstn = Some(Box::new(f));
+    }
+
+    /// called with the network updated by the chunk, and the outputs before the update
+    #[inline]
+    pub fn after_each_chunk<F>(&mut self, f: F)
+    where
+        F: 'a + Fn(&DynNetwork<T>, &Config<A, C, Opt, Sch>, &[&DynItem<T>], &[DVector<T>]),
+    {
+        self.chunk_lstn = Some(Box::new(f));
+    }
+
+    #[inline]
+    pub fn after_each_iter<F>(&mut self, f: F)
+    where
+        F: 'a + Fn(usize, &DynNetwork<T>, &Config<A, C, Opt, Sch>),
+    {
+        self.iter_lstn = Some(Box::new(f));
+    }
+
+    /// same as `Trainer::build` without dropout
+    ///
+    /// with the same seed, a network converted by [`DynNetwork::from_layers`] trains to
+    /// the same weights, losses and metrics as the derived layers
+    pub fn build(self) -> (DynNetwork<T>, History)
+    where
+        T: MaybeSync
+    {
+        let Self { mut data, validation, patience, net, mut config, item_lstn, chunk_lstn, iter_lstn, metrics } = self;
+        let mut history = History::new(metrics.iter().map(|m| m.name()));
+        history.reserve(config.iter_num);
+        let mut fitting = DynFitting {
+            net,
+            best: None,
+            moments: Moments::default(),
+            outputs: Vec::with_capacity(config.batch_size),
+            labels: Vec::new(),
+            epoch_outputs: Vec::new(),
+            val_labels: validation.iter().map(|item| item.label.clone()).collect(),
+            val_outputs: Vec::with_capacity(validation.len()),
+            item_lstn,
+            chunk_lstn,
+            iter_lstn,
+            metrics,
+        };
+        train::fit(&mut fitting, &mut config, &mut data, &validation, patience, &mut history);
+        (fitting.best.unwrap_or(fitting.net), history)
+    }
+}
+
+/// State of [`DynTrainer::build`] kept across chunks and iters.
+struct DynFitting<'a, A, C, Opt, Sch, T> {
+    net: DynNetwork<T>,
+    best: Option<DynNetwork<T>>,
+    moments: Moments<Vec<DynLayer<T>>>,
+    outputs: Vec<DVector<T>>,
+    /// labels and outputs of the iter for metrics, only kept with metrics
+    labels: Vec<DVector<T>>,
+    epoch_outputs: Vec<DVector<T>>,
+    val_labels: Vec<DVector<T>>,
+    val_outputs: Vec<DVector<T>>,
+    item_lstn: Option<DynItemLstn<'a, A, C, Opt, Sch, T>>,
+    chunk_lstn: Option<DynChunkLstn<'a, A, C, Opt, Sch, T>>,
+    iter_lstn: Option<DynIterLstn<'a, A, C, Opt, Sch, T>>,
+    metrics: Vec<Box<dyn 'a + DynMetric<T>>>,
+}
+
+impl<'a, A, C, Opt, Sch, T> train::Fit<A, C, Opt, Sch> for DynFitting<'a, A, C, Opt, Sch, T>
+where
+    C: LossFunc,
+    Opt: Optimizer,
+    T: Real + MaybeSync
+{
+    type Item = DynItem<T>;
+
+    #[inline]
+    fn weight(config: &Config<A, C, Opt, Sch>, item: &Self::Item) -> f64 {
+        config.weight_of(item.weight, item.class())
+    }
+
+    /// `_seeds` are not used since there is no dropout
+    fn fit_chunk(&mut self, config: &Config<A, C, Opt, Sch>, chunk: &[&Self::Item], weights: &[f64], _seeds: &[u64]) -> f64 {
+        let net = &self.net;
+        let weighted = chunk.iter().copied().zip(weights.iter().copied()).collect_vec();
+        let (outputs, gradients): (Vec<_>, Vec<_>) = map_items(&weighted, |&(item, weight)| {
+            let calc = net.forward(&item.data);
+            let out = calc.out().clone();
+            let last_gradient = C::d(&item.label, &out) * real::<T>(weight);
+            (out, net.backward(&item.data, last_gradient, calc))
+        }).into_iter().unzip();
+        self.outputs = outputs;
+
+        if let Some(f) = &self.item_lstn {
+            for (item, out) in chunk.iter().zip(self.outputs.iter()) {
+                f(&self.net, config, item, out);
+            }
+        }
+
+        self.net.update(config.learn_rate, &config.optimizer, &mut self.moments, &config.regularization, gradients);
+
+        let loss = chunk.iter()
+            .zip(self.outputs.iter())
+            .zip(weights.iter())
+            .map(|((item, out), w)| w * to_f64(C::f(&item.label, out)))
+            .sum::<f64>();
+
+        if let Some(f) = &self.chunk_lstn {
+            f(&self.net, config, chunk, &self.outputs);
+        }
+
+        if !self.metrics.is_empty() {
+            self.labels.extend(chunk.iter().map(|item| item.label.clone()));
+            self.epoch_outputs.append(&mut self.outputs);
+        }
+        loss
+    }
+
+    #[inline]
+    fn penalty(&self, config: &Config<A, C, Opt, Sch>) -> f64 {
+        config.regularization.loss_of(self.net.params())
+    }
+
+    fn end_iter(&mut self, i: usize, config: &Config<A, C, Opt, Sch>, history: &mut History) {
+        for (metric, values) in self.metrics.iter().zip(history.train_metrics.iter_mut()) {
+            values.1.push(metric.compute(&self.labels, &self.epoch_outputs));
+        }
+        self.labels.clear();
+        self.epoch_outputs.clear();
+
+        if let Some(f) = &self.iter_lstn {
+            f(i, &self.net, config);
+        }
+    }
+
+    fn validate(&mut self, config: &Config<A, C, Opt, Sch>, items: &[&Self::Item], history: &mut History) -> f64 {
+        let net = &self.net;
+        self.val_outputs.clear();
+        self.val_outputs.extend(items.iter().map(|item| net.test(&item.data)));
+        for (metric, values) in self.metrics.iter().zip(history.val_metrics.iter_mut()) {
+            values.1.push(metric.compute(&self.val_labels, &self.val_outputs));
+        }
+
+        let sum = items.iter()
+            .zip(&self.val_outputs)
+            .map(|(item, out)| Self::weight(config, item) * to_f64(C::f(&item.label, out)))
+            .sum::<f64>();
+        sum / items.len() as f64
+    }
+
+    #[inline]
+    fn keep_best(&mut self) {
+        self.best = Some(self.net.clone());
+    }
+}
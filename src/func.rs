@@ -1,77 +1,473 @@
 #![allow(non_snake_case)]
 
-use na::SVector;
+use na::{DefaultAllocator, Dim, OVector, SVector, allocator::Allocator};
 
+use crate::model::{Init, Module, Params, Real, real};
+
+/// An activation and its derivative at the input `x`, layers call `d` with both `x` and
+/// the output `y = f(x)`, so activations whose derivative is a function of `y` can use it.
 pub trait ActivitionFunc {
-    fn f(x: f64) -> f64;
-    // fn d(x: f64) -> f64;
-    fn d_from_y(y: f64) -> f64;
+    fn f<T: Real>(x: T) -> T;
+
+    /// derivative at input `x`, where `y = f(x)`
+    fn d<T: Real>(x: T, y: T) -> T;
 
-    fn fv<const S: usize>(v: &SVector<f64,S>) -> SVector<f64,S> {
+    fn fv<T: Real, const S: usize>(v: &SVector<T,S>) -> SVector<T,S> {
         v.map(Self::f)
     }
+}
 
-    fn d_from_yv<const S: usize>(v: &SVector<f64,S>) -> SVector<f64,S> {
+/// Derivative of an activation by its output `y = f(x)` alone, implemented by the
+/// activations where it exists, which are all but [`GELU`], [`Swish`] and [`Mish`].
+pub trait DerivFromY: ActivitionFunc {
+    /// derivative by the output `y`
+    fn d_from_y<T: Real>(y: T) -> T;
+
+    fn d_from_yv<T: Real, const S: usize>(v: &SVector<T,S>) -> SVector<T,S> {
         v.map(Self::d_from_y)
     }
 }
 
+/// computed by `tanh` of std, which doesn't overflow for large `|x|`
 #[derive(Clone, Copy, Default,)]
 pub struct Tanh;
 
 impl ActivitionFunc for Tanh {
     #[inline]
-    fn f(x: f64) -> f64 {
-        let ex = x.exp();
-        let e_x = (-x).exp();
-        (ex - e_x)/(ex + e_x)
+    fn f<T: Real>(x: T) -> T {
+        x.tanh()
     }
 
     #[inline]
-    fn d_from_y(y: f64) -> f64 {
-        1f64 - y.powi(2)
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
     }
 }
 
+impl DerivFromY for Tanh {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        T::one() - y.powi(2)
+    }
+}
+
+/// `e^x` is only computed for negative `x`, so it doesn't overflow
 #[derive(Clone, Copy, Default,)]
 pub struct Sigmoid;
 
 impl ActivitionFunc for Sigmoid {
     #[inline]
-    fn f(x: f64) -> f64 {
-        1. / 1. + (-x).exp()
+    fn f<T: Real>(x: T) -> T {
+        if x >= T::zero() {
+            T::one() / (T::one() + (-x).exp())
+        } else {
+            let e = x.exp();
+            e / (T::one() + e)
+        }
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for Sigmoid {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        y * (T::one() - y)
+    }
+}
+
+/// `f(x) = x`, usually for the output layer of regression.
+#[derive(Clone, Copy, Default,)]
+pub struct Identity;
+
+impl ActivitionFunc for Identity {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for Identity {
+    #[inline]
+    fn d_from_y<T: Real>(_: T) -> T {
+        T::one()
+    }
+}
+
+/// `max(x, 0)`
+#[derive(Clone, Copy, Default,)]
+pub struct ReLU;
+
+impl ActivitionFunc for ReLU {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x.max(T::zero())
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for ReLU {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        if y > T::zero() { T::one() } else { T::zero() }
+    }
+}
+
+/// `x` if `x > 0` else `0.01 * x`
+/// 
+/// The slope of negative inputs is fixed, [`PReLU`] learns it.
+#[derive(Clone, Copy, Default,)]
+pub struct LeakyReLU;
+
+impl LeakyReLU {
+    pub const SLOPE: f64 = 0.01;
+}
+
+impl ActivitionFunc for LeakyReLU {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        if x > T::zero() { x } else { real::<T>(Self::SLOPE) * x }
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for LeakyReLU {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        if y > T::zero() { T::one() } else { real(Self::SLOPE) }
+    }
+}
+
+/// `x` if `x > 0` else `slope * x`, with a learned slope for each of the `N` features.
+/// 
+/// Since the slope is learned, it is a [`Module`] for a field of networks declared with
+/// [`derive_layers`](crate::derive_layers) instead of an activation:
+/// 
+/// ```ignore
+/// #[derive_layers(input(8), output(2))]
+/// struct Parametric {
+///     hidden: Layer<16, 8, T>,
+///     prelu: PReLU<16, T>,
+///     #[activation(Sigmoid)]
+///     out: Layer<2, 16, T>,
+/// }
+/// ```
+/// 
+/// As params the slope is the bias of shape `(N, 0)`, so regularization leaves it alone
+/// unless biases are included, weight decay would pull it to ReLU. `Default` is zero as
+/// other layers, since it is also the gradient, `init_with` starts from
+/// [`PReLU::SLOPE`] whatever the scheme.
+#[derive(Clone, Debug)]
+pub struct PReLU<const N: usize, T = f64> {
+    pub slope: SVector<T, N>,
+}
+
+impl<const N: usize, T> PReLU<N, T> {
+    /// initial slope
+    pub const SLOPE: f64 = 0.25;
+}
+
+impl<const N: usize, T: Real> Default for PReLU<N, T> {
+    fn default() -> Self {
+        Self { slope: SVector::zeros() }
+    }
+}
+
+impl<const N: usize, T> Params<T> for PReLU<N, T> {
+    #[inline]
+    fn shapes() -> Vec<(usize, usize)> {
+        vec![(N, 0)]
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(&[T], &[T])> {
+        vec![(&[], self.slope.as_slice())]
+    }
+
+    #[inline]
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        vec![(&mut [], self.slope.as_mut_slice())]
+    }
+}
+
+impl<const N: usize, T: Real> Module<T> for PReLU<N, T> {
+    type Input = SVector<T, N>;
+    type Output = SVector<T, N>;
+    type Cache = ();
+
+    fn forward(&self, input: &SVector<T, N>) -> (SVector<T, N>, ()) {
+        (input.zip_map(&self.slope, |x, a| if x > T::zero() { x } else { a * x }), ())
+    }
+
+    fn backward_into(&self, grad: &mut Self, input: &SVector<T, N>, _: &(), d_out: &SVector<T, N>) -> SVector<T, N> {
+        for i in 0..N {
+            if input[i] <= T::zero() {
+                grad.slope[i] += d_out[i] * input[i];
+            }
+        }
+        input.zip_zip_map(&self.slope, d_out, |x, a, d| if x > T::zero() { d } else { a * d })
+    }
+
+    #[inline]
+    fn init_with<R: rand::Rng + ?Sized>(_: Init, _: &mut R) -> Self {
+        Self { slope: SVector::repeat(real(Self::SLOPE)) }
+    }
+}
+
+/// `x` if `x > 0` else `e^x - 1`
+#[derive(Clone, Copy, Default,)]
+pub struct ELU;
+
+impl ActivitionFunc for ELU {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        if x > T::zero() { x } else { x.exp_m1() }
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for ELU {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        if y > T::zero() { T::one() } else { y + T::one() }
+    }
+}
+
+/// Scaled ELU, `λ x` if `x > 0` else `λ α (e^x - 1)`, self-normalizing with `LeCun` init.
+#[derive(Clone, Copy, Default,)]
+pub struct SELU;
+
+impl SELU {
+    pub const LAMBDA: f64 = 1.050_700_987_355_480_5;
+    pub const ALPHA: f64 = 1.673_263_242_354_377_3;
+}
+
+impl ActivitionFunc for SELU {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        let lambda = real::<T>(Self::LAMBDA);
+        if x > T::zero() { lambda * x } else { lambda * real::<T>(Self::ALPHA) * x.exp_m1() }
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for SELU {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        let lambda = real::<T>(Self::LAMBDA);
+        if y > T::zero() { lambda } else { y + lambda * real::<T>(Self::ALPHA) }
+    }
+}
+
+/// `ln(1 + e^x)`, computed as `max(x, 0) + ln(1 + e^-|x|)` so it doesn't overflow
+#[derive(Clone, Copy, Default,)]
+pub struct Softplus;
+
+impl ActivitionFunc for Softplus {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x.max(T::zero()) + (-x.abs()).exp().ln_1p()
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for Softplus {
+    /// `sigmoid(x) = 1 - e^-y`
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        -(-y).exp_m1()
+    }
+}
+
+/// `x` clamped into `[-1, 1]`
+#[derive(Clone, Copy, Default,)]
+pub struct HardTanh;
+
+impl ActivitionFunc for HardTanh {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x.max(-T::one()).min(T::one())
     }
 
     #[inline]
-    fn d_from_y(y: f64) -> f64 {
-        y * (1f64 - y)
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
     }
 }
 
+impl DerivFromY for HardTanh {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        if y.abs() < T::one() { T::one() } else { T::zero() }
+    }
+}
+
+/// `x / 6 + 1 / 2` clamped into `[0, 1]`
+#[derive(Clone, Copy, Default,)]
+pub struct HardSigmoid;
+
+impl ActivitionFunc for HardSigmoid {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        (x / real(6.) + real(0.5)).max(T::zero()).min(T::one())
+    }
+
+    #[inline]
+    fn d<T: Real>(_: T, y: T) -> T {
+        Self::d_from_y(y)
+    }
+}
+
+impl DerivFromY for HardSigmoid {
+    #[inline]
+    fn d_from_y<T: Real>(y: T) -> T {
+        if y > T::zero() && y < T::one() { real(1. / 6.) } else { T::zero() }
+    }
+}
+
+/// Gaussian error linear unit by the tanh approximation,
+/// `x / 2 * (1 + tanh(sqrt(2 / π) * (x + 0.044715 x³)))`
+#[derive(Clone, Copy, Default,)]
+pub struct GELU;
+
+impl GELU {
+    const C: f64 = 0.044_715;
+
+    /// `(inner, tanh(inner))`
+    #[inline]
+    fn tanh<T: Real>(x: T) -> (T, T) {
+        let inner = (real::<T>(2.) / T::pi()).sqrt() * (x + real::<T>(Self::C) * x.powi(3));
+        (inner, inner.tanh())
+    }
+}
+
+impl ActivitionFunc for GELU {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        real::<T>(0.5) * x * (T::one() + Self::tanh(x).1)
+    }
+
+    #[inline]
+    fn d<T: Real>(x: T, _: T) -> T {
+        let half = real::<T>(0.5);
+        let (_, t) = Self::tanh(x);
+        let d_inner = (real::<T>(2.) / T::pi()).sqrt() * (T::one() + real::<T>(3. * Self::C) * x * x);
+        half * (T::one() + t) + half * x * (T::one() - t * t) * d_inner
+    }
+}
+
+/// `x * sigmoid(x)`
+#[derive(Clone, Copy, Default,)]
+pub struct Swish;
+
+/// same as [`Swish`]
+pub type SiLU = Swish;
+
+impl ActivitionFunc for Swish {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x * Sigmoid::f(x)
+    }
+
+    /// `y + sigmoid(x) * (1 - y)`
+    #[inline]
+    fn d<T: Real>(x: T, y: T) -> T {
+        y + Sigmoid::f(x) * (T::one() - y)
+    }
+}
+
+/// `x * tanh(softplus(x))`
+#[derive(Clone, Copy, Default,)]
+pub struct Mish;
+
+impl ActivitionFunc for Mish {
+    #[inline]
+    fn f<T: Real>(x: T) -> T {
+        x * Softplus::f(x).tanh()
+    }
+
+    #[inline]
+    fn d<T: Real>(x: T, _: T) -> T {
+        let t = Softplus::f(x).tanh();
+        t + x * (T::one() - t * t) * Sigmoid::f(x)
+    }
+}
+
+/// `Y` is the label and `y` the output, both `SVector` or both `DVector`.
+/// 
+/// Losses are summed over the outputs of an item, and averaged over items by the trainer.
 pub trait LossFunc {
-    fn f<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> f64;
-    fn d<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> SVector<f64,S>;
+    /// whether the loss applies softmax to the outputs, which should then be the logits
+    /// of layers with a softmax output, see [`Layers::SOFTMAX`](crate::model::Layers::SOFTMAX)
+    const SOFTMAX: bool = false;
+
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>;
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>;
 }
 
+/// Cross entropy of `softmax(y)`, so the output `y` is treated as logits.
+/// 
+/// `d` is the fused gradient `softmax(y) - Y` of softmax and cross entropy with respect
+/// to the logits, which is right for an output layer without activation, e.g. the
+/// `softmax` option of `derive_layers`.
 #[derive(Clone, Copy, Default,)]
 pub struct CrossEntroy;
 
 impl LossFunc for CrossEntroy {
-    fn f<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> f64 {
-        softmax(y)
-            .iter()
-            .zip(Y.iter())
-            .fold(0., |pre, (p, Y)| {
-                pre - Y * p.log2()
-            })
+    const SOFTMAX: bool = true;
+
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        // ln(softmax(y)) = y - max - ln(Σ e^(y - max))
+        let max = y.max();
+        let log_sum = y.fold(T::zero(), |sum, y| sum + (y - max).exp()).ln() + max;
+        y.zip_fold(Y, T::zero(), |pre, y, Y| pre - Y * (y - log_sum))
     }
 
-    fn d<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> SVector<f64,S> {
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
         let mut result = softmax(y);
         result.iter_mut()
             .zip(Y.iter())
             .for_each(|(p, Y)| {
-                *p -= Y
+                *p -= *Y
             });
         result
     }
@@ -81,38 +477,301 @@ pub trait SoftMax<const S: usize> {
     fn softmax(&mut self);
 }
 
-impl<const S: usize> SoftMax<S> for SVector<f64, S>{
+impl<T: Real, const S: usize> SoftMax<S> for SVector<T, S>{
     #[inline]
     fn softmax(&mut self) {
         softmax_with(self)
     }
 }
 
+/// the max is subtracted before `exp`, so large inputs don't overflow
 #[inline]
-pub fn softmax<const S: usize>(x: &SVector<f64,S>) -> SVector<f64,S>  {
-    let mut e = x.map(|val| val.exp());
-    let sum = e.sum();
-    e.iter_mut().for_each(|e| *e /= sum);
+pub fn softmax<T: Real, D: Dim>(x: &OVector<T,D>) -> OVector<T,D>
+where
+    DefaultAllocator: Allocator<T, D>
+{
+    let mut e = x.clone_owned();
+    softmax_with(&mut e);
     e
 }
 
 #[inline]
-pub fn softmax_with<const S: usize>(x: &mut SVector<f64,S>) {
-    x.iter_mut().for_each(|val| *val = val.exp());
+pub fn softmax_with<T: Real, D: Dim>(x: &mut OVector<T,D>)
+where
+    DefaultAllocator: Allocator<T, D>
+{
+    let max = x.max();
+    x.iter_mut().for_each(|val| *val = (*val - max).exp());
     let sum = x.sum();
     x.iter_mut().for_each(|e| *e /= sum);
 }
 
+#[derive(Clone, Copy, Default,)]
 pub struct DistanceFunc;
 
 impl LossFunc for DistanceFunc {
-    fn f<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> f64 {
-        y.iter().zip(Y.iter()).fold(0f64, |pre, (y, Y)| {
-            pre + (y-Y).powi(2)
-        }) / 2f64
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.iter().zip(Y.iter()).fold(T::zero(), |pre, (y, Y)| {
+            pre + (*y-*Y).powi(2)
+        }) / real(2.)
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| y-Y)
+    }
+}
+
+/// `-1`, `0` or `1` by the sign of `x`
+#[inline]
+fn sign<T: Real>(x: T) -> T {
+    if x > T::zero() {
+        T::one()
+    } else if x < T::zero() {
+        -T::one()
+    } else {
+        T::zero()
+    }
+}
+
+/// lower bound of probabilities passed to `ln`
+const EPS: f64 = 1e-12;
+
+/// Absolute error `Σ|y - Y|`, summed over outputs like every loss, so averaged over
+/// items it is the mean absolute error of single outputs.
+#[derive(Clone, Copy, Default,)]
+pub struct MeanAbsError;
+
+impl LossFunc for MeanAbsError {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_fold(Y, T::zero(), |pre, y, Y| pre + (y - Y).abs())
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| sign(y - Y))
+    }
+}
+
+/// Huber loss with `delta = 1`, the same as smooth L1:
+/// `r² / 2` if `|r| <= 1` else `|r| - 1 / 2`, where `r = y - Y`, summed.
+#[derive(Clone, Copy, Default,)]
+pub struct Huber;
+
+impl LossFunc for Huber {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let half = real::<T>(0.5);
+        y.zip_fold(Y, T::zero(), |pre, y, Y| {
+            let r = (y - Y).abs();
+            pre + if r <= T::one() { half * r * r } else { r - half }
+        })
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| (y - Y).max(-T::one()).min(T::one()))
+    }
+}
+
+/// Binary cross entropy of `sigmoid(y)`, so the output `y` is treated as logits
+/// and labels are `0` or `1` per element.
+/// 
+/// Computed as `max(y, 0) - y * Y + ln(1 + e^-|y|)`, which doesn't overflow.
+#[derive(Clone, Copy, Default,)]
+pub struct BceWithLogits;
+
+impl LossFunc for BceWithLogits {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_fold(Y, T::zero(), |pre, y, Y| {
+            pre + y.max(T::zero()) - y * Y + (-y.abs()).exp().ln_1p()
+        })
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| Sigmoid::f(y) - Y)
+    }
+}
+
+/// Cross entropy of probabilities `y`, e.g. outputs of `Sigmoid` normalized to sum 1,
+/// `-Σ Y ln(y)`. Use [`CrossEntroy`] for logits.
+#[derive(Clone, Copy, Default,)]
+pub struct CategoricalCrossEntropy;
+
+impl LossFunc for CategoricalCrossEntropy {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let eps = real::<T>(EPS);
+        y.zip_fold(Y, T::zero(), |pre, y, Y| pre - Y * y.max(eps).ln())
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let eps = real::<T>(EPS);
+        y.zip_map(Y, |y, Y| -Y / y.max(eps))
+    }
+}
+
+/// Hinge loss with labels `-1` or `1` per element, `Σ max(0, 1 - Y * y)`.
+#[derive(Clone, Copy, Default,)]
+pub struct Hinge;
+
+impl LossFunc for Hinge {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_fold(Y, T::zero(), |pre, y, Y| pre + (T::one() - Y * y).max(T::zero()))
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| if Y * y < T::one() { -Y } else { T::zero() })
+    }
+}
+
+/// Squared hinge loss with labels `-1` or `1` per element, `Σ max(0, 1 - Y * y)²`.
+#[derive(Clone, Copy, Default,)]
+pub struct SquaredHinge;
+
+impl LossFunc for SquaredHinge {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_fold(Y, T::zero(), |pre, y, Y| pre + (T::one() - Y * y).max(T::zero()).powi(2))
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| real::<T>(-2.) * Y * (T::one() - Y * y).max(T::zero()))
+    }
+}
+
+/// Kullback-Leibler divergence of probabilities `y` from the label distribution `Y`,
+/// `Σ Y ln(Y / y)`, where terms with `Y = 0` are zero.
+#[derive(Clone, Copy, Default,)]
+pub struct KlDivergence;
+
+impl LossFunc for KlDivergence {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let eps = real::<T>(EPS);
+        y.zip_fold(Y, T::zero(), |pre, y, Y| {
+            if Y > T::zero() { pre + Y * (Y / y.max(eps)).ln() } else { pre }
+        })
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let eps = real::<T>(EPS);
+        y.zip_map(Y, |y, Y| -Y / y.max(eps))
+    }
+}
+
+/// `Σ ln(cosh(y - Y))`, computed as `|r| + ln(1 + e^(-2|r|)) - ln 2` so it doesn't overflow.
+#[derive(Clone, Copy, Default,)]
+pub struct LogCosh;
+
+impl LossFunc for LogCosh {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let ln2 = T::ln_2();
+        y.zip_fold(Y, T::zero(), |pre, y, Y| {
+            let r = (y - Y).abs();
+            pre + r + (real::<T>(-2.) * r).exp().ln_1p() - ln2
+        })
+    }
+
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        y.zip_map(Y, |y, Y| (y - Y).tanh())
+    }
+}
+
+/// Binary focal loss of probabilities `y` with labels `0` or `1` per element,
+/// `alpha = 0.25` and `gamma = 2`:
+/// 
+/// `-Σ alpha Y (1 - y)^gamma ln(y) + (1 - alpha) (1 - Y) y^gamma ln(1 - y)`
+/// 
+/// It focuses on hard items by down-weighting well classified ones.
+#[derive(Clone, Copy, Default,)]
+pub struct Focal;
+
+impl Focal {
+    const ALPHA: f64 = 0.25;
+    const GAMMA: i32 = 2;
+
+    /// `y` clamped into `(0, 1)`
+    #[inline]
+    fn clamp<T: Real>(y: T) -> T {
+        let eps = real::<T>(EPS);
+        y.max(eps).min(T::one() - eps)
+    }
+}
+
+impl LossFunc for Focal {
+    fn f<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> T
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let alpha = real::<T>(Self::ALPHA);
+        y.zip_fold(Y, T::zero(), |pre, y, Y| {
+            let p = Self::clamp(y);
+            let pos = alpha * Y * (T::one() - p).powi(Self::GAMMA) * p.ln();
+            let neg = (T::one() - alpha) * (T::one() - Y) * p.powi(Self::GAMMA) * (T::one() - p).ln();
+            pre - pos - neg
+        })
     }
 
-    fn d<const S: usize>(Y: &SVector<f64,S>, y: &SVector<f64,S>) -> SVector<f64,S> {
-        y.zip_map(&Y, |y, Y| y-Y)
+    fn d<T: Real, D: Dim>(Y: &OVector<T,D>, y: &OVector<T,D>) -> OVector<T,D>
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let alpha = real::<T>(Self::ALPHA);
+        let gamma = real::<T>(Self::GAMMA as f64);
+        y.zip_map(Y, |y, Y| {
+            let p = Self::clamp(y);
+            let q = T::one() - p;
+            let pos = alpha * (gamma * q.powi(Self::GAMMA - 1) * p.ln() - q.powi(Self::GAMMA) / p);
+            let neg = (T::one() - alpha) * (p.powi(Self::GAMMA) / q - gamma * p.powi(Self::GAMMA - 1) * q.ln());
+            Y * pos + (T::one() - Y) * neg
+        })
     }
 }
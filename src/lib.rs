@@ -1,39 +1,64 @@
 use func::*;
 use model::*;
+use optim::*;
+use schedule::*;
 use train::*;
 
 pub use nn_macros::derive_layers;
+pub use train::History;
 
+pub mod check;
+pub mod conv;
+pub mod dynamic;
 pub mod func;
+pub mod metric;
 pub mod model;
+pub mod norm;
+pub mod optim;
+pub mod persist;
+pub mod regularize;
+pub mod schedule;
 mod train;
 
 /// `F`: Activition function
 /// 
+/// `Opt`: Optimizer
+/// 
+/// `Sch`: learn rate scheduler
+/// 
 /// `L`: Layers
 /// 
 /// `S`: input vector size
 /// 
 /// `E`: output vector size
+/// 
+/// `T`: scalar type
 #[derive(Clone)]
-pub struct Network<A, C, L, Cal, const I: usize, const O: usize>
+pub struct Network<A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T = f64>
 where
-    L: Layers<A, Cal, I, O>
+    L: Layers<A, Cal, I, O, T>
 {
     layers: L,
-    config: Config<A, C>,
-    _maker: std::marker::PhantomData<Cal>
+    config: Config<A, C, Opt, Sch>,
+    _maker: std::marker::PhantomData<(Cal, T)>
 }
 
-impl<A, C, L, Cal, const I: usize, const O: usize> Network<A, C, L, Cal, I, O>
+impl<A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> Network<A, C, Opt, Sch, L, Cal, I, O, T>
 where
     A: ActivitionFunc,
     C: LossFunc,
-    L: Layers<A, Cal, I, O>,
-    Cal: Calculation<O>,
+    Opt: Optimizer,
+    Sch: LrScheduler,
+    L: Layers<A, Cal, I, O, T>,
+    Cal: Calculation<O, T>,
+    T: Real,
 {
+    /// fails to compile if loss `C` takes logits of softmax, like `CrossEntroy`, and
+    /// `layers` are not derived with `softmax`
     #[inline]
-    pub fn cfg(layers: L, config: Config<A, C>) -> Network<A, C, L, Cal, I, O> {
+    pub fn cfg(layers: L, config: Config<A, C, Opt, Sch>) -> Network<A, C, Opt, Sch, L, Cal, I, O, T> {
+        #[allow(clippy::let_unit_value)]
+        let () = SoftmaxCheck::<A, C, L, Cal, I, O, T>::OK;
         Network {
             layers,
             config,
@@ -42,9 +67,9 @@ where
     }
 
     #[inline]
-    pub fn train<'a, T>(self, data: T) -> Trainer<'a, A, C, L, Cal, I, O>
+    pub fn train<'a, D>(self, data: D) -> Trainer<'a, A, C, Opt, Sch, L, Cal, I, O, T>
     where
-        T: IntoIterator<Item = &'a self::Item<I, O>>,
+        D: IntoIterator<Item = &'a self::Item<I, O, T>>,
         Self: 'a
     {
         Trainer::new(self.layers, data.into_iter(), self.config)
@@ -52,38 +77,88 @@ where
 
 }
 
-impl<L, Cal, const I: usize, const O: usize> Network<Sigmoid, CrossEntroy, L, Cal, I, O>
+impl<L, Cal, const I: usize, const O: usize, T> Network<Sigmoid, CrossEntroy, Sgd, StepDecay, L, Cal, I, O, T>
 where
-    L: Layers<Sigmoid, Cal, I, O>,
-    Cal: Calculation<O>
+    L: Layers<Sigmoid, Cal, I, O, T>,
+    Cal: Calculation<O, T>,
+    T: Real,
 {
-    /// use default [`Config`]:
+    /// use default [`Config`], whose `CrossEntroy` needs layers derived with `softmax`,
+    /// other layers do not compile and take a loss by [`Network::cfg`], e.g. with
+    /// `Config::default_with_func(Sigmoid, DistanceFunc)`
     #[inline]
-    pub fn new(layers: L) -> Network<Sigmoid, CrossEntroy, L, Cal, I, O> {
+    pub fn new(layers: L) -> Network<Sigmoid, CrossEntroy, Sgd, StepDecay, L, Cal, I, O, T> {
         Network::cfg(layers, Default::default())
     }
 }
 
-pub struct Item<const S: usize, const O: usize> {
-    pub data: SVector<f64, S>,
-    pub label: SVector<f64, O>,
+/// `C` takes logits of softmax only if `L` outputs them
+struct SoftmaxCheck<A, C, L, Cal, const I: usize, const O: usize, T>(std::marker::PhantomData<(A, C, L, Cal, T)>);
+
+impl<A, C, L, Cal, const I: usize, const O: usize, T> SoftmaxCheck<A, C, L, Cal, I, O, T>
+where
+    C: LossFunc,
+    L: Layers<A, Cal, I, O, T>
+{
+    const OK: () = assert!(!C::SOFTMAX || L::SOFTMAX, "the loss takes logits of softmax, derive the layers with `softmax`");
+}
+
+pub struct Item<const S: usize, const O: usize, T = f64> {
+    pub data: SVector<T, S>,
+    pub label: SVector<T, O>,
+    /// scale of the loss and gradient of this item, `1` if `None`
+    pub weight: Option<f64>,
+}
+
+impl<const S: usize, const O: usize, T: Real> Item<S, O, T> {
+    #[inline]
+    pub fn new(data: SVector<T, S>, label: SVector<T, O>) -> Self {
+        Self { data, label, weight: None }
+    }
+
+    #[inline]
+    pub fn with_weight(self, weight: f64) -> Self {
+        Self { weight: Some(weight), ..self }
+    }
+
+    /// class of `label` by [`metric::class_of`]
+    #[inline]
+    pub fn class(&self) -> usize {
+        metric::class_of(&self.label)
+    }
 }
 
 /// default:
 /// 
-/// ```
+/// ```text
 /// learn_rate: 0.005
 /// batch_size: 100
 /// iter_num: 10_000
 /// activition_func: Sigmoid
+/// optimizer: Sgd
+/// scheduler: StepDecay { step_size: 10_000, gamma: 0.5 }
+/// seed: None
+/// regularization: none
+/// class_weights: None
 /// ```
+/// 
+/// During training, `learn_rate` is set to the rate given by `scheduler` before each iter,
+/// so listeners see the current rate.
 #[derive(Clone)]
-pub struct Config<A,C> {
+pub struct Config<A, C, Opt = Sgd, Sch = StepDecay> {
     pub learn_rate: f64,
     pub batch_size: usize,
     pub iter_num: usize,
     pub actvt_func: A,
     pub loss_func: C,
+    pub optimizer: Opt,
+    pub scheduler: Sch,
+    /// seed of the rng used in training, training is reproducible if set
+    pub seed: Option<u64>,
+    /// weight penalties, whose value is included in the training loss
+    pub regularization: Regularization,
+    /// weight of each class, scaling the loss and gradient of items by [`Item::class`]
+    pub class_weights: Option<Vec<f64>>,
 }
 
 impl<A: ActivitionFunc, C: LossFunc> Config<A, C> {
@@ -93,7 +168,67 @@ impl<A: ActivitionFunc, C: LossFunc> Config<A, C> {
             batch_size: 100,
             iter_num: 10_000,
             actvt_func,
-            loss_func: cost_func
+            loss_func: cost_func,
+            optimizer: Sgd,
+            scheduler: StepDecay::default(),
+            seed: None,
+            regularization: Regularization::default(),
+            class_weights: None,
+        }
+    }
+}
+
+impl<A, C, Opt, Sch> Config<A, C, Opt, Sch> {
+    /// replace the optimizer, keeping other settings
+    pub fn with_optimizer<P: Optimizer>(self, optimizer: P) -> Config<A, C, P, Sch> {
+        Config {
+            learn_rate: self.learn_rate,
+            batch_size: self.batch_size,
+            iter_num: self.iter_num,
+            actvt_func: self.actvt_func,
+            loss_func: self.loss_func,
+            optimizer,
+            scheduler: self.scheduler,
+            seed: self.seed,
+            regularization: self.regularization,
+            class_weights: self.class_weights,
+        }
+    }
+
+    /// replace the learn rate scheduler, keeping other settings
+    pub fn with_scheduler<S: LrScheduler>(self, scheduler: S) -> Config<A, C, Opt, S> {
+        Config {
+            learn_rate: self.learn_rate,
+            batch_size: self.batch_size,
+            iter_num: self.iter_num,
+            actvt_func: self.actvt_func,
+            loss_func: self.loss_func,
+            optimizer: self.optimizer,
+            scheduler,
+            seed: self.seed,
+            regularization: self.regularization,
+            class_weights: self.class_weights,
+        }
+    }
+
+    /// weight of `item`, its own weight times the weight of its class
+    pub fn item_weight<const S: usize, const O: usize, T: Real>(&self, item: &Item<S, O, T>) -> f64 {
+        self.weight_of(item.weight, item.class())
+    }
+
+    /// `weight` of an item of `class` times the weight of `class`
+    pub(crate) fn weight_of(&self, weight: Option<f64>, class: usize) -> f64 {
+        let class = self.class_weights.as_ref()
+            .and_then(|w| w.get(class).copied())
+            .unwrap_or(1.);
+        weight.unwrap_or(1.) * class
+    }
+
+    /// rng seeded by `seed`, or from entropy if `seed` is `None`
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
         }
     }
 }
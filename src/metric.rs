@@ -0,0 +1,351 @@
+//! Metrics evaluated on the outputs of an epoch and recorded in [`History`](crate::History).
+//!
+//! Classification metrics take the class of a vector by [`class_of`], so labels are
+//! one-hot vectors, or a single `0`/`1` element for binary classification.
+//!
+//! The metrics of this module are also [`DynMetric`](crate::dynamic::DynMetric)s of
+//! [`DynTrainer`](crate::dynamic::DynTrainer).
+
+use na::{DefaultAllocator, Dim, OVector, SVector, allocator::Allocator};
+
+use crate::model::{Real, to_f64};
+
+/// A value computed from the labels and outputs of all items.
+pub trait Metric<const O: usize, T = f64> {
+    /// key of the metric in [`History`](crate::History)
+    fn name(&self) -> String;
+
+    /// `labels` and `outputs` have the same length and order
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64;
+}
+
+/// index of the largest element, or whether the element is `>= 0.5` if there is only one
+#[inline]
+pub fn class_of<T: Real, D: Dim>(v: &OVector<T, D>) -> usize
+where
+    DefaultAllocator: Allocator<T, D>
+{
+    if v.len() == 1 {
+        (to_f64(v[0]) >= 0.5) as usize
+    } else {
+        v.imax()
+    }
+}
+
+/// Ratio of items whose output class equals the label class.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Accuracy;
+
+impl<const O: usize, T: Real> Metric<O, T> for Accuracy {
+    fn name(&self) -> String {
+        "accuracy".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl Accuracy {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        ConfusionMatrix::new(labels, outputs).accuracy()
+    }
+}
+
+/// Ratio of items whose label class is among the `k` largest outputs.
+/// 
+/// Classes are taken by [`class_of`], so with a single output the label class is the
+/// first of the 2 classes if the output class equals it, and the second otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct TopK(pub usize);
+
+impl<const O: usize, T: Real> Metric<O, T> for TopK {
+    fn name(&self) -> String {
+        format!("top_{}_accuracy", self.0)
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl TopK {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let hits = labels.iter().zip(outputs).filter(|(label, out)| {
+            let class = class_of(*label);
+            // rank of the label class, ties count in favour of it
+            let rank = if out.len() == 1 {
+                (class_of(*out) != class) as usize
+            } else {
+                out.iter().filter(|&&o| o > out[class]).count()
+            };
+            rank < self.0
+        }).count();
+        ratio(hits, labels.len())
+    }
+}
+
+/// Precision averaged over classes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Precision;
+
+impl<const O: usize, T: Real> Metric<O, T> for Precision {
+    fn name(&self) -> String {
+        "precision".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl Precision {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let m = ConfusionMatrix::new(labels, outputs);
+        m.macro_avg(|c| m.precision(c))
+    }
+}
+
+/// Recall averaged over classes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Recall;
+
+impl<const O: usize, T: Real> Metric<O, T> for Recall {
+    fn name(&self) -> String {
+        "recall".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl Recall {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let m = ConfusionMatrix::new(labels, outputs);
+        m.macro_avg(|c| m.recall(c))
+    }
+}
+
+/// F1 score averaged over classes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct F1;
+
+impl<const O: usize, T: Real> Metric<O, T> for F1 {
+    fn name(&self) -> String {
+        "f1".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl F1 {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let m = ConfusionMatrix::new(labels, outputs);
+        m.macro_avg(|c| m.f1(c))
+    }
+}
+
+/// Mean squared error over all elements, `0` without items.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mse;
+
+impl<const O: usize, T: Real> Metric<O, T> for Mse {
+    fn name(&self) -> String {
+        "mse".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl Mse {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let sum = errors(labels, outputs).map(|e| e * e).sum::<f64>();
+        average(sum, labels.iter().map(|l| l.len()).sum())
+    }
+}
+
+/// Mean absolute error over all elements, `0` without items.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mae;
+
+impl<const O: usize, T: Real> Metric<O, T> for Mae {
+    fn name(&self) -> String {
+        "mae".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl Mae {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let sum = errors(labels, outputs).map(f64::abs).sum::<f64>();
+        average(sum, labels.iter().map(|l| l.len()).sum())
+    }
+}
+
+/// Coefficient of determination `1 - SS_res / SS_tot`, where `SS_tot` is taken around
+/// the mean label of each element.
+/// 
+/// If all labels are the same, `SS_tot` is zero and the value is `1` for exact outputs
+/// and `0` otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct R2;
+
+impl<const O: usize, T: Real> Metric<O, T> for R2 {
+    fn name(&self) -> String {
+        "r2".to_owned()
+    }
+
+    fn compute(&self, labels: &[SVector<T, O>], outputs: &[SVector<T, O>]) -> f64 {
+        self.of(labels, outputs)
+    }
+}
+
+impl R2 {
+    pub(crate) fn of<T: Real, D: Dim>(&self, labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> f64
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        let n = labels.len() as f64;
+        let size = labels.first().map_or(0, |l| l.len());
+        let mut mean = vec![0.; size];
+        for l in labels {
+            mean.iter_mut().zip(l.iter()).for_each(|(m, &l)| *m += to_f64(l));
+        }
+        mean.iter_mut().for_each(|m| *m /= n);
+        let ss_tot = labels.iter()
+            .flat_map(|l| l.iter().zip(&mean).map(|(&l, m)| (to_f64(l) - m).powi(2)))
+            .sum::<f64>();
+        let ss_res = errors(labels, outputs).map(|e| e * e).sum::<f64>();
+        if ss_tot == 0. {
+            if ss_res == 0. { 1. } else { 0. }
+        } else {
+            1. - ss_res / ss_tot
+        }
+    }
+}
+
+/// Result of [`Model::evaluate`](crate::model::Model::evaluate).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Evaluation {
+    /// average loss of items, `0` without items
+    pub loss: f64,
+    /// name and value of each metric, in the given order
+    pub metrics: Vec<(String, f64)>,
+}
+
+impl Evaluation {
+    /// value of metric `name`
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+}
+
+/// `output - label` of every element
+fn errors<'v, T: Real, D: Dim>(
+    labels: &'v [OVector<T, D>],
+    outputs: &'v [OVector<T, D>]
+) -> impl Iterator<Item = f64> + 'v
+where
+    DefaultAllocator: Allocator<T, D>
+{
+    labels.iter()
+        .zip(outputs)
+        .flat_map(|(l, o)| l.iter().zip(o.iter()).map(|(&l, &o)| to_f64(o - l)))
+}
+
+/// `part / total`, `0` if `total` is zero
+#[inline]
+pub(crate) fn ratio(part: usize, total: usize) -> f64 {
+    average(part as f64, total)
+}
+
+/// `sum / count`, `0` if `count` is zero
+#[inline]
+pub(crate) fn average(sum: f64, count: usize) -> f64 {
+    if count == 0 { 0. } else { sum / count as f64 }
+}
+
+/// Count of items by label class (rows) and output class (columns).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl ConfusionMatrix {
+    /// classes are taken by [`class_of`], so there are 2 classes if vectors have a single element
+    pub fn new<T: Real, D: Dim>(labels: &[OVector<T, D>], outputs: &[OVector<T, D>]) -> Self
+    where
+        DefaultAllocator: Allocator<T, D>
+    {
+        // the size of runtime vectors is the one of the first label
+        let size = D::try_to_usize().or_else(|| labels.first().map(|l| l.len())).unwrap_or(1);
+        let classes = size.max(2);
+        let mut counts = vec![vec![0; classes]; classes];
+        for (label, out) in labels.iter().zip(outputs) {
+            counts[class_of(label)][class_of(out)] += 1;
+        }
+        Self { counts }
+    }
+
+    #[inline]
+    pub fn classes(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let hits = (0..self.classes()).map(|c| self.counts[c][c]).sum();
+        let total = self.counts.iter().flatten().sum();
+        ratio(hits, total)
+    }
+
+    /// ratio of items output as `class` which are labeled `class`
+    pub fn precision(&self, class: usize) -> f64 {
+        let predicted = self.counts.iter().map(|row| row[class]).sum();
+        ratio(self.counts[class][class], predicted)
+    }
+
+    /// ratio of items labeled `class` which are output as `class`
+    pub fn recall(&self, class: usize) -> f64 {
+        let actual = self.counts[class].iter().sum();
+        ratio(self.counts[class][class], actual)
+    }
+
+    pub fn f1(&self, class: usize) -> f64 {
+        let (p, r) = (self.precision(class), self.recall(class));
+        if p + r == 0. { 0. } else { 2. * p * r / (p + r) }
+    }
+
+    /// average of `f` over classes
+    pub fn macro_avg(&self, f: impl Fn(usize) -> f64) -> f64 {
+        (0..self.classes()).map(f).sum::<f64>() / self.classes() as f64
+    }
+}
@@ -1,146 +1,799 @@
-use std::{marker::PhantomData, ops::{AddAssign, DivAssign, MulAssign, SubAssign}};
+use std::{marker::PhantomData, ops::{AddAssign, DivAssign, MulAssign, SubAssign}, path::Path};
 
-pub use na::{SMatrix, SVector};
+pub use na::{RealField, SMatrix, SVector};
 
-pub struct Model<L, F, C, const I: usize, const O: usize>
+use na::{Const, DMatrix, Dim, Dynamic, Matrix, MatrixSlice, OMatrix, VecStorage};
+use rand_distr::StandardNormal;
+
+pub use crate::optim::{Moments, Optimizer};
+pub use crate::regularize::{Penalty, Regularization};
+pub use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{Item, func::{LossFunc, softmax}, metric::{Evaluation, Metric, average}, persist, train::MaybeSync};
+
+/// Scalar type of parameters, `f32` or `f64`.
+pub trait Real: RealField + Copy {}
+
+impl<T: RealField + Copy> Real for T {}
+
+/// Items of size `S` laid out as columns, one column per item.
+pub type Batch<T, const S: usize> = OMatrix<T, Const<S>, Dynamic>;
+
+/// Heap buffers of batched passes, recycled across calls so that a pass
+/// does not allocate once the buffers are large enough.
+#[derive(Clone, Default)]
+pub struct Buffers<T> {
+    free: Vec<Vec<T>>,
+    rngs: Vec<StdRng>,
+}
+
+impl<T: Real> Buffers<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self { free: Vec::new(), rngs: Vec::new() }
+    }
+
+    /// zero batch of `len` items, backed by a recycled buffer if any
+    pub fn batch<const S: usize>(&mut self, len: usize) -> Batch<T, S> {
+        let data = self.take(S * len);
+        Matrix::from_data(VecStorage::new(Const::<S>, Dynamic::new(len), data))
+    }
+
+    /// keep the buffer of `m` for later [`batch`](Self::batch)
+    #[inline]
+    pub fn recycle<R: Dim, C: Dim>(&mut self, m: Matrix<T, R, C, VecStorage<T, R, C>>) {
+        self.free.push(m.data.into());
+    }
+
+    /// a dropout rng of each item, seeded by `seeds` as the rng of `forward_train`
+    pub fn seed_rngs(&mut self, seeds: &[u64]) -> &mut [StdRng] {
+        self.rngs.clear();
+        self.rngs.extend(seeds.iter().map(|&seed| StdRng::seed_from_u64(seed)));
+        &mut self.rngs
+    }
+
+    /// the rngs of the last [`seed_rngs`](Self::seed_rngs)
+    #[inline]
+    pub fn rngs(&mut self) -> &mut [StdRng] {
+        &mut self.rngs
+    }
+
+    /// smallest free buffer holding `len`, or the largest one grown to `len`
+    fn take(&mut self, len: usize) -> Vec<T> {
+        let fit = (0..self.free.len())
+            .filter(|&i| self.free[i].capacity() >= len)
+            .min_by_key(|&i| self.free[i].capacity())
+            .or_else(|| (0..self.free.len()).max_by_key(|&i| self.free[i].capacity()));
+        let mut data = fit.map(|i| self.free.swap_remove(i)).unwrap_or_default();
+        data.clear();
+        data.resize(len, T::zero());
+        data
+    }
+}
+
+/// convert `x` to `T`
+#[inline]
+pub fn real<T: Real>(x: f64) -> T {
+    na::convert(x)
+}
+
+/// convert `x` from `T` to `f64`
+#[inline]
+pub fn to_f64<T: Real>(x: T) -> f64 {
+    x.to_subset_unchecked()
+}
+
+pub struct Model<L, F, C, const I: usize, const O: usize, T = f64>
 where
-    L: Layers<F, C, I, O>,
-    C: Calculation<O>
+    L: Layers<F, C, I, O, T>,
+    C: Calculation<O, T>,
+    T: Real
 {
     pub layers: L,
-    _maker: PhantomData<(F,C)>,
+    /// the config of training, stored by [`save`](Self::save)
+    pub config: Option<persist::ConfigMeta>,
+    _maker: PhantomData<(F,C,T)>,
 }
 
-impl<L, F, C, const I: usize, const O: usize> Model<L, F, C, I, O> 
+impl<L, F, C, const I: usize, const O: usize, T> Model<L, F, C, I, O, T>
 where
-    L: Layers<F, C, I, O>,
-    C: Calculation<O>
+    L: Layers<F, C, I, O, T>,
+    C: Calculation<O, T>,
+    T: Real
 {
     #[inline]
     pub fn new(layers: L) -> Self {
         Self {
             layers,
+            config: None,
             _maker: Default::default()
         }
     }
 
+    /// the model with `config` to store along with the layers
+    #[inline]
+    pub fn with_config(self, config: persist::ConfigMeta) -> Self {
+        Self { config: Some(config), ..self }
+    }
+
     #[inline]
-    pub fn test(&self, item: &SVector<f64, I>) -> SVector<f64,O> {
+    pub fn test(&self, item: &SVector<T, I>) -> SVector<T,O> {
         self.layers.test(item)
     }
+
+    /// probability of each class, see [`SoftmaxLayers::predict_proba`]
+    #[inline]
+    pub fn predict_proba(&self, item: &SVector<T, I>) -> SVector<T,O>
+    where
+        L: SoftmaxLayers<F, C, I, O, T>
+    {
+        self.layers.predict_proba(item)
+    }
+
+    /// outputs of all `inputs` in order, on multiple threads with feature `parallel`
+    pub fn predict_batch<'i, D>(&self, inputs: D) -> Vec<SVector<T, O>>
+    where
+        D: IntoIterator<Item = &'i SVector<T, I>>,
+        L: MaybeSync,
+        T: MaybeSync + 'i
+    {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let layers = &self.layers;
+        map_items(&inputs, |x| <L as Layers<F, C, I, O, T>>::test(layers, x))
+    }
+
+    /// average loss `Loss` and `metrics` of `items`, item weights are ignored
+    /// 
+    /// `_loss` is only for its type, e.g. `model.evaluate(items, DistanceFunc, &[&Mse])`
+    pub fn evaluate<Loss: LossFunc>(
+        &self,
+        items: &[Item<I, O, T>],
+        _loss: Loss,
+        metrics: &[&dyn Metric<O, T>]
+    ) -> Evaluation
+    where
+        L: MaybeSync,
+        T: MaybeSync
+    {
+        let outputs = self.predict_batch(items.iter().map(|item| &item.data));
+        let labels = items.iter().map(|item| item.label).collect::<Vec<_>>();
+        let loss = labels.iter()
+            .zip(outputs.iter())
+            .map(|(label, out)| to_f64(Loss::f(label, out)))
+            .sum::<f64>();
+        let loss = average(loss, items.len());
+        let metrics = metrics.iter()
+            .map(|m| (m.name(), m.compute(&labels, &outputs)))
+            .collect();
+        Evaluation { loss, metrics }
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_items<X, Y>(items: &[X], f: impl Fn(&X) -> Y) -> Vec<Y> {
+    items.iter().map(f).collect()
+}
+
+#[cfg(feature = "parallel")]
+pub(crate) fn map_items<X: Sync, Y: Send>(items: &[X], f: impl Fn(&X) -> Y + Sync + Send) -> Vec<Y> {
+    use rayon::prelude::*;
+
+    items.par_iter().map(f).collect()
+}
+
+impl<L, F, C, const I: usize, const O: usize, T> Model<L, F, C, I, O, T>
+where
+    L: Layers<F, C, I, O, T> + Params<T>,
+    C: Calculation<O, T>,
+    T: Real
+{
+    /// save layers and config to `path` in binary format
+    #[inline]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> persist::Result<()> {
+        persist::save(&self.layers, self.config.as_ref(), path)
+    }
+
+    /// save layers and config to `path` in JSON format
+    #[inline]
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> persist::Result<()> {
+        persist::save_json(&self.layers, self.config.as_ref(), path)
+    }
+
+    /// load layers from binary file `path`, fails if shapes of layers differ
+    #[inline]
+    pub fn load<P: AsRef<Path>>(path: P) -> persist::Result<Self>
+    where
+        L: Default
+    {
+        persist::load(path).map(Self::from)
+    }
+
+    /// load layers from JSON file `path`, fails if shapes of layers differ
+    #[inline]
+    pub fn load_json<P: AsRef<Path>>(path: P) -> persist::Result<Self>
+    where
+        L: Default
+    {
+        persist::load_json(path).map(Self::from)
+    }
+}
+
+impl<L, F, C, const I: usize, const O: usize, T> From<persist::Saved<L>> for Model<L, F, C, I, O, T>
+where
+    L: Layers<F, C, I, O, T>,
+    C: Calculation<O, T>,
+    T: Real
+{
+    #[inline]
+    fn from(saved: persist::Saved<L>) -> Self {
+        Self { layers: saved.layers, config: saved.config, _maker: PhantomData }
+    }
 }
 
 /// `I`: input vec size
 /// 
 /// `O` output vec size
-pub trait Layers<F, C, const I: usize, const O: usize> {
+/// 
+/// `T`: scalar type
+pub trait Layers<F, C, const I: usize, const O: usize, T = f64> {
+
+    /// whether the last layer outputs logits of softmax, as with the `softmax` argument
+    /// of `derive_layers`
+    const SOFTMAX: bool = false;
 
     /// go forward and get calculation result of all layers
-    fn forward(&self, item: &SVector<f64,I>) -> C;
+    fn forward(&self, item: &SVector<T,I>) -> C;
+
+    /// go forward as in training, where dropout is applied with `rng`
+    /// 
+    /// same as `forward` by default
+    fn forward_train<R: Rng + ?Sized>(&self, item: &SVector<T,I>, _rng: &mut R) -> C {
+        self.forward(item)
+    }
 
     /// backward and get gradient
     /// 
     /// `gradient`: Partial derivative of `E` with respect to `out`, where E is loss function
-    fn backward(&self, input: &SVector<f64,I>, gradient: SVector<f64, O>, calc: C) -> Self;
+    fn backward(&self, input: &SVector<T,I>, gradient: SVector<T, O>, calc: C) -> Self;
 
+    /// average `gradients` and apply them with `optimizer`
+    /// 
     /// `rate`: learn rate
-    fn update(&mut self, rate: f64, gradients: impl IntoIterator<Item = Self>);
+    /// 
+    /// `moments`: optimizer state, shaped the same as `Self`
+    /// 
+    /// `regularization`: penalties added to the averaged gradients
+    fn update<Opt: Optimizer>(
+        &mut self,
+        rate: f64,
+        optimizer: &Opt,
+        moments: &mut Moments<Self>,
+        regularization: &Regularization,
+        gradients: impl IntoIterator<Item = Self>
+    ) where Self: Sized;
+
+    /// `backward` and add the gradient to `grad` instead of returning it
+    /// 
+    /// by default the gradient of `backward` is added through `Params`, derived layers
+    /// override it so that nothing is allocated
+    fn backward_into(&self, grad: &mut Self, input: &SVector<T,I>, gradient: SVector<T, O>, calc: C)
+    where
+        Self: Params<T> + Sized,
+        T: Real
+    {
+        let g = self.backward(input, gradient, calc);
+        for ((sw, sb), (w, b)) in grad.params_mut().into_iter().zip(g.params()) {
+            sw.iter_mut().chain(sb.iter_mut()).zip(w.iter().chain(b)).for_each(|(s, &g)| *s += g);
+        }
+    }
+
+    /// go forward as in training with a column per item of `inputs`, and backward the
+    /// output gradients that `d_out` writes into its second argument from the outputs
+    /// 
+    /// `seeds`: seed of the dropout rng of each item, used as the rng of `forward_train`
+    /// 
+    /// the outputs are written to `outputs`, and the sum of the item gradients is added to `grad`
+    /// 
+    /// by default items go through `forward_train` and `backward_into` one by one, keeping
+    /// their calculations in a `Vec`. layers derived for a layer count override it with a
+    /// matrix product over the whole batch per layer, whose matrices are taken from `buffers`
+    /// so that nothing is allocated once the buffers are warm
+    fn backprop_batch_into(
+        &self,
+        grad: &mut Self,
+        inputs: &Batch<T, I>,
+        seeds: &[u64],
+        outputs: &mut Batch<T, O>,
+        d_out: impl FnOnce(&Batch<T, O>, &mut Batch<T, O>),
+        buffers: &mut Buffers<T>
+    )
+    where
+        Self: Params<T> + Sized,
+        C: Calculation<O, T>,
+        T: Real
+    {
+        let calcs = inputs.column_iter()
+            .zip(seeds)
+            .map(|(x, &seed)| self.forward_train(&x.into_owned(), &mut StdRng::seed_from_u64(seed)))
+            .collect::<Vec<_>>();
+        for (mut out, calc) in outputs.column_iter_mut().zip(calcs.iter()) {
+            out.copy_from(calc.out());
+        }
+        let mut k = buffers.batch::<O>(inputs.ncols());
+        d_out(outputs, &mut k);
+
+        for ((x, k), calc) in inputs.column_iter().zip(k.column_iter()).zip(calcs) {
+            self.backward_into(grad, &x.into_owned(), k.into_owned(), calc);
+        }
+        buffers.recycle(k);
+    }
+
+    /// go forward as in training with a column per item of `inputs`, and backward the
+    /// output gradients returned by `d_out` from the outputs
+    /// 
+    /// `seeds`: seed of the dropout rng of each item, used as the rng of `forward_train`
+    /// 
+    /// returns the outputs and the average gradient of the items, through
+    /// [`backprop_batch_into`](Self::backprop_batch_into) with fresh buffers
+    fn backprop_batch(
+        &self,
+        inputs: &Batch<T, I>,
+        seeds: &[u64],
+        d_out: impl FnOnce(&Batch<T, O>) -> Batch<T, O>
+    ) -> (Batch<T, O>, Self)
+    where
+        Self: Params<T> + Default + Sized,
+        C: Calculation<O, T>,
+        T: Real
+    {
+        let mut grad = Self::default();
+        let mut outputs = Batch::zeros(inputs.ncols());
+        self.backprop_batch_into(&mut grad, inputs, seeds, &mut outputs, |out, k| k.copy_from(&d_out(out)), &mut Buffers::new());
+
+        let len = real::<T>(inputs.ncols() as f64);
+        for (w, b) in grad.params_mut() {
+            w.iter_mut().chain(b.iter_mut()).for_each(|s| *s /= len);
+        }
+        (outputs, grad)
+    }
+
+    fn test(&self, item: &SVector<T, I>) -> SVector<T,O>;
+
+}
+
+/// Layers whose last layer outputs logits of softmax, implemented by `derive_layers` with
+/// the `softmax` argument.
+pub trait SoftmaxLayers<F, C, const I: usize, const O: usize, T = f64>: Layers<F, C, I, O, T> {
+    /// probability of each class, softmax of the logits of `test`
+    #[inline]
+    fn predict_proba(&self, item: &SVector<T, I>) -> SVector<T, O>
+    where
+        T: Real
+    {
+        softmax(&self.test(item))
+    }
+}
+
+/// Access to the parameters of every layer, in order from input to output.
+/// 
+/// Weights are column-major, as stored by nalgebra.
+pub trait Params<T = f64> {
+    /// `(rows, cols)` of the weight matrix of each layer
+    /// 
+    /// the weight of a dense layer has `rows * cols` params and its bias `rows`. other
+    /// layers may have params of other lengths, such as a slope in the bias of shape
+    /// `(N, 0)`, which are saved as they are, but they are not layers of a `DynNetwork`
+    fn shapes() -> Vec<(usize, usize)>;
 
-    fn test(&self, item: &SVector<f64, I>) -> SVector<f64,O>;
+    /// `(w, b)` of each layer
+    fn params(&self) -> Vec<(&[T], &[T])>;
 
+    /// `(w, b)` of each layer
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])>;
+
+    /// layer, starting from 1, of each entry of `params`, whose penalty is
+    /// [`Regularization::layer`] of it
+    /// 
+    /// the position of the entry by default, networks declared by `derive_layers` give
+    /// the field of the entry, counting layers without params like pooling
+    fn layers() -> Vec<usize> {
+        (1..=Self::shapes().len()).collect()
+    }
 }
 
-pub trait Calculation<const O: usize> {
-    fn out(&self) -> &SVector<f64,O>;
+pub trait Calculation<const O: usize, T = f64> {
+    fn out(&self) -> &SVector<T,O>;
+}
+
+/// A single layer with its own forward and backward pass, e.g. a dense [`Layer`]
+/// or the layers in [`conv`](crate::conv), which can be a field of a network declared
+/// with [`derive_layers`](crate::derive_layers).
+/// 
+/// `Input` and `Output` are `SVector`s, so that the output of a layer is the input of the next.
+pub trait Module<T = f64>: Params<T> + Clone + Default {
+    type Input;
+    type Output;
+    /// what `backward_into` needs from `forward` besides the input
+    type Cache: Clone;
+
+    fn forward(&self, input: &Self::Input) -> (Self::Output, Self::Cache);
+
+    /// go forward as in training, same as `forward` by default
+    fn forward_train<R: Rng + ?Sized>(&self, input: &Self::Input, _rng: &mut R) -> (Self::Output, Self::Cache) {
+        self.forward(input)
+    }
+
+    fn test(&self, input: &Self::Input) -> Self::Output {
+        self.forward(input).0
+    }
+
+    /// add the gradient of parameters to `grad` and return the gradient of `input`,
+    /// where `d_out` is the gradient of the output
+    fn backward_into(&self, grad: &mut Self, input: &Self::Input, cache: &Self::Cache, d_out: &Self::Output) -> Self::Input;
+
+    /// weights initialized by `init`, biases are zero
+    fn init_with<R: Rng + ?Sized>(init: Init, rng: &mut R) -> Self;
+
+    /// all parameters uniform in `[0, 1)` by default, as [`Layer::random_with`]
+    fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::init_with(Init::Uniform, rng)
+    }
+
+    /// apply `grad` to all parameters with `optimizer`, `m` and `v` are the moment buffers
+    fn optimize<Opt: Optimizer>(
+        &mut self,
+        optimizer: &Opt,
+        t: usize,
+        rate: f64,
+        grad: &Self,
+        m: &mut Self,
+        v: &mut Self
+    )
+    where
+        T: Real
+    {
+        let rate = real(rate);
+        let layers = self.params_mut().into_iter().zip(grad.params()).zip(m.params_mut()).zip(v.params_mut());
+        for ((((pw, pb), (gw, gb)), (mw, mb)), (vw, vb)) in layers {
+            let params = pw.iter_mut().chain(pb.iter_mut());
+            let grads = gw.iter().chain(gb.iter());
+            let ms = mw.iter_mut().chain(mb.iter_mut());
+            let vs = vw.iter_mut().chain(vb.iter_mut());
+            for (((p, g), m), v) in params.zip(grads).zip(ms).zip(vs) {
+                optimizer.step(t, rate, p, *g, m, v);
+            }
+        }
+    }
+
+    /// add the gradient of `penalty` on `params` to this gradient
+    fn penalize(&mut self, params: &Self, penalty: Penalty, include_bias: bool)
+    where
+        T: Real
+    {
+        if penalty.is_none() {
+            return;
+        }
+        for ((gw, gb), (pw, pb)) in self.params_mut().into_iter().zip(params.params()) {
+            gw.iter_mut().zip(pw).for_each(|(g, &p)| *g += penalty.d(p));
+            if include_bias {
+                gb.iter_mut().zip(pb).for_each(|(g, &p)| *g += penalty.d(p));
+            }
+        }
+    }
 }
 
 /// `S`: cur layer size
 /// 
 /// `P`: pre layer size
+/// 
+/// `T`: scalar type
 #[derive(Clone)]
-pub struct Layer<const S: usize, const P: usize> {
-    pub w: SMatrix<f64, S, P>,
-    pub b: SVector<f64, S>,
+pub struct Layer<const S: usize, const P: usize, T = f64> {
+    pub w: SMatrix<T, S, P>,
+    pub b: SVector<T, S>,
 }
 
-impl<const S: usize, const P: usize> Layer<S, P> {
+impl<const S: usize, const P: usize, T: Real> Layer<S, P, T> {
 
     #[inline]
     pub fn new() -> Self {
         Self {
-            w: SMatrix::repeat(0f64),
-            b: SVector::repeat(0f64), 
+            w: SMatrix::repeat(T::zero()),
+            b: SVector::repeat(T::zero()),
         }
     }
 
     #[inline]
-    pub fn calc(&self, input: &SVector<f64,P>) -> SVector<f64,S> {
+    pub fn calc(&self, input: &SVector<T,P>) -> SVector<T,S> {
         self.w * input + self.b
     }
 
+    /// `calc` of every column of `inputs` written to `out`, as a single matrix product
+    #[inline]
+    pub fn calc_batch_to(&self, inputs: &Batch<T,P>, out: &mut Batch<T,S>) {
+        out.column_iter_mut().for_each(|mut z| z.copy_from(&self.b));
+        out.gemm(T::one(), &self.w, inputs, T::one());
+    }
+
+    /// gradient of the inputs of every column written to `out`, where `delta` is
+    /// the gradient of `z`
+    #[inline]
+    pub fn back_batch_to(&self, delta: &Batch<T,S>, out: &mut Batch<T,P>) {
+        out.gemm_tr(T::one(), &self.w, delta, T::zero());
+    }
+
+    /// add the gradient of every column to `self`, where `delta` is the gradient of `z`
+    /// and `inputs` the inputs of the layer
+    ///
+    /// the weights take a single product of `delta` and the transpose of `inputs`, which is
+    /// a view of the same data instead of a copy
+    #[inline]
+    pub fn accumulate_batch(&mut self, delta: &Batch<T,S>, inputs: &Batch<T,P>) {
+        let inputs_tr = MatrixSlice::from_slice_with_strides_generic(
+            inputs.as_slice(), Dynamic::new(inputs.ncols()), Const::<P>, Const::<P>, Const::<1>
+        );
+        self.w.gemm(T::one(), delta, &inputs_tr, T::one());
+        self.b += delta.column_sum();
+    }
+
+    /// apply `grad` to all parameters with `optimizer`, `m` and `v` are the moment buffers
+    pub fn optimize<Opt: Optimizer>(
+        &mut self,
+        optimizer: &Opt,
+        t: usize,
+        rate: f64,
+        grad: &Self,
+        m: &mut Self,
+        v: &mut Self
+    ) {
+        let rate = real(rate);
+        let params = self.w.iter_mut().chain(self.b.iter_mut());
+        let grads = grad.w.iter().chain(grad.b.iter());
+        let ms = m.w.iter_mut().chain(m.b.iter_mut());
+        let vs = v.w.iter_mut().chain(v.b.iter_mut());
+        for (((p, g), m), v) in params.zip(grads).zip(ms).zip(vs) {
+            optimizer.step(t, rate, p, *g, m, v);
+        }
+    }
+
+    /// add the gradient of `penalty` on `params` to this gradient
+    pub fn penalize(&mut self, params: &Self, penalty: Penalty, include_bias: bool) {
+        if penalty.is_none() {
+            return;
+        }
+        self.w.zip_apply(&params.w, |g, p| *g += penalty.d(p));
+        if include_bias {
+            self.b.zip_apply(&params.b, |g, p| *g += penalty.d(p));
+        }
+    }
+
     pub fn random() -> Self {
-        let iter = (1..).map(|_| rand::random::<f64>());
+        Self::random_with(&mut rand::thread_rng())
+    }
+
+    /// same as [`random`](Self::random), drawing values from `rng`
+    pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let w = SMatrix::from_fn(|_, _| real(rng.gen::<f64>()));
+        let b = SVector::from_fn(|_, _| real(rng.gen::<f64>()));
+        Self { w, b }
+    }
+
+    /// weights initialized by `init`, biases are zero
+    pub fn init_with<R: Rng + ?Sized>(init: Init, rng: &mut R) -> Self {
         Self {
-            w: SMatrix::from_iterator(iter.clone()),
-            b: SVector::from_iterator(iter),
+            w: init.weights::<R, S, P>(rng).map(real),
+            b: SVector::repeat(T::zero()),
+        }
+    }
+
+    /// convert parameters to scalar type `U`, e.g. `f32` for deployment
+    pub fn cast<U: Real>(&self) -> Layer<S, P, U> {
+        Layer {
+            w: self.w.map(|v| real(to_f64(v))),
+            b: self.b.map(|v| real(to_f64(v))),
+        }
+    }
+}
+
+/// Weight initialization scheme of a [`Layer`].
+/// 
+/// `fan_in` is the pre layer size `P`, `fan_out` is the layer size `S`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Init {
+    /// uniform in `[0, 1)`, same as [`Layer::random`]
+    Uniform,
+    /// uniform in `±sqrt(6 / (fan_in + fan_out))`, suits Tanh and Sigmoid
+    XavierUniform,
+    /// normal with std `sqrt(2 / (fan_in + fan_out))`
+    XavierNormal,
+    /// uniform in `±sqrt(6 / fan_in)`, suits ReLU
+    HeUniform,
+    /// normal with std `sqrt(2 / fan_in)`
+    HeNormal,
+    /// uniform in `±sqrt(3 / fan_in)`, suits SELU
+    LeCunUniform,
+    /// normal with std `sqrt(1 / fan_in)`
+    LeCunNormal,
+    /// (semi-)orthogonal matrix from the QR decomposition of a normal matrix
+    Orthogonal,
+    Zeros,
+}
+
+impl Init {
+    /// random weight matrix of a layer with size `S` and pre layer size `P`
+    pub fn weights<R: Rng + ?Sized, const S: usize, const P: usize>(self, rng: &mut R) -> SMatrix<f64, S, P> {
+        let w = self.weights_dyn(S, P, rng);
+        SMatrix::from_fn(|i, j| w[(i, j)])
+    }
+
+    /// same as [`weights`](Self::weights) with runtime sizes, `rows` is the layer size
+    /// and `cols` the pre layer size
+    pub fn weights_dyn<R: Rng + ?Sized>(self, rows: usize, cols: usize, rng: &mut R) -> DMatrix<f64> {
+        let fan_in = cols as f64;
+        let fan_out = rows as f64;
+        let mut uniform = |limit: f64| DMatrix::from_fn(rows, cols, |_, _| rng.gen_range(-limit..=limit));
+        match self {
+            Init::Uniform => DMatrix::from_fn(rows, cols, |_, _| rng.gen::<f64>()),
+            Init::XavierUniform => uniform((6. / (fan_in + fan_out)).sqrt()),
+            Init::HeUniform => uniform((6. / fan_in).sqrt()),
+            Init::LeCunUniform => uniform((3. / fan_in).sqrt()),
+            Init::XavierNormal => normal(rng, rows, cols, (2. / (fan_in + fan_out)).sqrt()),
+            Init::HeNormal => normal(rng, rows, cols, (2. / fan_in).sqrt()),
+            Init::LeCunNormal => normal(rng, rows, cols, (1. / fan_in).sqrt()),
+            Init::Orthogonal => orthogonal(rng, rows, cols),
+            Init::Zeros => DMatrix::zeros(rows, cols),
+        }
+    }
+}
+
+#[inline]
+fn normal<R: Rng + ?Sized>(rng: &mut R, rows: usize, cols: usize, std: f64) -> DMatrix<f64> {
+    DMatrix::from_fn(rows, cols, |_, _| rng.sample::<f64, _>(StandardNormal) * std)
+}
+
+/// rows are orthonormal if `rows <= cols`, otherwise columns are
+fn orthogonal<R: Rng + ?Sized>(rng: &mut R, rows: usize, cols: usize) -> DMatrix<f64> {
+    let a = DMatrix::from_fn(rows.max(cols), rows.min(cols), |_, _| rng.sample::<f64, _>(StandardNormal));
+    let (mut q, r) = a.qr().unpack();
+    // make the decomposition unique, so that q is uniformly distributed
+    for (i, mut col) in q.column_iter_mut().enumerate() {
+        if r[(i, i)] < 0. {
+            col.neg_mut();
         }
     }
+    if rows >= cols { q } else { q.transpose() }
 }
 
-impl<const S: usize, const P: usize> Default for Layer<S, P> {
+/// Inverted dropout mask: each element is zero with probability `rate`, otherwise `1 / (1 - rate)`,
+/// so that the expected output is kept and nothing needs to be scaled in test.
+pub fn dropout_mask<T: Real, R: Rng + ?Sized, const S: usize>(rate: f64, rng: &mut R) -> SVector<T, S> {
+    let keep = real::<T>(1. / (1. - rate));
+    SVector::from_fn(|_, _| if rng.gen::<f64>() < rate { T::zero() } else { keep })
+}
+
+/// [`dropout_mask`] of each column, drawn from the rng of the column in `rngs`
+pub fn dropout_mask_batch<T: Real, R: Rng, const S: usize>(rate: f64, rngs: &mut [R]) -> Batch<T, S> {
+    let mut mask = Batch::zeros(rngs.len());
+    dropout_mask_batch_to(rate, rngs, &mut mask);
+    mask
+}
+
+/// [`dropout_mask_batch`] written to `mask`
+pub fn dropout_mask_batch_to<T: Real, R: Rng, const S: usize>(rate: f64, rngs: &mut [R], mask: &mut Batch<T, S>) {
+    for (mut col, rng) in mask.column_iter_mut().zip(rngs) {
+        col.copy_from(&dropout_mask::<T, _, S>(rate, rng));
+    }
+}
+
+impl<const S: usize, const P: usize, T: Real> Default for Layer<S, P, T> {
     #[inline]
     fn default() -> Self {
         Self {
-            w: SMatrix::repeat(0f64),
-            b: SVector::repeat(0f64),
+            w: SMatrix::repeat(T::zero()),
+            b: SVector::repeat(T::zero()),
         }
     }
 }
 
-impl<const S: usize, const P: usize> AsRef<Self> for Layer<S, P> {
+impl<const S: usize, const P: usize, T: Real> Params<T> for Layer<S, P, T> {
+    #[inline]
+    fn shapes() -> Vec<(usize, usize)> {
+        vec![(S, P)]
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(&[T], &[T])> {
+        vec![(self.w.as_slice(), self.b.as_slice())]
+    }
+
+    #[inline]
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        vec![(self.w.as_mut_slice(), self.b.as_mut_slice())]
+    }
+}
+
+impl<const S: usize, const P: usize, T> AsRef<Self> for Layer<S, P, T> {
     #[inline]
     fn as_ref(&self) -> &Self {
-        &self
+        self
     }
 }
 
-impl<T: AsRef<Self>,const S: usize, const P: usize> AddAssign<&T> for Layer<S, P>{
+impl<R: AsRef<Self>, const S: usize, const P: usize, T: Real> AddAssign<&R> for Layer<S, P, T>{
     #[inline]
-    fn add_assign(&mut self, rhs: &T) {
+    fn add_assign(&mut self, rhs: &R) {
         let rhs = rhs.as_ref();
         self.w += rhs.w;
         self.b += rhs.b;
     }
 }
 
-impl<const S: usize, const P: usize> SubAssign<(&SMatrix<f64, S, P>, &SVector<f64, S>)> for Layer<S, P>{
-    fn sub_assign(&mut self, rhs: (&SMatrix<f64, S, P>, &SVector<f64, S>)) {
+impl<const S: usize, const P: usize, T: Real> SubAssign<(&SMatrix<T, S, P>, &SVector<T, S>)> for Layer<S, P, T>{
+    fn sub_assign(&mut self, rhs: (&SMatrix<T, S, P>, &SVector<T, S>)) {
         self.w -= rhs.0;
         self.b -= rhs.1;
     }
 }
 
-impl<T: AsRef<Self>, const S: usize, const P: usize> SubAssign<&T> for Layer<S, P>{
+impl<R: AsRef<Self>, const S: usize, const P: usize, T: Real> SubAssign<&R> for Layer<S, P, T>{
     #[inline]
-    fn sub_assign(&mut self, rhs: &T) {
+    fn sub_assign(&mut self, rhs: &R) {
         let rhs = rhs.as_ref();
         self.w -= rhs.w;
         self.b -= rhs.b;
     }
 }
 
-impl<const S: usize, const P: usize> DivAssign<f64> for Layer<S, P>{
+impl<const S: usize, const P: usize, T: Real> DivAssign<T> for Layer<S, P, T>{
     #[inline]
-    fn div_assign(&mut self, rhs: f64) {
+    fn div_assign(&mut self, rhs: T) {
         self.w /= rhs;
         self.b /= rhs;
     }
 }
 
-impl<const S: usize, const P: usize> MulAssign<f64> for Layer<S, P>{
+impl<const S: usize, const P: usize, T: Real> MulAssign<T> for Layer<S, P, T>{
     #[inline]
-    fn mul_assign(&mut self, rhs: f64) {
+    fn mul_assign(&mut self, rhs: T) {
         self.w *= rhs;
         self.b *= rhs;
     }
 }
+
+impl<const S: usize, const P: usize, T: Real> Module<T> for Layer<S, P, T> {
+    type Input = SVector<T, P>;
+    type Output = SVector<T, S>;
+    type Cache = ();
+
+    #[inline]
+    fn forward(&self, input: &SVector<T, P>) -> (SVector<T, S>, ()) {
+        (self.calc(input), ())
+    }
+
+    #[inline]
+    fn backward_into(&self, grad: &mut Self, input: &SVector<T, P>, _: &(), d_out: &SVector<T, S>) -> SVector<T, P> {
+        grad.w.ger(T::one(), d_out, input, T::one());
+        grad.b += d_out;
+        self.w.tr_mul(d_out)
+    }
+
+    #[inline]
+    fn init_with<R: Rng + ?Sized>(init: Init, rng: &mut R) -> Self {
+        Layer::init_with(init, rng)
+    }
+
+    #[inline]
+    fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Layer::random_with(rng)
+    }
+
+    #[inline]
+    fn optimize<Opt: Optimizer>(&mut self, optimizer: &Opt, t: usize, rate: f64, grad: &Self, m: &mut Self, v: &mut Self) {
+        Layer::optimize(self, optimizer, t, rate, grad, m, v)
+    }
+
+    #[inline]
+    fn penalize(&mut self, params: &Self, penalty: Penalty, include_bias: bool) {
+        Layer::penalize(self, params, penalty, include_bias)
+    }
+}
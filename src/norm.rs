@@ -0,0 +1,91 @@
+//! Normalization layers, which implement [`Module`].
+//!
+//! They are fields of networks declared with [`derive_layers`](crate::derive_layers),
+//! usually between a dense layer and its activation:
+//!
+//! ```ignore
+//! #[derive_layers(input(8), output(2))]
+//! struct Normed {
+//!     hidden: Layer<16, 8, T>,
+//!     #[activation(ReLU)]
+//!     norm: LayerNorm<16, T>,
+//!     #[activation(Sigmoid)]
+//!     out: Layer<2, 16, T>,
+//! }
+//! ```
+
+use crate::model::{Init, Module, Params, Real, SVector, real};
+
+/// added to the variance so that constant inputs are not divided by zero
+const EPS: f64 = 1e-5;
+
+/// Normalization of each item to zero mean and unit variance over its `N` features,
+/// then scaled by `gamma` and shifted by `beta`, which are learned.
+///
+/// As params `gamma` is the weight of shape `(N, 1)` and `beta` the bias. `Default` is
+/// zero as other layers, since it is also the gradient, `init_with` starts from
+/// `gamma = 1, beta = 0` whatever the scheme.
+#[derive(Clone, Debug)]
+pub struct LayerNorm<const N: usize, T = f64> {
+    pub gamma: SVector<T, N>,
+    pub beta: SVector<T, N>,
+}
+
+impl<const N: usize, T: Real> Default for LayerNorm<N, T> {
+    fn default() -> Self {
+        Self { gamma: SVector::zeros(), beta: SVector::zeros() }
+    }
+}
+
+impl<const N: usize, T> Params<T> for LayerNorm<N, T> {
+    #[inline]
+    fn shapes() -> Vec<(usize, usize)> {
+        vec![(N, 1)]
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(&[T], &[T])> {
+        vec![(self.gamma.as_slice(), self.beta.as_slice())]
+    }
+
+    #[inline]
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        vec![(self.gamma.as_mut_slice(), self.beta.as_mut_slice())]
+    }
+}
+
+impl<const N: usize, T: Real> Module<T> for LayerNorm<N, T> {
+    type Input = SVector<T, N>;
+    type Output = SVector<T, N>;
+    /// the normalized input and the inverse of its standard deviation
+    type Cache = (SVector<T, N>, T);
+
+    fn forward(&self, input: &SVector<T, N>) -> (SVector<T, N>, (SVector<T, N>, T)) {
+        let n = real::<T>(N as f64);
+        let centered = input.add_scalar(-input.sum() / n);
+        let inv_std = T::one() / (centered.norm_squared() / n + real(EPS)).sqrt();
+        let normed = centered * inv_std;
+        (normed.component_mul(&self.gamma) + self.beta, (normed, inv_std))
+    }
+
+    fn backward_into(
+        &self,
+        grad: &mut Self,
+        _: &SVector<T, N>,
+        (normed, inv_std): &(SVector<T, N>, T),
+        d_out: &SVector<T, N>
+    ) -> SVector<T, N> {
+        grad.gamma += d_out.component_mul(normed);
+        grad.beta += d_out;
+
+        let n = real::<T>(N as f64);
+        let d_normed = d_out.component_mul(&self.gamma);
+        let proj = d_normed.dot(normed);
+        (d_normed * n - normed * proj).add_scalar(-d_normed.sum()) * (*inv_std / n)
+    }
+
+    #[inline]
+    fn init_with<R: rand::Rng + ?Sized>(_: Init, _: &mut R) -> Self {
+        Self { gamma: SVector::repeat(T::one()), beta: SVector::zeros() }
+    }
+}
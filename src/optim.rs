@@ -0,0 +1,192 @@
+//! Optimizers applied by [`Layers::update`](crate::model::Layers::update).
+//!
+//! All of the optimizers work element-wise, so an [`Optimizer`] only describes how
+//! a single parameter is updated, and [`Moments`] keeps the buffers of the whole layers.
+//! Hyper parameters are `f64` and converted to the scalar type of the layers.
+
+use crate::model::{Real, real};
+
+/// Update rule of a single parameter.
+pub trait Optimizer {
+    /// `t`: update step, starts from 1
+    ///
+    /// `rate`: learn rate
+    ///
+    /// `p`: the parameter, `g`: averaged gradient of `p`
+    ///
+    /// `m`, `v`: first and second moment buffers of `p`, zero at the beginning
+    fn step<T: Real>(&self, t: usize, rate: T, p: &mut T, g: T, m: &mut T, v: &mut T);
+}
+
+/// Moment buffers of layers `L`, shaped the same as `L`.
+#[derive(Clone, Default)]
+pub struct Moments<L> {
+    /// count of steps taken
+    pub t: usize,
+    pub m: L,
+    pub v: L,
+}
+
+impl<L> Moments<L> {
+    /// increase and return the step count
+    #[inline]
+    pub fn next_step(&mut self) -> usize {
+        self.t += 1;
+        self.t
+    }
+}
+
+/// Plain stochastic gradient descent: `p -= rate * g`.
+#[derive(Clone, Copy, Default)]
+pub struct Sgd;
+
+impl Optimizer for Sgd {
+    #[inline]
+    fn step<T: Real>(&self, _: usize, rate: T, p: &mut T, g: T, _: &mut T, _: &mut T) {
+        *p -= rate * g;
+    }
+}
+
+/// SGD with momentum.
+///
+/// default: `momentum: 0.9`
+#[derive(Clone, Copy)]
+pub struct Momentum {
+    pub momentum: f64,
+}
+
+impl Default for Momentum {
+    fn default() -> Self {
+        Self { momentum: 0.9 }
+    }
+}
+
+impl Optimizer for Momentum {
+    #[inline]
+    fn step<T: Real>(&self, _: usize, rate: T, p: &mut T, g: T, m: &mut T, _: &mut T) {
+        *m = real::<T>(self.momentum) * *m + g;
+        *p -= rate * *m;
+    }
+}
+
+/// SGD with Nesterov momentum.
+///
+/// default: `momentum: 0.9`
+#[derive(Clone, Copy)]
+pub struct Nesterov {
+    pub momentum: f64,
+}
+
+impl Default for Nesterov {
+    fn default() -> Self {
+        Self { momentum: 0.9 }
+    }
+}
+
+impl Optimizer for Nesterov {
+    #[inline]
+    fn step<T: Real>(&self, _: usize, rate: T, p: &mut T, g: T, m: &mut T, _: &mut T) {
+        let momentum = real::<T>(self.momentum);
+        *m = momentum * *m + g;
+        *p -= rate * (g + momentum * *m);
+    }
+}
+
+/// default: `decay: 0.9, eps: 1e-8`
+#[derive(Clone, Copy)]
+pub struct RmsProp {
+    pub decay: f64,
+    pub eps: f64,
+}
+
+impl Default for RmsProp {
+    fn default() -> Self {
+        Self { decay: 0.9, eps: 1e-8 }
+    }
+}
+
+impl Optimizer for RmsProp {
+    #[inline]
+    fn step<T: Real>(&self, _: usize, rate: T, p: &mut T, g: T, _: &mut T, v: &mut T) {
+        *v = real::<T>(self.decay) * *v + real::<T>(1. - self.decay) * g * g;
+        *p -= rate * g / (v.sqrt() + real(self.eps));
+    }
+}
+
+/// default: `eps: 1e-8`
+#[derive(Clone, Copy)]
+pub struct Adagrad {
+    pub eps: f64,
+}
+
+impl Default for Adagrad {
+    fn default() -> Self {
+        Self { eps: 1e-8 }
+    }
+}
+
+impl Optimizer for Adagrad {
+    #[inline]
+    fn step<T: Real>(&self, _: usize, rate: T, p: &mut T, g: T, _: &mut T, v: &mut T) {
+        *v += g * g;
+        *p -= rate * g / (v.sqrt() + real(self.eps));
+    }
+}
+
+/// default: `beta1: 0.9, beta2: 0.999, eps: 1e-8`
+#[derive(Clone, Copy)]
+pub struct Adam {
+    pub beta1: f64,
+    pub beta2: f64,
+    pub eps: f64,
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Self { beta1: 0.9, beta2: 0.999, eps: 1e-8 }
+    }
+}
+
+impl Adam {
+    /// bias-corrected adam step, shared with [`AdamW`]
+    #[inline]
+    fn delta<T: Real>(&self, t: usize, g: T, m: &mut T, v: &mut T) -> T {
+        *m = real::<T>(self.beta1) * *m + real::<T>(1. - self.beta1) * g;
+        *v = real::<T>(self.beta2) * *v + real::<T>(1. - self.beta2) * g * g;
+        let m_hat = *m / real(1. - self.beta1.powi(t as i32));
+        let v_hat = *v / real(1. - self.beta2.powi(t as i32));
+        m_hat / (v_hat.sqrt() + real(self.eps))
+    }
+}
+
+impl Optimizer for Adam {
+    #[inline]
+    fn step<T: Real>(&self, t: usize, rate: T, p: &mut T, g: T, m: &mut T, v: &mut T) {
+        *p -= rate * self.delta(t, g, m, v);
+    }
+}
+
+/// Adam with decoupled weight decay.
+///
+/// default: `beta1: 0.9, beta2: 0.999, eps: 1e-8, weight_decay: 0.01`
+#[derive(Clone, Copy)]
+pub struct AdamW {
+    pub beta1: f64,
+    pub beta2: f64,
+    pub eps: f64,
+    pub weight_decay: f64,
+}
+
+impl Default for AdamW {
+    fn default() -> Self {
+        Self { beta1: 0.9, beta2: 0.999, eps: 1e-8, weight_decay: 0.01 }
+    }
+}
+
+impl Optimizer for AdamW {
+    #[inline]
+    fn step<T: Real>(&self, t: usize, rate: T, p: &mut T, g: T, m: &mut T, v: &mut T) {
+        let adam = Adam { beta1: self.beta1, beta2: self.beta2, eps: self.eps };
+        *p -= rate * (adam.delta(t, g, m, v) + real::<T>(self.weight_decay) * *p);
+    }
+}
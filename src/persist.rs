@@ -0,0 +1,517 @@
+//! Saving and loading of layers implementing [`Params`].
+//!
+//! # Binary format
+//!
+//! All integers and floats are little endian.
+//!
+//! ```text
+//! magic       4 bytes     b"SNN\0"
+//! version     u16         FORMAT_VERSION
+//! dtype       u8          byte size of a param: 4 (f32) or 8 (f64)
+//! has_config  u8          0 or 1
+//! config      if has_config == 1:
+//!     learn_rate  f64
+//!     batch_size  u64
+//!     iter_num    u64
+//!     actvt_func  string
+//!     loss_func   string
+//!     optimizer   string
+//! layer_count u32
+//! shapes      layer_count * (rows u32, cols u32)
+//! lengths     layer_count * (w u32, b u32)
+//! has_actvt   u8          0 or 1
+//! actvt       if has_actvt == 1:
+//!     names       layer_count * string
+//!     softmax     u8      0 or 1
+//! params      layer_count * (w: w dtype column-major, b: b dtype)
+//! ```
+//!
+//! where a `string` is its byte length as `u32` followed by utf-8 bytes. The lengths of
+//! dense layers are `rows * cols` and `rows`, other layers may have params of other lengths
+//! than their shape, which must be the lengths of the aimed layers on load.
+//!
+//! Params are stored in the scalar type of the saved layers and converted to the
+//! scalar type of the aimed layers on load, so a model trained in `f64` loads as `f32`.
+//!
+//! Activations are only stored by [`DynNetwork`](crate::dynamic::DynNetwork), whose
+//! activations are not part of its type. Layers implementing [`Params`] ignore them on load,
+//! so a `DynNetwork` file loads into derived layers of the same shapes.
+//!
+//! # JSON format
+//!
+//! ```text
+//! {
+//!     "format": "simple-nn",
+//!     "version": 1,
+//!     "dtype": "f32" | "f64",
+//!     "config": null | { "learn_rate", "batch_size", "iter_num", "actvt_func", "loss_func", "optimizer" },
+//!     "layers": [ { "rows", "cols", "activation"?, "w": [..], "b": [..] }, .. ],
+//!     "softmax"?: bool
+//! }
+//! ```
+
+use std::{fmt, fs::File, io::{self, BufReader, BufWriter, Read, Write}, path::Path};
+
+use serde_json::{json, Value};
+
+use crate::{Config, model::{Params, Real, real, to_f64}};
+
+pub const MAGIC: &[u8; 4] = b"SNN\0";
+
+/// version of both the binary and the JSON format
+pub const FORMAT_VERSION: u16 = 1;
+
+const JSON_FORMAT: &str = "simple-nn";
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// the data is not produced by this crate
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// byte size of params is neither 4 nor 8
+    UnsupportedDtype(u8),
+    /// the data is well-formed but lacks or has a wrong typed field
+    Malformed(String),
+    LayerCount { expected: usize, found: usize },
+    /// shape `(rows, cols)` of layer `layer` (starts from 1) differs from the aimed layers
+    ShapeMismatch { layer: usize, expected: (usize, usize), found: (usize, usize) },
+    /// lengths `(w, b)` of the params of layer `layer` differ from the aimed layers
+    ParamCount { layer: usize, expected: (usize, usize), found: (usize, usize) },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Json(e) => write!(f, "json error: {}", e),
+            Error::BadMagic => write!(f, "not a simple-nn model"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            Error::UnsupportedDtype(d) => write!(f, "unsupported param size {} bytes", d),
+            Error::Malformed(msg) => write!(f, "malformed model: {}", msg),
+            Error::LayerCount { expected, found } =>
+                write!(f, "expected {} layers, found {}", expected, found),
+            Error::ShapeMismatch { layer, expected, found } =>
+                write!(f, "layer {} expected shape {:?}, found {:?}", layer, expected, found),
+            Error::ParamCount { layer, expected, found } =>
+                write!(f, "layer {} expected {:?} params, found {:?}", layer, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// [`Config`] settings stored along with the layers, functions are kept by type name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigMeta {
+    pub learn_rate: f64,
+    pub batch_size: usize,
+    pub iter_num: usize,
+    pub actvt_func: String,
+    pub loss_func: String,
+    pub optimizer: String,
+}
+
+impl<A, C, Opt, Sch> From<&Config<A, C, Opt, Sch>> for ConfigMeta {
+    fn from(config: &Config<A, C, Opt, Sch>) -> Self {
+        Self {
+            learn_rate: config.learn_rate,
+            batch_size: config.batch_size,
+            iter_num: config.iter_num,
+            actvt_func: std::any::type_name::<A>().to_owned(),
+            loss_func: std::any::type_name::<C>().to_owned(),
+            optimizer: std::any::type_name::<Opt>().to_owned(),
+        }
+    }
+}
+
+/// Loaded layers and the config they were saved with.
+pub struct Saved<L> {
+    pub layers: L,
+    pub config: Option<ConfigMeta>,
+}
+
+/// byte size of params written for scalar type `T`
+fn dtype<T>() -> u8 {
+    if std::mem::size_of::<T>() == 4 { 4 } else { 8 }
+}
+
+fn dtype_name(dtype: u8) -> &'static str {
+    if dtype == 4 { "f32" } else { "f64" }
+}
+
+/// check that `found` shapes match the shapes of `L`
+pub(crate) fn check_shapes<L: Params<T>, T>(found: &[(usize, usize)]) -> Result<()> {
+    let expected = L::shapes();
+    if expected.len() != found.len() {
+        return Err(Error::LayerCount { expected: expected.len(), found: found.len() });
+    }
+    for (i, (e, f)) in expected.into_iter().zip(found.iter()).enumerate() {
+        if e != *f {
+            return Err(Error::ShapeMismatch { layer: i + 1, expected: e, found: *f });
+        }
+    }
+    Ok(())
+}
+
+/// copy `params` into `layers`, fails if the lengths of a layer differ
+pub(crate) fn copy_params<'p, L: Params<T>, T: Copy + 'p>(
+    layers: &mut L,
+    params: impl IntoIterator<Item = (&'p [T], &'p [T])>
+) -> Result<()> {
+    for (i, ((w, b), (src_w, src_b))) in layers.params_mut().into_iter().zip(params).enumerate() {
+        if (w.len(), b.len()) != (src_w.len(), src_b.len()) {
+            let (expected, found) = ((w.len(), b.len()), (src_w.len(), src_b.len()));
+            return Err(Error::ParamCount { layer: i + 1, expected, found });
+        }
+        w.copy_from_slice(src_w);
+        b.copy_from_slice(src_b);
+    }
+    Ok(())
+}
+
+/// Activation name of each layer and whether softmax is applied to the output.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Activations {
+    pub names: Vec<String>,
+    pub softmax: bool,
+}
+
+/// Stored content independent of the type of layers.
+pub(crate) struct Raw<T> {
+    pub config: Option<ConfigMeta>,
+    pub shapes: Vec<(usize, usize)>,
+    pub activations: Option<Activations>,
+    /// `(w, b)` of each layer, of the lengths they were saved with
+    pub params: Vec<(Vec<T>, Vec<T>)>,
+}
+
+impl<T: Real> Raw<T> {
+    /// layers `L` filled with the params, fails if shapes or lengths of params differ
+    fn into_layers<L: Params<T> + Default>(self) -> Result<Saved<L>> {
+        check_shapes::<L, T>(&self.shapes)?;
+        let mut layers = L::default();
+        copy_params(&mut layers, self.params.iter().map(|(w, b)| (w.as_slice(), b.as_slice())))?;
+        Ok(Saved { layers, config: self.config })
+    }
+}
+
+pub fn write_binary<L: Params<T>, T: Real, W: Write>(layers: &L, config: Option<&ConfigMeta>, w: W) -> Result<()> {
+    write_raw(&L::shapes(), layers.params(), None, config, w)
+}
+
+pub(crate) fn write_raw<T: Real, W: Write>(
+    shapes: &[(usize, usize)],
+    params: Vec<(&[T], &[T])>,
+    activations: Option<&Activations>,
+    config: Option<&ConfigMeta>,
+    mut w: W
+) -> Result<()> {
+    fn write_str<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+        w.write_all(&(s.len() as u32).to_le_bytes())?;
+        w.write_all(s.as_bytes())
+    }
+
+    w.write_all(MAGIC)?;
+    w.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    let dtype = dtype::<T>();
+    w.write_all(&[dtype])?;
+
+    match config {
+        Some(c) => {
+            w.write_all(&[1])?;
+            w.write_all(&c.learn_rate.to_le_bytes())?;
+            w.write_all(&(c.batch_size as u64).to_le_bytes())?;
+            w.write_all(&(c.iter_num as u64).to_le_bytes())?;
+            write_str(&mut w, &c.actvt_func)?;
+            write_str(&mut w, &c.loss_func)?;
+            write_str(&mut w, &c.optimizer)?;
+        }
+        None => w.write_all(&[0])?,
+    }
+
+    w.write_all(&(shapes.len() as u32).to_le_bytes())?;
+    for &(rows, cols) in shapes {
+        w.write_all(&(rows as u32).to_le_bytes())?;
+        w.write_all(&(cols as u32).to_le_bytes())?;
+    }
+    for (weights, bias) in &params {
+        w.write_all(&(weights.len() as u32).to_le_bytes())?;
+        w.write_all(&(bias.len() as u32).to_le_bytes())?;
+    }
+    match activations {
+        Some(a) => {
+            w.write_all(&[1])?;
+            for name in &a.names {
+                write_str(&mut w, name)?;
+            }
+            w.write_all(&[a.softmax as u8])?;
+        }
+        None => w.write_all(&[0])?,
+    }
+    for (weights, bias) in params {
+        for &v in weights.iter().chain(bias) {
+            match dtype {
+                4 => w.write_all(&(to_f64(v) as f32).to_le_bytes())?,
+                _ => w.write_all(&to_f64(v).to_le_bytes())?,
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+pub fn read_binary<L: Params<T> + Default, T: Real, R: Read>(r: R) -> Result<Saved<L>> {
+    read_raw(r)?.into_layers()
+}
+
+pub(crate) fn read_raw<T: Real, R: Read>(mut r: R) -> Result<Raw<T>> {
+    fn read_bytes<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        r.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+    fn read_u32<R: Read>(r: &mut R) -> io::Result<usize> {
+        Ok(u32::from_le_bytes(read_bytes(r)?) as usize)
+    }
+    fn read_u64<R: Read>(r: &mut R) -> io::Result<usize> {
+        Ok(u64::from_le_bytes(read_bytes(r)?) as usize)
+    }
+    fn read_f64<R: Read>(r: &mut R) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(read_bytes(r)?))
+    }
+    fn read_f32<R: Read>(r: &mut R) -> io::Result<f64> {
+        Ok(f32::from_le_bytes(read_bytes(r)?) as f64)
+    }
+    fn read_str<R: Read>(r: &mut R) -> Result<String> {
+        let len = read_u32(r)?;
+        let mut buf = vec![0u8; len];
+        r.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| Error::Malformed(e.to_string()))
+    }
+    fn read_flag<R: Read>(r: &mut R, name: &str) -> Result<bool> {
+        match read_bytes::<_, 1>(r)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            flag => Err(Error::Malformed(format!("invalid {} flag {}", name, flag))),
+        }
+    }
+
+    if &read_bytes::<_, 4>(&mut r)? != MAGIC {
+        return Err(Error::BadMagic);
+    }
+    let version = u16::from_le_bytes(read_bytes(&mut r)?);
+    if version != FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let dtype = read_bytes::<_, 1>(&mut r)?[0];
+    let read_param = match dtype {
+        4 => read_f32,
+        8 => read_f64,
+        _ => return Err(Error::UnsupportedDtype(dtype)),
+    };
+
+    let config = match read_flag(&mut r, "config")? {
+        false => None,
+        true => Some(ConfigMeta {
+            learn_rate: read_f64(&mut r)?,
+            batch_size: read_u64(&mut r)?,
+            iter_num: read_u64(&mut r)?,
+            actvt_func: read_str(&mut r)?,
+            loss_func: read_str(&mut r)?,
+            optimizer: read_str(&mut r)?,
+        }),
+    };
+
+    let count = read_u32(&mut r)?;
+    let mut shapes = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        shapes.push((read_u32(&mut r)?, read_u32(&mut r)?));
+    }
+    let mut lengths = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        lengths.push((read_u32(&mut r)?, read_u32(&mut r)?));
+    }
+
+    let activations = match read_flag(&mut r, "activation")? {
+        false => None,
+        true => Some(Activations {
+            names: (0..count).map(|_| read_str(&mut r)).collect::<Result<_>>()?,
+            softmax: read_flag(&mut r, "softmax")?,
+        }),
+    };
+
+    let mut read_params = |len: usize| (0..len)
+        .map(|_| read_param(&mut r).map(real))
+        .collect::<io::Result<Vec<T>>>();
+    let mut params = Vec::with_capacity(count.min(1024));
+    for (w, b) in lengths {
+        params.push((read_params(w)?, read_params(b)?));
+    }
+
+    Ok(Raw { config, shapes, activations, params })
+}
+
+pub fn to_json<L: Params<T>, T: Real>(layers: &L, config: Option<&ConfigMeta>) -> String {
+    to_json_raw(&L::shapes(), layers.params(), None, config)
+}
+
+pub(crate) fn to_json_raw<T: Real>(
+    shapes: &[(usize, usize)],
+    params: Vec<(&[T], &[T])>,
+    activations: Option<&Activations>,
+    config: Option<&ConfigMeta>
+) -> String {
+    let config = config.map(|c| json!({
+        "learn_rate": c.learn_rate,
+        "batch_size": c.batch_size,
+        "iter_num": c.iter_num,
+        "actvt_func": c.actvt_func,
+        "loss_func": c.loss_func,
+        "optimizer": c.optimizer,
+    }));
+    let layers = shapes.iter()
+        .zip(params)
+        .enumerate()
+        .map(|(i, (&(rows, cols), (w, b)))| {
+            let mut layer = json!({
+                "rows": rows,
+                "cols": cols,
+                "w": w.iter().map(|&v| to_f64(v)).collect::<Vec<_>>(),
+                "b": b.iter().map(|&v| to_f64(v)).collect::<Vec<_>>(),
+            });
+            if let Some(a) = activations {
+                layer["activation"] = json!(a.names[i]);
+            }
+            layer
+        })
+        .collect::<Vec<_>>();
+
+    let mut root = json!({
+        "format": JSON_FORMAT,
+        "version": FORMAT_VERSION,
+        "dtype": dtype_name(dtype::<T>()),
+        "config": config,
+        "layers": layers,
+    });
+    if let Some(a) = activations {
+        root["softmax"] = json!(a.softmax);
+    }
+    root.to_string()
+}
+
+pub fn from_json<L: Params<T> + Default, T: Real>(s: &str) -> Result<Saved<L>> {
+    from_json_raw(s)?.into_layers()
+}
+
+pub(crate) fn from_json_raw<T: Real>(s: &str) -> Result<Raw<T>> {
+    fn field<'v>(v: &'v Value, key: &str) -> Result<&'v Value> {
+        v.get(key).ok_or_else(|| Error::Malformed(format!("missing field `{}`", key)))
+    }
+    fn usize_field(v: &Value, key: &str) -> Result<usize> {
+        field(v, key)?.as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| Error::Malformed(format!("`{}` is not an unsigned integer", key)))
+    }
+    fn str_field(v: &Value, key: &str) -> Result<String> {
+        field(v, key)?.as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::Malformed(format!("`{}` is not a string", key)))
+    }
+    fn floats<T: Real>(v: &Value, key: &str) -> Result<Vec<T>> {
+        let src = field(v, key)?.as_array()
+            .ok_or_else(|| Error::Malformed(format!("`{}` is not an array", key)))?;
+        src.iter()
+            .map(|s| s.as_f64()
+                .map(real)
+                .ok_or_else(|| Error::Malformed(format!("`{}` contains a non-number", key))))
+            .collect()
+    }
+
+    let root: Value = serde_json::from_str(s)?;
+    if root.get("format").and_then(Value::as_str) != Some(JSON_FORMAT) {
+        return Err(Error::BadMagic);
+    }
+    let version = usize_field(&root, "version")?;
+    if version != FORMAT_VERSION as usize {
+        return Err(Error::UnsupportedVersion(version as u16));
+    }
+
+    let config = match field(&root, "config")? {
+        Value::Null => None,
+        c => Some(ConfigMeta {
+            learn_rate: field(c, "learn_rate")?.as_f64()
+                .ok_or_else(|| Error::Malformed("`learn_rate` is not a number".to_owned()))?,
+            batch_size: usize_field(c, "batch_size")?,
+            iter_num: usize_field(c, "iter_num")?,
+            actvt_func: str_field(c, "actvt_func")?,
+            loss_func: str_field(c, "loss_func")?,
+            optimizer: str_field(c, "optimizer")?,
+        }),
+    };
+
+    let saved = field(&root, "layers")?.as_array()
+        .ok_or_else(|| Error::Malformed("`layers` is not an array".to_owned()))?;
+    let shapes = saved.iter()
+        .map(|l| Ok((usize_field(l, "rows")?, usize_field(l, "cols")?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let activations = match root.get("softmax") {
+        None => None,
+        Some(softmax) => Some(Activations {
+            names: saved.iter().map(|l| str_field(l, "activation")).collect::<Result<_>>()?,
+            softmax: softmax.as_bool()
+                .ok_or_else(|| Error::Malformed("`softmax` is not a bool".to_owned()))?,
+        }),
+    };
+
+    let params = saved.iter()
+        .map(|l| Ok((floats(l, "w")?, floats(l, "b")?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Raw { config, shapes, activations, params })
+}
+
+/// save `layers` to file `path` in binary format
+pub fn save<L: Params<T>, T: Real, P: AsRef<Path>>(layers: &L, config: Option<&ConfigMeta>, path: P) -> Result<()> {
+    write_binary(layers, config, BufWriter::new(File::create(path)?))
+}
+
+/// load layers from binary file `path`
+pub fn load<L: Params<T> + Default, T: Real, P: AsRef<Path>>(path: P) -> Result<Saved<L>> {
+    read_binary(BufReader::new(File::open(path)?))
+}
+
+/// save `layers` to file `path` in JSON format
+pub fn save_json<L: Params<T>, T: Real, P: AsRef<Path>>(layers: &L, config: Option<&ConfigMeta>, path: P) -> Result<()> {
+    std::fs::write(path, to_json(layers, config))?;
+    Ok(())
+}
+
+/// load layers from JSON file `path`
+pub fn load_json<L: Params<T> + Default, T: Real, P: AsRef<Path>>(path: P) -> Result<Saved<L>> {
+    from_json(&std::fs::read_to_string(path)?)
+}
@@ -0,0 +1,115 @@
+//! Weight penalties folded into the gradients by [`Layers::update`](crate::model::Layers::update).
+//!
+//! The penalty of a parameter `p` is `l1 * |p| + l2 / 2 * p²`, and the sum over all
+//! penalized parameters is added to the training loss.
+
+use crate::model::{Params, Real, real, to_f64};
+
+/// L1 and L2 strength of a layer.
+///
+/// default: no penalty
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Penalty {
+    pub l1: f64,
+    pub l2: f64,
+}
+
+impl Penalty {
+    pub fn l1(strength: f64) -> Self {
+        Self { l1: strength, l2: 0. }
+    }
+
+    /// weight decay
+    pub fn l2(strength: f64) -> Self {
+        Self { l1: 0., l2: strength }
+    }
+
+    /// `l1_ratio` of `strength` goes to L1, the rest to L2
+    pub fn elastic_net(strength: f64, l1_ratio: f64) -> Self {
+        Self { l1: strength * l1_ratio, l2: strength * (1. - l1_ratio) }
+    }
+
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        self.l1 == 0. && self.l2 == 0.
+    }
+
+    /// penalty of a single parameter
+    #[inline]
+    pub fn loss<T: Real>(&self, p: T) -> f64 {
+        let p = to_f64(p);
+        self.l1 * p.abs() + self.l2 / 2. * p * p
+    }
+
+    /// derivative of the penalty with respect to `p`, zero L1 part at `p == 0`
+    #[inline]
+    pub fn d<T: Real>(&self, p: T) -> T {
+        let sign = if p > T::zero() {
+            T::one()
+        } else if p < T::zero() {
+            -T::one()
+        } else {
+            T::zero()
+        };
+        real::<T>(self.l1) * sign + real::<T>(self.l2) * p
+    }
+}
+
+/// Penalties of all layers.
+///
+/// default: no penalty, biases excluded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Regularization {
+    /// penalty of layers not given in `per_layer`
+    pub penalty: Penalty,
+    /// penalty of layer `i + 1`, `None` or missing entries fall back to `penalty`
+    pub per_layer: Vec<Option<Penalty>>,
+    /// penalize biases as well as weights
+    pub include_bias: bool,
+}
+
+impl Regularization {
+    /// same `penalty` for weights of all layers
+    pub fn new(penalty: Penalty) -> Self {
+        Self { penalty, ..Default::default() }
+    }
+
+    /// penalty of layer `layer`, which starts from 1
+    #[inline]
+    pub fn layer(&self, layer: usize) -> Penalty {
+        self.per_layer.get(layer - 1).copied().flatten().unwrap_or(self.penalty)
+    }
+
+    /// no penalty on any layer
+    pub fn is_none(&self) -> bool {
+        self.penalty.is_none() && self.per_layer.iter().all(|p| p.map_or(true, |p| p.is_none()))
+    }
+
+    /// total penalty of `layers`, added to the training loss, each entry of `params`
+    /// is penalized by the penalty of its layer in [`Params::layers`]
+    pub fn loss<L: Params<T>, T: Real>(&self, layers: &L) -> f64 {
+        if self.is_none() {
+            return 0.;
+        }
+        self.loss_by_layer(L::layers().into_iter().zip(layers.params()))
+    }
+
+    /// total penalty of `(w, b)` of each layer
+    pub fn loss_of<'p, T: Real>(&self, params: impl IntoIterator<Item = (&'p [T], &'p [T])>) -> f64 {
+        self.loss_by_layer(params.into_iter().enumerate().map(|(i, p)| (i + 1, p)))
+    }
+
+    /// total penalty of `(w, b)` of each layer `i`
+    fn loss_by_layer<'p, T: Real>(&self, params: impl Iterator<Item = (usize, (&'p [T], &'p [T]))>) -> f64 {
+        params
+            .map(|(i, (w, b))| {
+                let penalty = self.layer(i);
+                if penalty.is_none() {
+                    return 0.;
+                }
+                let bias = if self.include_bias { b } else { &[] };
+                w.iter().chain(bias).map(|&p| penalty.loss(p)).sum::<f64>()
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,188 @@
+//! Learning rate schedulers, applied by the trainer before each iter.
+
+use std::f64::consts::PI;
+
+/// Learn rate of each iter.
+pub trait LrScheduler {
+    /// `epoch`: current iter, starts from 1
+    ///
+    /// `base`: the initial `learn_rate` of `Config`
+    ///
+    /// `val_loss`: validation loss of the previous iter, `None` without validation
+    /// or before the first iter
+    fn rate(&mut self, epoch: usize, base: f64, val_loss: Option<f64>) -> f64;
+}
+
+/// Keep `base` unchanged.
+#[derive(Clone, Copy, Default)]
+pub struct Constant;
+
+impl LrScheduler for Constant {
+    #[inline]
+    fn rate(&mut self, _: usize, base: f64, _: Option<f64>) -> f64 {
+        base
+    }
+}
+
+/// Multiply the rate by `gamma` every `step_size` iters.
+///
+/// default: `step_size: 10_000, gamma: 0.5`
+#[derive(Clone, Copy)]
+pub struct StepDecay {
+    pub step_size: usize,
+    pub gamma: f64,
+}
+
+impl Default for StepDecay {
+    fn default() -> Self {
+        Self { step_size: 10_000, gamma: 0.5 }
+    }
+}
+
+impl LrScheduler for StepDecay {
+    #[inline]
+    fn rate(&mut self, epoch: usize, base: f64, _: Option<f64>) -> f64 {
+        base * self.gamma.powi(((epoch - 1) / self.step_size) as i32)
+    }
+}
+
+/// Multiply the rate by `gamma` every iter.
+#[derive(Clone, Copy)]
+pub struct ExponentialDecay {
+    pub gamma: f64,
+}
+
+impl LrScheduler for ExponentialDecay {
+    #[inline]
+    fn rate(&mut self, epoch: usize, base: f64, _: Option<f64>) -> f64 {
+        base * self.gamma.powi(epoch as i32 - 1)
+    }
+}
+
+/// Cosine annealing from `base` to `min_rate` with warm restarts (SGDR).
+///
+/// The first cycle lasts `period` iters, and each following cycle is `mult` times longer.
+#[derive(Clone, Copy)]
+pub struct CosineAnnealing {
+    pub period: usize,
+    pub mult: usize,
+    pub min_rate: f64,
+}
+
+impl LrScheduler for CosineAnnealing {
+    fn rate(&mut self, epoch: usize, base: f64, _: Option<f64>) -> f64 {
+        let mut cur = epoch - 1;
+        let mut period = self.period.max(1);
+        while cur >= period {
+            cur -= period;
+            period *= self.mult.max(1);
+        }
+        let progress = cur as f64 / period as f64;
+        self.min_rate + (base - self.min_rate) * (1. + (PI * progress).cos()) / 2.
+    }
+}
+
+/// Increase the rate linearly from `base / warmup` to `base` in the first `warmup` iters,
+/// then follow `then` as if training started after warmup.
+#[derive(Clone, Copy)]
+pub struct LinearWarmup<S> {
+    pub warmup: usize,
+    pub then: S,
+}
+
+impl<S: LrScheduler> LrScheduler for LinearWarmup<S> {
+    #[inline]
+    fn rate(&mut self, epoch: usize, base: f64, val_loss: Option<f64>) -> f64 {
+        if epoch <= self.warmup {
+            base * epoch as f64 / self.warmup as f64
+        } else {
+            self.then.rate(epoch - self.warmup, base, val_loss)
+        }
+    }
+}
+
+/// One-cycle policy over `total` iters, `base` is the peak rate.
+///
+/// The rate starts at `base / div`, rises to `base` in the first `pct_start` of the iters,
+/// then anneals to `base / (div * final_div)` with cosine.
+///
+/// default of [`OneCycle::new`]: `pct_start: 0.3, div: 25, final_div: 1e4`
+#[derive(Clone, Copy)]
+pub struct OneCycle {
+    pub total: usize,
+    pub pct_start: f64,
+    pub div: f64,
+    pub final_div: f64,
+}
+
+impl OneCycle {
+    #[inline]
+    pub fn new(total: usize) -> Self {
+        Self { total, pct_start: 0.3, div: 25., final_div: 1e4 }
+    }
+}
+
+impl LrScheduler for OneCycle {
+    fn rate(&mut self, epoch: usize, base: f64, _: Option<f64>) -> f64 {
+        fn anneal(from: f64, to: f64, progress: f64) -> f64 {
+            to + (from - to) * (1. + (PI * progress.min(1.)).cos()) / 2.
+        }
+        let start = base / self.div;
+        let end = start / self.final_div;
+        let up = (self.total as f64 * self.pct_start).max(1.);
+        let cur = (epoch - 1) as f64;
+        if cur < up {
+            anneal(start, base, cur / up)
+        } else {
+            anneal(base, end, (cur - up) / (self.total as f64 - up).max(1.))
+        }
+    }
+}
+
+/// Multiply the rate by `factor` when validation loss has not improved by
+/// `threshold` for `patience` iters, never going below `min_rate`.
+///
+/// Keeps `base` without validation.
+#[derive(Clone, Copy)]
+pub struct ReduceOnPlateau {
+    pub factor: f64,
+    pub patience: usize,
+    pub threshold: f64,
+    pub min_rate: f64,
+    best: Option<f64>,
+    wait: usize,
+    scale: f64,
+}
+
+impl ReduceOnPlateau {
+    #[inline]
+    pub fn new(factor: f64, patience: usize) -> Self {
+        Self {
+            factor,
+            patience,
+            threshold: 1e-4,
+            min_rate: 0.,
+            best: None,
+            wait: 0,
+            scale: 1.,
+        }
+    }
+}
+
+impl LrScheduler for ReduceOnPlateau {
+    fn rate(&mut self, _: usize, base: f64, val_loss: Option<f64>) -> f64 {
+        if let Some(loss) = val_loss {
+            if self.best.map_or(true, |b| loss < b - self.threshold) {
+                self.best = Some(loss);
+                self.wait = 0;
+            } else {
+                self.wait += 1;
+                if self.wait >= self.patience {
+                    self.scale *= self.factor;
+                    self.wait = 0;
+                }
+            }
+        }
+        (base * self.scale).max(self.min_rate)
+    }
+}
@@ -4,97 +4,134 @@ use std::marker::PhantomData;
 use itertools::Itertools;
 use rand::prelude::SliceRandom;
 
-use crate::{Config, Item, func::*, model::*};
+use crate::{Config, Item, func::*, metric::Metric, model::*, optim::*, persist::ConfigMeta, schedule::*};
 
-pub struct Trainer<'a, A, C, L, Cal, const I: usize, const O: usize> {
-    data: Vec<&'a Item<I, O>>,
+type ItemLstn<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> =
+    Box<dyn 'a + Fn(TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>, &Item<I,O,T>, &SVector<T, O>)>;
+type ChunkLstn<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> =
+    Box<dyn 'a + Fn(TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>, &[&Item<I,O,T>], &[SVector<T, O>])>;
+type IterLstn<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> =
+    Box<dyn 'a + Fn(usize, TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>)>;
+
+pub struct Trainer<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> {
+    data: Vec<&'a Item<I, O, T>>,
+    validation: Vec<&'a Item<I, O, T>>,
+    patience: Option<usize>,
     layers: L,
-    config: Config<A, C>,
-    item_lstn: Option<Box<dyn 'a + Fn(TempModel<L,A,Cal,I,O>, &Config<A,C>, &Item<I,O>, &SVector<f64, O>)>>,
-    chunk_lstn: Option<Box<dyn 'a + Fn(TempModel<L,A,Cal,I,O>, &Config<A,C>, &[&Item<I,O>], &[SVector<f64, O>])>>,
-    iter_lstn: Option<Box<dyn 'a + Fn(usize, TempModel<L,A,Cal,I,O>, &Config<A,C>)>>,
-    _maker: std::marker::PhantomData<Cal>
+    config: Config<A, C, Opt, Sch>,
+    item_lstn: Option<ItemLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    chunk_lstn: Option<ChunkLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    iter_lstn: Option<IterLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    metrics: Vec<Box<dyn 'a + Metric<O, T>>>,
+    _maker: std::marker::PhantomData<(Cal, T)>
 }
 
-impl<'a, A, C, L, Cal, const I: usize, const O: usize> Trainer<'a, A, C, L, Cal, I, O>
+impl<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> Trainer<'a, A, C, Opt, Sch, L, Cal, I, O, T>
 where
     A: ActivitionFunc,
     C: LossFunc,
-    L: Layers<A, Cal, I, O>,
-    Cal: Calculation<O>
+    Opt: Optimizer,
+    Sch: LrScheduler,
+    L: Layers<A, Cal, I, O, T>,
+    Cal: Calculation<O, T>,
+    T: Real
 {
     #[inline]
-    pub fn new<T>(layers: L, items: T, config: Config<A, C>) -> Self
+    pub fn new<D>(layers: L, items: D, config: Config<A, C, Opt, Sch>) -> Self
     where
-        T: Iterator<Item = &'a self::Item<I, O>>
+        D: Iterator<Item = &'a self::Item<I, O, T>>
     {
         Self {
             data: items.collect(),
+            validation: Vec::new(),
+            patience: None,
             config,
             layers,
             item_lstn: None,
             chunk_lstn: None,
             iter_lstn: None,
+            metrics: Vec::new(),
             _maker: Default::default()
         }
     }
 
-    pub fn build(mut self) -> Model<L, A, Cal, I, O> {
-        #![allow(non_snake_case)]
-
-        let mut rng = rand::thread_rng();
-    
-        for i in 1..self.config.iter_num+1 {
-            self.data.shuffle(&mut rng);
-            let chunks = self.data
-                .chunks(self.config.batch_size)
-                .collect_vec();
-
-            for chunk in chunks {
-                let mut outputs = Vec::with_capacity(chunk.len());
-
-                let layers = &mut self.layers;
-                let config = &self.config;
-                let item_listener = &self.item_lstn;
-
-                let gradients = chunk.iter()
-                    .map(|item| {
-                        let calc = layers.forward(&item.data);
-                        let out = calc.out().to_owned();
-                        outputs.push(out);
-                        let last_gradient = C::d(&item.label, calc.out());
-
-                        if let Some(f) = item_listener {
-                            f(TempModel::new(layers), config, item, &out);
-                        }
-
-                        layers.backward(&item.data, last_gradient, calc)
-                    }).collect_vec();
+    /// evaluate loss on `items` after each iter, weights with the lowest loss are kept
+    #[inline]
+    pub fn validate_with<D>(&mut self, items: D)
+    where
+        D: IntoIterator<Item = &'a Item<I, O, T>>
+    {
+        self.validation = items.into_iter().collect();
+    }
 
-                layers.update(self.config.learn_rate, gradients);   
+    /// stop training when validation loss has not improved for `patience` iters,
+    /// has no effect without [`validate_with`](Self::validate_with)
+    #[inline]
+    pub fn early_stop(&mut self, patience: usize) {
+        self.patience = Some(patience);
+    }
 
-                self.call_chunk_listeners(chunk, &outputs);
-            }
+    /// record `metric` of training items, and of validation items if any, after each iter
+    #[inline]
+    pub fn metric<M>(&mut self, metric: M)
+    where
+        M: 'a + Metric<O, T>
+    {
+        self.metrics.push(Box::new(metric));
+    }
 
-            self.call_iter_lstn(i);
+    /// train and get the model with the history of losses and metrics, the model is the
+    /// given one and the history is empty without training items
+    /// 
+    /// buffers of chunks are kept across chunks and iters, so once they are warm a chunk
+    /// of dense layers derived for a layer count does not allocate, per iter only the
+    /// penalty of regularization, metrics and listeners may. the exception is the matrix
+    /// product of nalgebra, which takes a packing buffer for products larger than 5 in
+    /// every dimension
+    /// 
+    /// declared layers allocate in each chunk: the default `backprop_batch_into` keeps the
+    /// calculations of a batch in a `Vec`, `params_mut` collects one in each update, and
+    /// gradients taken from the buffers are replaced by `Default`, which allocates the
+    /// matrices of convolutions
+    pub fn build(self) -> (Model<L, A, Cal, I, O, T>, History)
+    where
+        L: Default + Clone + Params<T> + MaybeSync,
+        T: MaybeSync
+    {
+        let Self { mut data, validation, patience, layers, mut config, item_lstn, chunk_lstn, iter_lstn, metrics, .. } = self;
+        let mut history = History::new(metrics.iter().map(|m| m.name()));
+        history.reserve(config.iter_num);
 
-            if i % 10_000 == 0 {
-                self.config.learn_rate /= 2f64;
-            }
-        }
+        let mut fitting = Fitting {
+            layers,
+            best: None,
+            moments: Moments::default(),
+            scratches: (0..BATCH_COUNT).map(|_| Scratch::default()).collect(),
+            outputs: Vec::with_capacity(config.batch_size),
+            labels: Vec::new(),
+            epoch_outputs: Vec::new(),
+            val_labels: validation.iter().map(|item| item.label).collect(),
+            val_outputs: Vec::with_capacity(validation.len()),
+            item_lstn,
+            chunk_lstn,
+            iter_lstn,
+            metrics,
+        };
+        fit(&mut fitting, &mut config, &mut data, &validation, patience, &mut history);
 
-        Model::new(self.layers)
+        let config = ConfigMeta::from(&config);
+        (Model::new(fitting.best.unwrap_or(fitting.layers)).with_config(config), history)
     }
 
     #[inline]
     pub fn after_each_item<F>(&mut self, f: F)
     where
-        F: 'a + Fn(TempModel<L,A,Cal,I,O>, &Config<A,C>, &Item<I,O>, &SVector<f64, O>),
+        F: 'a + Fn(TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>, &Item<I,O,T>, &SVector<T, O>),
     {
         self.item_lstn = Some(Box::new(f));
     }
 
-    // fn call_item_listeners(&self, item: &Item<I,O>, output: &SVector<f64, O>) {
+    // fn call_item_listeners(&self, item: &Item<I,O,T>, output: &SVector<T, O>) {
     //     for f in self.item_listener.iter() {
     //         f(&self.layers, &self.config, item, output);
     //     }
@@ -103,44 +140,394 @@ where
     #[inline]
     pub fn after_each_chunk<F>(&mut self, f: F)
     where
-        F: 'a + Fn(TempModel<L,A,Cal,I,O>, &Config<A,C>, &[&Item<I,O>], &[SVector<f64, O>]),
-        L: Layers<A,Cal,I,O>,
+        F: 'a + Fn(TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>, &[&Item<I,O,T>], &[SVector<T, O>]),
+        L: Layers<A,Cal,I,O,T>,
     {
         self.chunk_lstn = Some(Box::new(f));
     }
 
-    #[inline]
-    fn call_chunk_listeners(&self, chunk: &[&Item<I,O>], outputs: &[SVector<f64, O>]) {
-        if let Some(f) = &self.chunk_lstn {
-            f(TempModel::new(&self.layers), &self.config, chunk, outputs);
-        }
-    }
-
     #[inline]
     pub fn after_each_iter<F>(&mut self, f: F)
     where
-        F: 'a + Fn(usize, TempModel<L,A,Cal,I,O>, &Config<A,C>),
+        F: 'a + Fn(usize, TempModel<L,A,Cal,I,O,T>, &Config<A,C,Opt,Sch>),
     {
         self.iter_lstn = Some(Box::new(f));
     }
+}
+
+/// `Send + Sync` with feature `parallel`, so that layers can be shared between threads.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<X: Send + Sync> MaybeSync for X {}
+
+/// `Send + Sync` with feature `parallel`, so that layers can be shared between threads.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<X> MaybeSync for X {}
+
+/// Buffers of a batch of items, kept across chunks and iters so that training of dense
+/// layers derived for a layer count does not allocate.
+struct Scratch<L, const O: usize, T> {
+    /// sum of the scaled gradients of the items, taken by `update`
+    grad: L,
+    outputs: Vec<SVector<T, O>>,
+    buffers: Buffers<T>,
+}
+
+impl<L: Default, const O: usize, T: Real> Default for Scratch<L, O, T> {
+    fn default() -> Self {
+        Self { grad: L::default(), outputs: Vec::new(), buffers: Buffers::new() }
+    }
+}
+
+impl<L, const O: usize, T: Real> Scratch<L, O, T> {
+    /// outputs of `items` to `self.outputs`, and their gradients by [`Layers::backprop_batch_into`]
+    /// added to `self.grad`, each scaled by its weight in `weights` and by `scale`
+    fn backprop<A, C, Cal, const I: usize>(
+        &mut self,
+        layers: &L,
+        items: &[&Item<I, O, T>],
+        weights: &[f64],
+        seeds: &[u64],
+        scale: f64
+    )
+    where
+        C: LossFunc,
+        L: Layers<A, Cal, I, O, T> + Params<T>,
+        Cal: Calculation<O, T>
+    {
+        let mut inputs = self.buffers.batch::<I>(items.len());
+        for (mut col, item) in inputs.column_iter_mut().zip(items) {
+            col.copy_from(&item.data);
+        }
+        let mut outputs = self.buffers.batch::<O>(items.len());
+        layers.backprop_batch_into(&mut self.grad, &inputs, seeds, &mut outputs, |outputs, k| {
+            for (j, mut col) in k.column_iter_mut().enumerate() {
+                let d = C::d(&items[j].label, &outputs.column(j).into_owned());
+                col.copy_from(&(d * real::<T>(weights[j] * scale)));
+            }
+        }, &mut self.buffers);
+
+        self.outputs.clear();
+        self.outputs.extend(outputs.column_iter().map(|out| out.into_owned()));
+        self.buffers.recycle(inputs);
+        self.buffers.recycle(outputs);
+    }
+}
+
+/// count of batches a chunk is split into, fixed so that the split and the order in which
+/// the gradients of the batches are summed do not depend on the count of threads
+const BATCH_COUNT: usize = 8;
+
+/// backprop `chunk` by the first `scratches`, returns how many of them are used
+/// 
+/// the chunk is split into at most [`BATCH_COUNT`] batches, the gradient of each batch is
+/// scaled so that the average of them is the average gradient of `chunk`, and the outputs
+/// of the used scratches are the outputs of `chunk` in order
+/// 
+/// batches are computed one after another, or on multiple threads with feature `parallel`,
+/// with the same results
+fn backprop_chunk<A, C, L, Cal, const I: usize, const O: usize, T>(
+    layers: &L,
+    chunk: &[&Item<I, O, T>],
+    weights: &[f64],
+    seeds: &[u64],
+    scratches: &mut [Scratch<L, O, T>]
+) -> usize
+where
+    C: LossFunc,
+    L: Layers<A, Cal, I, O, T> + Params<T> + MaybeSync,
+    Cal: Calculation<O, T>,
+    T: Real + MaybeSync
+{
+    let size = chunk.len().div_ceil(scratches.len());
+    let used = chunk.len().div_ceil(size);
+    let scale = used as f64 / chunk.len() as f64;
+    let backprop = |(((items, weights), seeds), scratch): (((_, _), _), &mut Scratch<L, O, T>)| {
+        scratch.backprop::<A, C, _, I>(layers, items, weights, seeds, scale)
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    chunk.chunks(size)
+        .zip(weights.chunks(size))
+        .zip(seeds.chunks(size))
+        .zip(scratches.iter_mut())
+        .for_each(backprop);
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        chunk.par_chunks(size)
+            .zip(weights.par_chunks(size))
+            .zip(seeds.par_chunks(size))
+            .zip(scratches.par_iter_mut())
+            .for_each(backprop);
+    }
+    used
+}
+
+/// A network trained by [`fit`], for derived layers and for [`DynNetwork`](crate::dynamic::DynNetwork),
+/// so that both trainers take the same shuffles, dropout seeds, schedule and early stopping.
+pub(crate) trait Fit<A, C, Opt, Sch> {
+    type Item;
+
+    /// weight of `item`, scaling its loss and gradient
+    fn weight(config: &Config<A, C, Opt, Sch>, item: &Self::Item) -> f64;
+
+    /// backprop `chunk` and update by its gradients, returns the sum of weighted losses
+    ///
+    /// `weights` and `seeds` are the weight and dropout seed of each item
+    fn fit_chunk(&mut self, config: &Config<A, C, Opt, Sch>, chunk: &[&Self::Item], weights: &[f64], seeds: &[u64]) -> f64;
+
+    /// penalty of regularization, added to the training loss
+    fn penalty(&self, config: &Config<A, C, Opt, Sch>) -> f64;
+
+    /// called once the training loss of iter `i` is in `history`
+    fn end_iter(&mut self, i: usize, config: &Config<A, C, Opt, Sch>, history: &mut History);
+
+    /// average weighted loss of validation `items`
+    fn validate(&mut self, config: &Config<A, C, Opt, Sch>, items: &[&Self::Item], history: &mut History) -> f64;
+
+    /// keep the current weights as the best ones
+    fn keep_best(&mut self);
+}
+
+/// train `fitting` on `data` for `config.iter_num` iters, recording losses to `history`
+///
+/// each iter the rate is taken from the scheduler and `data` is shuffled, then each chunk
+/// is fitted with a dropout seed drawn for each item. with `validation`, the weights of the
+/// lowest validation loss are kept, and training stops after `patience` iters without improvement.
+/// without `data` nothing is fitted and `history` stays empty, instead of a loss of `0 / 0`
+pub(crate) fn fit<A, C, Opt, Sch, F>(
+    fitting: &mut F,
+    config: &mut Config<A, C, Opt, Sch>,
+    data: &mut [&F::Item],
+    validation: &[&F::Item],
+    patience: Option<usize>,
+    history: &mut History
+)
+where
+    Sch: LrScheduler,
+    F: Fit<A, C, Opt, Sch>
+{
+    if data.is_empty() {
+        return;
+    }
+    let mut rng = config.rng();
+    let base_rate = config.learn_rate;
+    let mut weights = Vec::with_capacity(config.batch_size);
+    let mut seeds = Vec::with_capacity(config.batch_size);
+
+    for i in 1..config.iter_num+1 {
+        let val_loss = history.val_loss.last().copied();
+        config.learn_rate = config.scheduler.rate(i, base_rate, val_loss);
+        let mut loss_sum = 0f64;
+
+        data.shuffle(&mut rng);
+
+        for chunk in data.chunks(config.batch_size) {
+            weights.clear();
+            weights.extend(chunk.iter().map(|item| F::weight(config, item)));
+            seeds.clear();
+            seeds.extend(chunk.iter().map(|_| rng.gen::<u64>()));
+            loss_sum += fitting.fit_chunk(config, chunk, &weights, &seeds);
+        }
+
+        history.epochs = i;
+        history.train_loss.push(loss_sum / data.len() as f64 + fitting.penalty(config));
+        fitting.end_iter(i, config, history);
+
+        if !validation.is_empty() {
+            let loss = fitting.validate(config, validation, history);
+            history.val_loss.push(loss);
+
+            if history.best_val_loss.map_or(true, |b| loss < b) {
+                history.best_val_loss = Some(loss);
+                history.best_epoch = Some(i);
+                fitting.keep_best();
+            } else if patience.is_some_and(|p| i - history.best_epoch.unwrap() >= p) {
+                history.stopped_early = true;
+                break;
+            }
+        }
+    }
+}
+
+/// State of [`Trainer::build`] kept across chunks and iters.
+struct Fitting<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> {
+    layers: L,
+    best: Option<L>,
+    moments: Moments<L>,
+    scratches: Vec<Scratch<L, O, T>>,
+    outputs: Vec<SVector<T, O>>,
+    /// labels and outputs of the iter for metrics, only kept with metrics
+    labels: Vec<SVector<T, O>>,
+    epoch_outputs: Vec<SVector<T, O>>,
+    val_labels: Vec<SVector<T, O>>,
+    val_outputs: Vec<SVector<T, O>>,
+    item_lstn: Option<ItemLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    chunk_lstn: Option<ChunkLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    iter_lstn: Option<IterLstn<'a, A, C, Opt, Sch, L, Cal, I, O, T>>,
+    metrics: Vec<Box<dyn 'a + Metric<O, T>>>,
+}
+
+impl<'a, A, C, Opt, Sch, L, Cal, const I: usize, const O: usize, T> Fit<A, C, Opt, Sch>
+    for Fitting<'a, A, C, Opt, Sch, L, Cal, I, O, T>
+where
+    C: LossFunc,
+    Opt: Optimizer,
+    L: Layers<A, Cal, I, O, T> + Default + Clone + Params<T> + MaybeSync,
+    Cal: Calculation<O, T>,
+    T: Real + MaybeSync
+{
+    type Item = Item<I, O, T>;
+
+    #[inline]
+    fn weight(config: &Config<A, C, Opt, Sch>, item: &Self::Item) -> f64 {
+        config.item_weight(item)
+    }
+
+    fn fit_chunk(&mut self, config: &Config<A, C, Opt, Sch>, chunk: &[&Self::Item], weights: &[f64], seeds: &[u64]) -> f64 {
+        let used = backprop_chunk::<A, C, _, _, I, O, T>(&self.layers, chunk, weights, seeds, &mut self.scratches);
+        self.outputs.clear();
+        self.outputs.extend(self.scratches[..used].iter().flat_map(|s| s.outputs.iter().copied()));
+
+        if let Some(f) = &self.item_lstn {
+            for (item, out) in chunk.iter().zip(self.outputs.iter()) {
+                f(TempModel::new(&self.layers), config, item, out);
+            }
+        }
+
+        let gradients = self.scratches[..used].iter_mut().map(|s| std::mem::take(&mut s.grad));
+        self.layers.update(config.learn_rate, &config.optimizer, &mut self.moments, &config.regularization, gradients);
+
+        let loss = chunk.iter()
+            .zip(self.outputs.iter())
+            .zip(weights.iter())
+            .map(|((item, out), w)| w * to_f64(C::f(&item.label, out)))
+            .sum::<f64>();
+
+        if let Some(f) = &self.chunk_lstn {
+            f(TempModel::new(&self.layers), config, chunk, &self.outputs);
+        }
+
+        if !self.metrics.is_empty() {
+            self.labels.extend(chunk.iter().map(|item| item.label));
+            self.epoch_outputs.extend(self.outputs.iter().copied());
+        }
+        loss
+    }
 
     #[inline]
-    fn call_iter_lstn(&self, iter_num: usize) {
+    fn penalty(&self, config: &Config<A, C, Opt, Sch>) -> f64 {
+        config.regularization.loss(&self.layers)
+    }
+
+    fn end_iter(&mut self, i: usize, config: &Config<A, C, Opt, Sch>, history: &mut History) {
+        for (metric, values) in self.metrics.iter().zip(history.train_metrics.iter_mut()) {
+            values.1.push(metric.compute(&self.labels, &self.epoch_outputs));
+        }
+        self.labels.clear();
+        self.epoch_outputs.clear();
+
         if let Some(f) = &self.iter_lstn {
-            f(iter_num,TempModel::new(&self.layers), &self.config);
+            f(i, TempModel::new(&self.layers), config);
+        }
+    }
+
+    fn validate(&mut self, config: &Config<A, C, Opt, Sch>, items: &[&Self::Item], history: &mut History) -> f64 {
+        let layers = &self.layers;
+        self.val_outputs.clear();
+        self.val_outputs.extend(items.iter().map(|item| layers.test(&item.data)));
+        for (metric, values) in self.metrics.iter().zip(history.val_metrics.iter_mut()) {
+            values.1.push(metric.compute(&self.val_labels, &self.val_outputs));
+        }
+
+        let sum = items.iter()
+            .zip(&self.val_outputs)
+            .map(|(item, out)| config.item_weight(item) * to_f64(C::f(&item.label, out)))
+            .sum::<f64>();
+        sum / items.len() as f64
+    }
+
+    #[inline]
+    fn keep_best(&mut self) {
+        self.best = Some(self.layers.clone());
+    }
+}
+
+/// Per-iter losses and metrics of a training run, with a summary of it.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    /// count of iters actually run
+    pub epochs: usize,
+    /// average training loss of each iter, including the penalty of `Config::regularization`
+    pub train_loss: Vec<f64>,
+    /// average validation loss of each iter, empty without validation
+    pub val_loss: Vec<f64>,
+    /// name and values of each iter of metrics on training items, in order of
+    /// `Trainer::metric` or [`DynTrainer::metric`](crate::dynamic::DynTrainer::metric), computed
+    /// from outputs of the iter as they were trained
+    pub train_metrics: Vec<(String, Vec<f64>)>,
+    /// name and values of each iter of metrics on validation items, values are empty without validation
+    pub val_metrics: Vec<(String, Vec<f64>)>,
+    /// iter with the lowest validation loss, whose weights are returned
+    pub best_epoch: Option<usize>,
+    pub best_val_loss: Option<f64>,
+    /// whether training stopped before `iter_num` because of patience
+    pub stopped_early: bool,
+}
+
+impl History {
+    pub(crate) fn new(metrics: impl Iterator<Item = String>) -> Self {
+        let metrics = metrics.map(|name| (name, Vec::new())).collect_vec();
+        Self {
+            train_metrics: metrics.clone(),
+            val_metrics: metrics,
+            ..Default::default()
+        }
+    }
+
+    /// room for values of `iters` iters, so that pushing them does not allocate
+    pub(crate) fn reserve(&mut self, iters: usize) {
+        self.train_loss.reserve(iters);
+        self.val_loss.reserve(iters);
+        for (_, values) in self.train_metrics.iter_mut().chain(self.val_metrics.iter_mut()) {
+            values.reserve(iters);
         }
     }
+
+    /// values of metric `name` on training items
+    pub fn metric(&self, name: &str) -> Option<&[f64]> {
+        find(&self.train_metrics, name)
+    }
+
+    /// values of metric `name` on validation items
+    pub fn val_metric(&self, name: &str) -> Option<&[f64]> {
+        find(&self.val_metrics, name)
+    }
+}
+
+#[inline]
+fn find<'h>(metrics: &'h [(String, Vec<f64>)], name: &str) -> Option<&'h [f64]> {
+    metrics.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_slice())
 }
 
-pub struct TempModel<'a, L, F, C, const I: usize, const O: usize> {
+pub struct TempModel<'a, L, F, C, const I: usize, const O: usize, T = f64> {
     pub layers: &'a L,
-    _maker: PhantomData<(F,C)>,
+    _maker: PhantomData<(F,C,T)>,
 }
 
-impl<'a, L, F, C, const I: usize, const O: usize> TempModel<'a,L, F, C,I,O> 
+impl<'a, L, F, C, const I: usize, const O: usize, T> TempModel<'a,L, F, C,I,O,T> 
 where
-    L: Layers<F, C, I, O>,
-    C: Calculation<O>
+    L: Layers<F, C, I, O, T>,
+    C: Calculation<O, T>,
+    T: Real
 {
     fn new(layers: &'a L) -> Self {
         Self {
@@ -150,7 +537,7 @@ where
     }
 
     #[inline]
-    pub fn test(&self, item: &SVector<f64, I>) -> SVector<f64,O> {
+    pub fn test(&self, item: &SVector<T, I>) -> SVector<T,O> {
         self.layers.test(item)
     }
 }
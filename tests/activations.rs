@@ -0,0 +1,160 @@
+extern crate simple_nn as nn;
+
+use nn::{func::*, model::SVector};
+
+const POINTS: [f64; 7] = [-20., -2.5, -0.7, 0.3, 1.1, 2.9, 20.];
+
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.)
+}
+
+/// compare `A::d` with the central difference of `A::f` at every point
+fn check_derivative<A: ActivitionFunc>() {
+    const H: f64 = 1e-6;
+    for x in POINTS {
+        let numeric = (A::f(x + H) - A::f(x - H)) / (2. * H);
+        let d = A::d(x, A::f(x));
+        assert!(close(numeric, d, 1e-6), "{}: d({}) = {}, numeric {}", std::any::type_name::<A>(), x, d, numeric);
+    }
+}
+
+/// `f` is finite for inputs which overflow `exp`
+fn check_stable<A: ActivitionFunc>() {
+    for x in [-1e3f64, -800., 800., 1e3] {
+        let y = A::f(x);
+        assert!(y.is_finite(), "{}: f({}) = {}", std::any::type_name::<A>(), x, y);
+        assert!(A::d(x, y).is_finite(), "{}: d({}) is not finite", std::any::type_name::<A>(), x);
+    }
+}
+
+macro_rules! activation_tests {
+    ($($name:ident: $act:ty),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                check_derivative::<$act>();
+                check_stable::<$act>();
+            }
+        )*
+    };
+}
+
+activation_tests! {
+    tanh: Tanh,
+    sigmoid: Sigmoid,
+    identity: Identity,
+    relu: ReLU,
+    leaky_relu: LeakyReLU,
+    elu: ELU,
+    selu: SELU,
+    softplus: Softplus,
+    hard_tanh: HardTanh,
+    hard_sigmoid: HardSigmoid,
+    gelu: GELU,
+    swish: Swish,
+    mish: Mish,
+}
+
+#[test]
+fn sigmoid_values() {
+    assert_eq!(Sigmoid::f(0.), 0.5);
+    assert!(close(Sigmoid::f(2.), 1. / (1. + (-2f64).exp()), 1e-15));
+    assert!(close(Sigmoid::f(-2.), 1. - Sigmoid::f(2.), 1e-15));
+    assert_eq!(Sigmoid::f(-1e3), 0.);
+    assert_eq!(Sigmoid::f(1e3), 1.);
+}
+
+#[test]
+fn tanh_values() {
+    assert_eq!(Tanh::f(0.), 0.);
+    assert!(close(Tanh::f(0.5), 0.5f64.tanh(), 1e-15));
+    assert_eq!(Tanh::f(1e3), 1.);
+    assert_eq!(Tanh::f(-1e3), -1.);
+}
+
+#[test]
+fn piecewise_values() {
+    assert_eq!(ReLU::f(-1.), 0.);
+    assert_eq!(ReLU::f(2.), 2.);
+    assert_eq!(LeakyReLU::f(-1.), -LeakyReLU::SLOPE);
+    assert!(close(ELU::f(-1.), (-1f64).exp() - 1., 1e-15));
+    assert!(close(SELU::f(1.), SELU::LAMBDA, 1e-15));
+    assert_eq!(HardTanh::f(-3.), -1.);
+    assert_eq!(HardTanh::f(0.5), 0.5);
+    assert_eq!(HardSigmoid::f(0.), 0.5);
+    assert_eq!(HardSigmoid::f(4.), 1.);
+    assert_eq!(HardSigmoid::f(-4.), 0.);
+}
+
+#[test]
+fn smooth_values() {
+    assert!(close(Softplus::f(0.), 2f64.ln(), 1e-15));
+    assert_eq!(Softplus::f(1e3), 1e3);
+    assert!(close(Swish::f(1.), Sigmoid::f(1.), 1e-15));
+    assert!(close(Mish::f(1.), Softplus::f(1f64).tanh(), 1e-15));
+    assert_eq!(GELU::f(0.), 0.);
+    // tanh approximation is close to x * Φ(x)
+    assert!(close(GELU::f(1.), 0.841_344_746, 1e-3));
+}
+
+#[test]
+fn vector_helpers() {
+    let v = SVector::from([-1., 0., 2.]);
+    assert_eq!(Tanh::fv(&v), v.map(Tanh::f));
+    let y = Sigmoid::fv(&v);
+    assert_eq!(Sigmoid::d_from_yv(&y), y.map(Sigmoid::d_from_y));
+}
+
+#[test]
+fn d_from_y_is_d() {
+    fn check<A: DerivFromY>() {
+        for x in POINTS {
+            let y = A::f(x);
+            assert_eq!(A::d_from_y(y), A::d(x, y), "{}", std::any::type_name::<A>());
+        }
+    }
+    check::<Tanh>();
+    check::<Sigmoid>();
+    check::<Identity>();
+    check::<ReLU>();
+    check::<LeakyReLU>();
+    check::<ELU>();
+    check::<SELU>();
+    check::<Softplus>();
+    check::<HardTanh>();
+    check::<HardSigmoid>();
+}
+
+#[test]
+fn softmax_sums_to_one() {
+    let p = softmax(&SVector::from([1., 2., 3., 4.]));
+    assert!(close(p.sum(), 1., 1e-15));
+    assert!(p.iter().zip(p.iter().skip(1)).all(|(a, b)| a < b));
+}
+
+#[test]
+fn softmax_values() {
+    let p = softmax(&SVector::from([0., 2f64.ln()]));
+    assert!(close(p[0], 1. / 3., 1e-15));
+    assert!(close(p[1], 2. / 3., 1e-15));
+}
+
+#[test]
+fn softmax_is_shift_invariant_and_stable() {
+    let x = SVector::from([0.5, -1., 2.]);
+    let p = softmax(&x);
+    let shifted: SVector<f64, 3> = softmax(&x.add_scalar(1000.));
+    assert!(shifted.iter().all(|p| p.is_finite()));
+    assert!((p - shifted).amax() < 1e-12);
+}
+
+#[test]
+fn softmax_in_place() {
+    let x = SVector::from([0.5, -1., 2.]);
+    let mut y = x;
+    softmax_with(&mut y);
+    assert_eq!(y, softmax(&x));
+    let mut z = x;
+    z.softmax();
+    assert_eq!(z, y);
+}
@@ -0,0 +1,68 @@
+//! steady-state training does not allocate, only checked without `parallel`
+//! since rayon allocates to schedule its jobs, and with batches small enough that
+//! nalgebra multiplies them without the packing buffer of its blocked product
+#![cfg(not(feature = "parallel"))]
+
+extern crate simple_nn as nn;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use nn::{Config, Item, Network, derive_layers, func::*, model::*, optim::Adam};
+
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::SeqCst);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::SeqCst);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive_layers(3, activation(ReLU, Tanh, Sigmoid), dropout(0.2, _, _), init(HeNormal, XavierNormal, XavierNormal))]
+struct Net{}
+
+fn data(rng: &mut StdRng) -> Vec<Item<4, 2>> {
+    (0..50)
+        .map(|_| Item::new(
+            SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0)),
+            SVector::from_fn(|_, _| rng.gen_range(0.0..1.0)),
+        ))
+        .collect()
+}
+
+#[test]
+fn steady_state_training_does_not_allocate() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let data = data(&mut rng);
+    let layers = Net::<4, 16, 8, 2>::random_with(&mut rng);
+    let mut config = Config::default_with_func(Sigmoid, DistanceFunc).with_optimizer(Adam::default());
+    // the last chunk is smaller than the others
+    config.batch_size = 8;
+    config.iter_num = 20;
+    config.seed = Some(3);
+
+    let counts = RefCell::new(Vec::with_capacity(config.iter_num));
+    let mut trainer = Network::cfg(layers, config).train(&data);
+    trainer.after_each_iter(|_, _, _| counts.borrow_mut().push(ALLOCS.load(Ordering::SeqCst)));
+    trainer.build();
+
+    let counts = counts.into_inner();
+    // buffers are warm after the first iters
+    assert_eq!(counts[2], counts[counts.len() - 1], "allocations after each iter: {:?}", counts);
+}
@@ -0,0 +1,134 @@
+extern crate simple_nn as nn;
+
+use nn::{derive_layers, func::*, model::*};
+
+const TOLERANCE: f64 = 1e-12;
+
+#[derive_layers(3, activation(Tanh, Sigmoid, Identity), init(XavierNormal, XavierNormal, XavierNormal))]
+struct Mixed{}
+
+#[derive_layers(3, activation(ELU, Softplus, _), softmax, init(HeNormal, HeNormal, XavierNormal))]
+struct Classifier{}
+
+#[derive_layers(3, dropout(0.5, 0.2, _), init(HeNormal, HeNormal, XavierNormal))]
+struct Dropout{}
+
+fn inputs<const I: usize>(rng: &mut StdRng, len: usize) -> Batch<f64, I> {
+    Batch::from_fn(len, |_, _| rng.gen_range(-1.0..1.0))
+}
+
+fn labels<const O: usize>(rng: &mut StdRng, len: usize) -> Batch<f64, O> {
+    Batch::from_fn(len, |_, _| rng.gen_range(0.0..1.0))
+}
+
+/// `DistanceFunc` gradient of each column of `outputs`
+fn d_out<const O: usize>(labels: &Batch<f64, O>, outputs: &Batch<f64, O>) -> Batch<f64, O> {
+    let columns = labels.column_iter()
+        .zip(outputs.column_iter())
+        .map(|(l, o)| DistanceFunc::d(&l.into_owned(), &o.into_owned()))
+        .collect::<Vec<SVector<f64, O>>>();
+    Batch::from_columns(&columns)
+}
+
+/// outputs and average gradient of items through `forward_train` and `backward` one by one
+fn per_item<A, L, Cal, const I: usize, const O: usize>(
+    layers: &L,
+    inputs: &Batch<f64, I>,
+    labels: &Batch<f64, O>,
+    seeds: &[u64],
+    _actvt: A
+) -> (Vec<SVector<f64, O>>, Vec<f64>)
+where
+    L: Layers<A, Cal, I, O, f64> + Params<f64>,
+    Cal: Calculation<O, f64>
+{
+    let mut outputs = Vec::new();
+    let mut sum: Vec<f64> = Vec::new();
+    for ((x, l), &seed) in inputs.column_iter().zip(labels.column_iter()).zip(seeds) {
+        let x = x.into_owned();
+        let calc = layers.forward_train(&x, &mut StdRng::seed_from_u64(seed));
+        let out = calc.out().to_owned();
+        let grad = layers.backward(&x, DistanceFunc::d(&l.into_owned(), &out), calc);
+        let flat = flatten(&grad);
+        if sum.is_empty() {
+            sum = flat;
+        } else {
+            sum.iter_mut().zip(flat).for_each(|(s, g)| *s += g);
+        }
+        outputs.push(out);
+    }
+    sum.iter_mut().for_each(|s| *s /= seeds.len() as f64);
+    (outputs, sum)
+}
+
+fn flatten<L: Params<f64>>(layers: &L) -> Vec<f64> {
+    layers.params()
+        .into_iter()
+        .flat_map(|(w, b)| w.iter().chain(b).copied().collect::<Vec<_>>())
+        .collect()
+}
+
+fn assert_batch_matches<A, L, Cal, const I: usize, const O: usize>(layers: &L, len: usize, actvt: A)
+where
+    L: Layers<A, Cal, I, O, f64> + Params<f64> + Default,
+    Cal: Calculation<O, f64>
+{
+    let mut rng = StdRng::seed_from_u64(11);
+    let inputs = inputs::<I>(&mut rng, len);
+    let labels = labels::<O>(&mut rng, len);
+    let seeds = (0..len).map(|_| rng.gen()).collect::<Vec<u64>>();
+
+    let (outputs, gradient) = layers.backprop_batch(&inputs, &seeds, |out| d_out(&labels, out));
+    let (expected_out, expected_grad) = per_item(layers, &inputs, &labels, &seeds, actvt);
+
+    for (j, out) in expected_out.iter().enumerate() {
+        for (a, b) in outputs.column(j).iter().zip(out.iter()) {
+            assert!((a - b).abs() < TOLERANCE, "output of item {} is {} instead of {}", j, a, b);
+        }
+    }
+    for (a, b) in flatten(&gradient).into_iter().zip(expected_grad) {
+        assert!((a - b).abs() < TOLERANCE, "gradient is {} instead of {}", a, b);
+    }
+}
+
+#[test]
+fn batch_matches_per_item() {
+    let layers = Mixed::<3, 6, 4, 2>::random_with(&mut StdRng::seed_from_u64(1));
+    assert_batch_matches(&layers, 7, Sigmoid);
+}
+
+#[test]
+fn batch_matches_per_item_with_softmax() {
+    let layers = Classifier::<4, 5, 6, 3>::random_with(&mut StdRng::seed_from_u64(2));
+    assert_batch_matches(&layers, 5, Sigmoid);
+}
+
+#[test]
+fn batch_matches_per_item_with_dropout() {
+    let layers = Dropout::<3, 8, 8, 2>::random_with(&mut StdRng::seed_from_u64(3));
+    assert_batch_matches(&layers, 9, ReLU);
+}
+
+#[test]
+fn single_item_batch() {
+    let layers = Mixed::<3, 6, 4, 2>::random_with(&mut StdRng::seed_from_u64(4));
+    assert_batch_matches(&layers, 1, Sigmoid);
+}
+
+#[test]
+fn backward_into_adds_to_gradient() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let layers = Dropout::<3, 8, 8, 2>::random_with(&mut rng);
+    let x = SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0));
+    let g = SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0));
+    let calc = |layers: &Dropout<3, 8, 8, 2>| Layers::<ReLU, _, 3, 2>::forward_train(layers, &x, &mut StdRng::seed_from_u64(9));
+
+    let once = Layers::<ReLU, _, 3, 2>::backward(&layers, &x, g, calc(&layers));
+    let mut twice = Dropout::default();
+    Layers::<ReLU, _, 3, 2>::backward_into(&layers, &mut twice, &x, g, calc(&layers));
+    Layers::<ReLU, _, 3, 2>::backward_into(&layers, &mut twice, &x, g, calc(&layers));
+
+    for (a, b) in flatten(&twice).into_iter().zip(flatten(&once)) {
+        assert!((a - 2. * b).abs() < TOLERANCE, "gradient is {} instead of {}", a, 2. * b);
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers shared by the integration tests, each of which uses only some of them.
+#![allow(dead_code)]
+
+use nn::{Item, model::*};
+
+/// step of central differences
+pub const EPS: f64 = 1e-6;
+/// largest relative error of `gradient_check` on a layer
+pub const TOLERANCE: f64 = 1e-5;
+
+pub fn rng() -> StdRng {
+    StdRng::seed_from_u64(7)
+}
+
+/// uniform in `[-1, 1)`
+pub fn random<const N: usize>(rng: &mut StdRng) -> SVector<f64, N> {
+    SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0))
+}
+
+/// data uniform in `[-1, 1)` and label in `[0, 1)`
+pub fn item<const I: usize, const O: usize>(rng: &mut StdRng) -> Item<I, O> {
+    Item::new(random(rng), SVector::from_fn(|_, _| rng.gen_range(0.0..1.0)))
+}
+
+/// `errors` of `gradient_check` are of `layer_count` layers, each under [`TOLERANCE`]
+pub fn assert_close(errors: Vec<f64>, layer_count: usize) {
+    assert_eq!(errors.len(), layer_count);
+    for (i, e) in errors.into_iter().enumerate() {
+        assert!(e < TOLERANCE, "layer {} has relative error {}", i + 1, e);
+    }
+}
+
+/// compare `backward_into` of `module` with central differences of `sum(out * r)`
+/// on every parameter and every input, within an absolute error of `1e-6`
+pub fn check_module<M, const I: usize, const O: usize>(module: &M, rng: &mut StdRng)
+where
+    M: Module<Input = SVector<f64, I>, Output = SVector<f64, O>>
+{
+    const TOLERANCE: f64 = 1e-6;
+
+    let input = random::<I>(rng);
+    let r = random::<O>(rng);
+    let loss = |m: &M, x: &SVector<f64, I>| m.test(x).dot(&r);
+
+    let (_, cache) = module.forward(&input);
+    let mut grad = M::default();
+    let dx = module.backward_into(&mut grad, &input, &cache, &r);
+
+    for (i, &a) in dx.iter().enumerate() {
+        let (mut plus, mut minus) = (input, input);
+        plus[i] += EPS;
+        minus[i] -= EPS;
+        let numeric = (loss(module, &plus) - loss(module, &minus)) / (2. * EPS);
+        assert!((a - numeric).abs() < TOLERANCE, "input {}: {} != {}", i, a, numeric);
+    }
+
+    let mut probe = module.clone();
+    for (layer, (w, b)) in grad.params().into_iter().enumerate() {
+        for (i, &a) in w.iter().chain(b).enumerate() {
+            let mut loss_at = |delta: f64| {
+                let (pw, pb) = probe.params_mut().swap_remove(layer);
+                let p = if i < pw.len() { &mut pw[i] } else { &mut pb[i - pw.len()] };
+                *p += delta;
+                loss(&probe, &input)
+            };
+            let numeric = (loss_at(EPS) - loss_at(-2. * EPS)) / (2. * EPS);
+            loss_at(EPS);
+            assert!((a - numeric).abs() < TOLERANCE, "param {}: {} != {}", i, a, numeric);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Compile tests of `derive_layers`, run `TRYBUILD=overwrite cargo test --test compile`
+//! to regenerate the expected errors.
+
+#[test]
+fn derive_layers() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
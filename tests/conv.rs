@@ -0,0 +1,123 @@
+extern crate simple_nn as nn;
+
+use nn::{conv::*, model::*};
+
+mod common;
+
+use common::{check_module, rng};
+
+#[test]
+fn conv2d_forward() {
+    let mut conv = Conv2d::<1, 1, 3, 3, 2, 1, 0, 9, 4>::default();
+    conv.w.fill(1.);
+    conv.b[0] = 0.5;
+    let x = SVector::from([1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+    let out = conv.test(&x);
+    assert_eq!(out, SVector::from([12.5, 16.5, 24.5, 28.5]));
+}
+
+#[test]
+fn conv2d_padding_and_stride() {
+    let mut conv = Conv2d::<1, 2, 3, 3, 3, 2, 1, 9, 8>::default();
+    conv.w.row_mut(0).fill(1.);
+    // the second kernel picks the center pixel of each window
+    conv.w[(1, 4)] = 1.;
+    let x = SVector::from([1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+    let out = conv.test(&x);
+    assert_eq!(out, SVector::from([12., 16., 24., 28., 1., 3., 7., 9.]));
+}
+
+#[test]
+fn conv1d_channels() {
+    // a kernel per input channel, laid out as the input
+    let mut conv = Conv1d::<2, 1, 3, 2, 1, 0, 6, 2>::default();
+    conv.w.copy_from_slice(&[1., 0., 0., 1.]);
+    let x = SVector::from([1., 2., 3., 10., 20., 30.]);
+    assert_eq!(conv.test(&x), SVector::from([21., 32.]));
+}
+
+#[test]
+fn max_pool() {
+    let pool = MaxPool2d::<1, 4, 4, 2, 2, 16, 4>::default();
+    let x = SVector::from([
+        1., 5., 2., 0.,
+        3., 4., 8., 8.,
+        0., 0., 1., 1.,
+        -1., 0., 1., 2.,
+    ]);
+    let (out, cache) = pool.forward(&x);
+    assert_eq!(out, SVector::from([5., 8., 0., 2.]));
+
+    // the gradient goes to the first max of each window
+    let dx = pool.backward_into(&mut Default::default(), &x, &cache, &SVector::from([1., 2., 3., 4.]));
+    let expected = SVector::from([
+        0., 1., 0., 0.,
+        0., 0., 2., 0.,
+        3., 0., 0., 0.,
+        0., 0., 0., 4.,
+    ]);
+    assert_eq!(dx, expected);
+}
+
+#[test]
+fn avg_pool() {
+    let pool = AvgPool1d::<2, 4, 2, 2, 8, 4>::default();
+    let x = SVector::from([1., 3., 5., 7., 0., 2., 4., 4.]);
+    let (out, cache) = pool.forward(&x);
+    assert_eq!(out, SVector::from([2., 6., 1., 4.]));
+
+    let dx = pool.backward_into(&mut Default::default(), &x, &cache, &SVector::from([2., 4., 6., 8.]));
+    assert_eq!(dx, SVector::from([1., 1., 2., 2., 3., 3., 4., 4.]));
+}
+
+#[test]
+fn flatten_passes_through() {
+    let flat = Flatten::<3>::default();
+    let x = SVector::from([1., 2., 3.]);
+    assert_eq!(flat.test(&x), x);
+    assert_eq!(flat.backward_into(&mut Default::default(), &x, &(), &x), x);
+    assert!(Flatten::<3>::shapes().is_empty());
+}
+
+#[test]
+fn shapes() {
+    assert_eq!(Conv2d::<3, 4, 5, 5, 3, 1, 1, 75, 100>::shapes(), vec![(4, 27)]);
+    assert_eq!(Conv1d::<2, 3, 8, 3, 2, 0, 16, 9>::shapes(), vec![(3, 6)]);
+    assert!(MaxPool2d::<1, 4, 4, 2, 2, 16, 4>::shapes().is_empty());
+}
+
+#[test]
+fn init_with() {
+    let conv = Conv2d::<2, 3, 4, 4, 3, 1, 0, 32, 12>::init_with(Init::HeNormal, &mut rng());
+    assert!(conv.w.iter().all(|w| w.abs() > 0.));
+    assert!(conv.b.iter().all(|&b| b == 0.));
+}
+
+#[test]
+fn conv1d_gradient() {
+    let mut rng = rng();
+    let conv = Conv1d::<2, 3, 7, 3, 2, 1, 14, 12>::init_with(Init::XavierNormal, &mut rng);
+    check_module(&conv, &mut rng);
+}
+
+#[test]
+fn conv2d_gradient() {
+    let mut rng = rng();
+    let conv = Conv2d::<2, 2, 5, 4, 3, 2, 1, 40, 12>::init_with(Init::XavierNormal, &mut rng);
+    check_module(&conv, &mut rng);
+}
+
+#[test]
+fn pool_gradient() {
+    let mut rng = rng();
+    check_module(&MaxPool2d::<2, 4, 5, 2, 1, 40, 24>::default(), &mut rng);
+    check_module(&AvgPool2d::<2, 4, 5, 3, 1, 40, 12>::default(), &mut rng);
+    check_module(&MaxPool1d::<3, 6, 3, 3, 18, 6>::default(), &mut rng);
+    check_module(&AvgPool1d::<3, 6, 2, 1, 18, 15>::default(), &mut rng);
+}
+
+#[test]
+fn dense_gradient() {
+    let mut rng = rng();
+    check_module(&Layer::<3, 4>::init_with(Init::XavierNormal, &mut rng), &mut rng);
+}
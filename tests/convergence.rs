@@ -0,0 +1,92 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Item, Network, derive_layers, func::*, metric::Accuracy, model::*, optim::Adam, schedule::Constant};
+
+#[path = "../examples/derive_layers/data.rs"]
+mod data;
+
+#[derive_layers(2, init(XavierNormal, XavierNormal))]
+struct Xor{}
+
+#[derive_layers(2, activation(Tanh, _), softmax, init(XavierNormal, XavierNormal))]
+struct Iris{}
+
+fn xor() -> Vec<Item<2, 1>> {
+    [(0., 0., 0.), (0., 1., 1.), (1., 0., 1.), (1., 1., 0.)]
+        .iter()
+        .map(|&(a, b, y)| Item::new(SVector::from([a, b]), SVector::from([y])))
+        .collect()
+}
+
+/// iris items with features scaled into `[0, 1]` and one-hot labels
+fn iris() -> Vec<Item<4, 3>> {
+    let rows: Vec<([f64; 4], usize)> = data::IRIS.lines().filter(|line| !line.trim().is_empty()).map(|line| {
+        let cols: Vec<&str> = line.split(',').collect();
+        let mut features = [0.; 4];
+        for (f, c) in features.iter_mut().zip(&cols) {
+            *f = c.parse().unwrap();
+        }
+        let class = match cols[4] {
+            "Iris-setosa" => 0,
+            "Iris-versicolor" => 1,
+            _ => 2,
+        };
+        (features, class)
+    }).collect();
+
+    let (mut min, mut max) = ([f64::MAX; 4], [f64::MIN; 4]);
+    for (features, _) in &rows {
+        for i in 0..4 {
+            min[i] = min[i].min(features[i]);
+            max[i] = max[i].max(features[i]);
+        }
+    }
+    rows.into_iter().map(|(features, class)| {
+        let mut label = SVector::zeros();
+        label[class] = 1.;
+        Item::new(SVector::from_fn(|i, _| (features[i] - min[i]) / (max[i] - min[i])), label)
+    }).collect()
+}
+
+#[test]
+fn xor_converges() {
+    let data = xor();
+    let layers = Xor::<2, 4, 1>::random_with(&mut StdRng::seed_from_u64(3));
+    let config = Config {
+        learn_rate: 0.05,
+        batch_size: 4,
+        iter_num: 2_000,
+        seed: Some(3),
+        ..Config::default_with_func(Tanh, DistanceFunc)
+    }.with_optimizer(Adam::default()).with_scheduler(Constant);
+
+    let (model, _) = Network::cfg(layers, config).train(&data[..]).build();
+    let eval = model.evaluate(&data, DistanceFunc, &[&Accuracy]);
+    assert_eq!(eval.metric("accuracy"), Some(1.));
+    assert!(eval.loss < 0.05, "loss {}", eval.loss);
+}
+
+#[test]
+fn iris_converges() {
+    let data = iris();
+    // every 5th item is held out
+    let (test, train): (Vec<_>, Vec<_>) = data.into_iter().enumerate().partition(|(i, _)| i % 5 == 0);
+    let test: Vec<_> = test.into_iter().map(|(_, item)| item).collect();
+    let train: Vec<_> = train.into_iter().map(|(_, item)| item).collect();
+
+    let layers = Iris::<4, 8, 3>::random_with(&mut StdRng::seed_from_u64(5));
+    let config = Config {
+        learn_rate: 0.01,
+        batch_size: 10,
+        iter_num: 300,
+        seed: Some(5),
+        ..Config::default_with_func(Tanh, CrossEntroy)
+    }.with_optimizer(Adam::default()).with_scheduler(Constant);
+
+    let (model, history) = Network::cfg(layers, config).train(&train[..]).build();
+    assert!(history.train_loss.last() < history.train_loss.first());
+
+    let eval = model.evaluate(&test, CrossEntroy, &[&Accuracy]);
+    let accuracy = eval.metric("accuracy").unwrap();
+    assert!(accuracy >= 0.9, "accuracy {}", accuracy);
+}
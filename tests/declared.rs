@@ -0,0 +1,199 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Item, Network, check::gradient_check, conv::*, derive_layers, func::*, model::*, optim::Adam};
+
+mod common;
+
+use common::{EPS, assert_close, item, rng};
+
+#[derive_layers(input(32), output(3), softmax)]
+struct Cnn {
+    #[activation(Tanh)]
+    #[init(XavierNormal)]
+    conv: Conv2d<2, 3, 4, 4, 3, 1, 1, 32, 48, T>,
+    pool: MaxPool2d<3, 4, 4, 2, 2, 48, 12, T>,
+    flat: Flatten<12, T>,
+    #[activation(_)]
+    #[dropout(0.2)]
+    #[init(XavierNormal)]
+    hidden: Layer<6, 12, T>,
+    #[init(XavierNormal)]
+    out: Layer<3, 6, T>,
+}
+
+#[derive_layers(input(12), output(2))]
+struct Signal {
+    #[activation(Softplus)]
+    #[init(HeNormal)]
+    conv: Conv1d<2, 2, 6, 3, 2, 1, 12, 6, T>,
+    pool: AvgPool1d<2, 3, 2, 1, 6, 4, T>,
+    #[activation(Sigmoid)]
+    #[init(XavierNormal)]
+    out: Layer<2, 4, T>,
+}
+
+#[derive_layers(input(3), output(2))]
+struct Dense {
+    #[activation(_)]
+    first: Layer<4, 3, T>,
+    #[activation(Sigmoid)]
+    second: Layer<2, 4, T>,
+}
+
+#[derive_layers(2, activation(_, Sigmoid))]
+struct Counted{}
+
+/// multiply the input by a learned scalar, a custom layer with a cache
+#[derive(Clone)]
+struct Scale<const N: usize, T = f64> {
+    s: SVector<T, 1>,
+    none: SVector<T, 0>,
+}
+
+impl<const N: usize, T: Real> Default for Scale<N, T> {
+    fn default() -> Self {
+        Self { s: SVector::zeros(), none: SVector::zeros() }
+    }
+}
+
+impl<const N: usize, T> Params<T> for Scale<N, T> {
+    fn shapes() -> Vec<(usize, usize)> {
+        vec![(0, 0)]
+    }
+
+    fn params(&self) -> Vec<(&[T], &[T])> {
+        vec![(self.none.as_slice(), self.s.as_slice())]
+    }
+
+    fn params_mut(&mut self) -> Vec<(&mut [T], &mut [T])> {
+        vec![(self.none.as_mut_slice(), self.s.as_mut_slice())]
+    }
+}
+
+impl<const N: usize, T: Real> Module<T> for Scale<N, T> {
+    type Input = SVector<T, N>;
+    type Output = SVector<T, N>;
+    /// the input times the scalar, kept to check that caches reach `backward_into`
+    type Cache = SVector<T, N>;
+
+    fn forward(&self, input: &SVector<T, N>) -> (SVector<T, N>, SVector<T, N>) {
+        let out = input * self.s[0];
+        (out, out)
+    }
+
+    fn backward_into(&self, grad: &mut Self, input: &SVector<T, N>, cache: &SVector<T, N>, d_out: &SVector<T, N>) -> SVector<T, N> {
+        assert_eq!(*cache, input * self.s[0]);
+        grad.s[0] += input.dot(d_out);
+        d_out * self.s[0]
+    }
+
+    fn init_with<R: Rng + ?Sized>(_: Init, _: &mut R) -> Self {
+        Self { s: SVector::repeat(T::one()), none: SVector::zeros() }
+    }
+}
+
+#[derive_layers(input(3), output(3))]
+struct Custom {
+    #[activation(Tanh)]
+    #[init(XavierNormal)]
+    dense: Layer<3, 3, T>,
+    scale: Scale<3, T>,
+}
+
+#[test]
+fn cnn_gradient() {
+    let mut rng = rng();
+    let net = Cnn::random_with(&mut rng);
+    let mut item = item::<32, 3>(&mut rng);
+    item.label = SVector::from([0., 1., 0.]);
+    assert_close(gradient_check(&net, &item, ReLU, CrossEntroy, EPS), 3);
+}
+
+#[test]
+fn conv1d_gradient() {
+    let mut rng = rng();
+    let net = Signal::random_with(&mut rng);
+    let item = item::<12, 2>(&mut rng);
+    assert_close(gradient_check(&net, &item, Tanh, DistanceFunc, EPS), 2);
+}
+
+#[test]
+fn custom_gradient() {
+    let mut rng = rng();
+    let net = Custom::random_with(&mut rng);
+    assert_eq!(net.scale.s[0], 1.);
+    let item = item::<3, 3>(&mut rng);
+    assert_close(gradient_check(&net, &item, Tanh, DistanceFunc, EPS), 2);
+}
+
+#[test]
+fn same_as_counted_layers() {
+    let mut rng = rng();
+    let counted = Counted::<3, 4, 2>::random_with(&mut rng);
+    let dense = Dense { first: counted.layer_1.clone(), second: counted.layer_2.clone() };
+    let item = item::<3, 2>(&mut rng);
+
+    let out = Layers::<Tanh, _, 3, 2>::test(&counted, &item.data);
+    assert_eq!(Layers::<Tanh, _, 3, 2>::test(&dense, &item.data), out);
+
+    let d = DistanceFunc::d(&item.label, &out);
+    let g = Layers::<Tanh, _, 3, 2>::backward(&counted, &item.data, d, Layers::<Tanh, _, 3, 2>::forward(&counted, &item.data));
+    let h = Layers::<Tanh, _, 3, 2>::backward(&dense, &item.data, d, Layers::<Tanh, _, 3, 2>::forward(&dense, &item.data));
+    assert_eq!(g.params(), h.params());
+}
+
+#[test]
+fn predict_proba_is_softmax() {
+    let net = Cnn::random_with(&mut rng());
+    let x = SVector::from_fn(|i, _| i as f64 / 32.);
+    let p = SoftmaxLayers::<ReLU, _, 32, 3>::predict_proba(&net, &x);
+    assert!((p.sum() - 1.).abs() < 1e-12);
+    assert_eq!(p, softmax(&Layers::<ReLU, _, 32, 3>::test(&net, &x)));
+}
+
+#[test]
+fn shapes_and_cast() {
+    assert_eq!(Cnn::<f64>::shapes(), vec![(3, 18), (6, 12), (3, 6)]);
+
+    let net = Cnn::random_with(&mut rng());
+    let cast: Cnn<f32> = net.cast();
+    let x = SVector::from_fn(|i, _| i as f64 / 32.);
+    let out = Layers::<ReLU, _, 32, 3>::test(&net, &x);
+    let out_f32 = Layers::<ReLU, _, 32, 3, f32>::test(&cast, &x.map(|v| v as f32));
+    for (a, b) in out.iter().zip(out_f32.iter()) {
+        assert!((a - *b as f64).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn cnn_learns() {
+    let mut rng = rng();
+    // the class is the channel with the brighter center
+    let data = (0..60).map(|i| {
+        let class = i % 2;
+        let data = SVector::from_fn(|j, _| {
+            let (c, y, x) = (j / 16, j / 4 % 4, j % 4);
+            let center = (1..3).contains(&y) && (1..3).contains(&x);
+            rng.gen_range(0.0..0.3) + if c == class && center { 1. } else { 0. }
+        });
+        let mut label = SVector::zeros();
+        label[class] = 1.;
+        Item::new(data, label)
+    }).collect::<Vec<_>>();
+
+    let layers = Cnn::random_with(&mut rng);
+    let config = Config {
+        learn_rate: 0.01,
+        batch_size: 10,
+        iter_num: 30,
+        seed: Some(7),
+        ..Config::default_with_func(ReLU, CrossEntroy)
+    }.with_optimizer(Adam::default());
+    let (model, history) = Network::cfg(layers, config).train(&data[..]).build();
+    assert!(history.train_loss.last() < history.train_loss.first());
+
+    let correct = data.iter()
+        .filter(|item| model.test(&item.data).imax() == item.label.imax())
+        .count();
+    assert!(correct >= 55, "{} of 60 correct", correct);
+}
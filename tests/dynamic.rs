@@ -0,0 +1,235 @@
+extern crate simple_nn as nn;
+
+use std::cell::Cell;
+
+use nn::{Config, Item, conv::*, derive_layers, dynamic::*, func::*, metric::{Accuracy, Mse}, model::*, norm::LayerNorm, optim::Adam, persist, schedule::Constant};
+
+mod common;
+
+use common::{random, rng};
+
+#[derive_layers(2, activation(Tanh, Sigmoid), init(XavierNormal, XavierNormal))]
+struct Fixed{}
+
+#[derive_layers(2, activation(ReLU, _), softmax, init(HeNormal, XavierNormal))]
+struct Classifier{}
+
+#[derive_layers(input(12), output(2))]
+struct Signal {
+    #[activation(ReLU)]
+    conv: Conv1d<2, 2, 6, 3, 2, 1, 12, 6, T>,
+    pool: AvgPool1d<2, 3, 2, 1, 6, 4, T>,
+    flat: Flatten<4, T>,
+    #[activation(Sigmoid)]
+    out: Layer<2, 4, T>,
+}
+
+#[derive_layers(input(3), output(2))]
+struct Normed {
+    hidden: Layer<4, 3, T>,
+    #[activation(ReLU)]
+    norm: LayerNorm<4, T>,
+    #[activation(Sigmoid)]
+    out: Layer<2, 4, T>,
+}
+
+fn to_dyn<const S: usize>(v: &SVector<f64, S>) -> DVector<f64> {
+    DVector::from_column_slice(v.as_slice())
+}
+
+#[test]
+fn same_outputs_and_gradients_as_derived_layers() {
+    let mut rng = rng();
+    let layers = Fixed::<3, 5, 2>::random_with(&mut rng);
+    let net = DynNetwork::from_layers(&layers, &[Activation::Tanh, Activation::Sigmoid]).unwrap();
+    assert_eq!(net.sizes(), vec![3, 5, 2]);
+
+    let x = random::<3>(&mut rng);
+    let label = SVector::from([0.2, 0.9]);
+    let out = Layers::<Sigmoid, _, 3, 2>::test(&layers, &x);
+    let dyn_out = net.test(&to_dyn(&x));
+    assert!((to_dyn(&out) - &dyn_out).amax() < 1e-12);
+
+    let calc = Layers::<Sigmoid, _, 3, 2>::forward(&layers, &x);
+    let grad = Layers::<Sigmoid, _, 3, 2>::backward(&layers, &x, DistanceFunc::d(&label, &out), calc);
+    let dyn_calc = net.forward(&to_dyn(&x));
+    let dyn_grad = net.backward(&to_dyn(&x), DistanceFunc::d(&to_dyn(&label), &dyn_out), dyn_calc);
+    for ((w, b), g) in grad.params().into_iter().zip(&dyn_grad) {
+        assert!(w.iter().zip(g.w.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(b.iter().zip(g.b.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+}
+
+#[test]
+fn convert_back_to_derived_layers() {
+    let layers = Fixed::<3, 5, 2>::random_with(&mut rng());
+    let net = DynNetwork::from_layers(&layers, &[Activation::Tanh, Activation::Sigmoid]).unwrap();
+    let back: Fixed<3, 5, 2> = net.to_layers().unwrap();
+    assert_eq!(back.params(), layers.params());
+
+    match net.to_layers::<Fixed<3, 4, 2>>() {
+        Err(persist::Error::ShapeMismatch { layer: 1, expected: (4, 3), found: (5, 3) }) => {}
+        _ => panic!("expected a shape mismatch"),
+    }
+}
+
+#[test]
+fn softmax_output() {
+    let layers = Classifier::<3, 6, 4>::random_with(&mut rng());
+    let net = DynNetwork::from_layers(&layers, &[Activation::ReLU, Activation::Sigmoid]).unwrap().with_softmax();
+    assert_eq!(net.activations, vec![Activation::ReLU, Activation::Identity]);
+
+    let x = random::<3>(&mut rng());
+    let p = SoftmaxLayers::<Tanh, _, 3, 4>::predict_proba(&layers, &x);
+    let dyn_p = net.predict_proba(&to_dyn(&x)).unwrap();
+    assert!((to_dyn(&p) - dyn_p).amax() < 1e-12);
+
+    let logits = DynNetwork::from_layers(&layers, &[Activation::ReLU, Activation::Identity]).unwrap();
+    assert_eq!(logits.predict_proba(&to_dyn(&x)), None);
+}
+
+#[test]
+fn build_errors() {
+    let relu = Activation::ReLU;
+    assert_eq!(DynNetwork::<f64>::zeros(&[3], &[]).unwrap_err(), BuildError::NoLayers);
+    assert_eq!(
+        DynNetwork::<f64>::zeros(&[3, 4, 2], &[relu]).unwrap_err(),
+        BuildError::ActivationCount { expected: 2, found: 1 }
+    );
+    let err = DynNetwork::<f64>::random_with(&[3, 2], &[relu, relu], &mut rng()).unwrap_err();
+    assert_eq!(err.to_string(), "expected 1 activations, found 2");
+
+    let layers = Fixed::<3, 5, 2>::default();
+    assert_eq!(
+        DynNetwork::from_layers(&layers, &[relu]).unwrap_err(),
+        BuildError::ActivationCount { expected: 2, found: 1 }
+    );
+
+    // pooling and flatten have no params
+    let relu_sigmoid = [relu, Activation::Sigmoid];
+    let err = DynNetwork::from_layers(&Signal::<f64>::default(), &relu_sigmoid).unwrap_err();
+    assert_eq!(err, BuildError::NotDense { layer: 2 });
+    assert_eq!(err.to_string(), "layer 2 is not a dense layer");
+    assert_eq!(
+        DynNetwork::from_layers(&Normed::<f64>::default(), &[relu, relu, relu]).unwrap_err(),
+        BuildError::ShapeMismatch { layer: 2, expected: (4, 4), found: (4, 1) }
+    );
+}
+
+#[test]
+fn activation_names() {
+    for &a in Activation::ALL {
+        assert_eq!(a.name().parse::<Activation>(), Ok(a));
+    }
+    assert_eq!("relu".parse::<Activation>(), Ok(Activation::ReLU));
+    assert!("Softmax".parse::<Activation>().is_err());
+}
+
+#[test]
+fn save_and_load() {
+    let net = DynNetwork::<f64>::init_with(&[3, 7, 4, 2], &[Activation::GELU, Activation::Tanh, Activation::Identity], Init::XavierUniform, &mut rng())
+        .unwrap()
+        .with_softmax();
+
+    let mut buf = Vec::new();
+    net.write_binary(&mut buf).unwrap();
+    let loaded = DynNetwork::<f64>::read_binary(&buf[..]).unwrap();
+    assert_eq!(loaded.params(), net.params());
+    assert_eq!(loaded.activations, net.activations);
+    assert!(loaded.softmax);
+
+    let loaded = DynNetwork::<f32>::from_json(&net.to_json()).unwrap();
+    assert_eq!(loaded.cast::<f64>().shapes(), net.shapes());
+    assert_eq!(loaded.activations, net.activations);
+
+    // loads into derived layers of the same shapes, but not the other way
+    #[derive_layers(3)]
+    struct Three{}
+    let layers: Three<3, 7, 4, 2> = persist::read_binary(&buf[..]).unwrap().layers;
+    assert_eq!(layers.params(), net.params());
+
+    let mut buf = Vec::new();
+    persist::write_binary(&layers, None, &mut buf).unwrap();
+    assert!(matches!(DynNetwork::<f64>::read_binary(&buf[..]), Err(persist::Error::Malformed(_))));
+}
+
+#[test]
+fn trains_xor() {
+    let data = [(0., 0., 0.), (0., 1., 1.), (1., 0., 1.), (1., 1., 0.)]
+        .iter()
+        .map(|&(a, b, y)| DynItem::from(&Item::new(SVector::from([a, b]), SVector::from([y]))))
+        .collect::<Vec<_>>();
+
+    let net = DynNetwork::init_with(&[2, 4, 1], &[Activation::Tanh, Activation::Tanh], Init::XavierNormal, &mut StdRng::seed_from_u64(3)).unwrap();
+    let config = Config {
+        learn_rate: 0.05,
+        batch_size: 4,
+        iter_num: 2_000,
+        seed: Some(3),
+        ..Config::default_with_func(Tanh, DistanceFunc)
+    }.with_optimizer(Adam::default()).with_scheduler(Constant);
+
+    let (net, history) = net.train(&data, config).build();
+    assert_eq!(history.epochs, 2_000);
+    assert_eq!(net.accuracy(&data), 1.);
+    assert!(net.loss(&data, DistanceFunc) < 0.05);
+    assert_eq!((net.accuracy(&[]), net.loss(&[], DistanceFunc)), (0., 0.));
+}
+
+#[test]
+fn trains_like_derived_layers() {
+    let mut rng = rng();
+    let items = (0..20)
+        .map(|_| Item::new(random::<3>(&mut rng), SVector::from([rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)])))
+        .collect::<Vec<_>>();
+    let dyn_items = items.iter().map(DynItem::from).collect::<Vec<_>>();
+    let config = || Config {
+        learn_rate: 0.1,
+        batch_size: 5,
+        iter_num: 10,
+        seed: Some(1),
+        ..Config::default_with_func(Sigmoid, DistanceFunc)
+    }.with_scheduler(Constant);
+
+    let layers = Fixed::<3, 5, 2>::random_with(&mut rng);
+    let net = DynNetwork::from_layers(&layers, &[Activation::Tanh, Activation::Sigmoid]).unwrap();
+
+    let mut trainer = nn::Network::cfg(layers, config()).train(&items[5..]);
+    trainer.validate_with(&items[..5]);
+    trainer.metric(Accuracy);
+    trainer.metric(Mse);
+    let (model, history) = trainer.build();
+    let mut dyn_trainer = net.train(&dyn_items[5..], config());
+    dyn_trainer.validate_with(&dyn_items[..5]);
+    dyn_trainer.metric(Accuracy);
+    dyn_trainer.metric(Mse);
+    let (item_count, chunk_count) = (Cell::new(0), Cell::new(0));
+    dyn_trainer.after_each_item(|_, _, item, out| {
+        assert_eq!(item.label.len(), out.len());
+        item_count.set(item_count.get() + 1);
+    });
+    dyn_trainer.after_each_chunk(|_, _, chunk, outputs| {
+        assert_eq!((chunk.len(), outputs.len()), (5, 5));
+        chunk_count.set(chunk_count.get() + 1);
+    });
+    let (net, dyn_history) = dyn_trainer.build();
+    assert_eq!((item_count.get(), chunk_count.get()), (150, 30));
+    for ((a, b), (c, d)) in model.layers.params().into_iter().zip(net.params()) {
+        assert!(a.iter().zip(c).chain(b.iter().zip(d)).all(|(x, y)| (x - y).abs() < 1e-12));
+    }
+    let losses = history.train_loss.iter().chain(&history.val_loss);
+    let dyn_losses = dyn_history.train_loss.iter().chain(&dyn_history.val_loss);
+    assert_eq!(dyn_history.val_loss.len(), 10);
+    for (a, b) in losses.zip(dyn_losses) {
+        assert!((a - b).abs() < 1e-12);
+    }
+    assert_eq!(history.best_epoch, dyn_history.best_epoch);
+    for name in ["accuracy", "mse"] {
+        let metrics = history.metric(name).unwrap().iter().chain(history.val_metric(name).unwrap());
+        let dyn_metrics = dyn_history.metric(name).unwrap().iter().chain(dyn_history.val_metric(name).unwrap());
+        assert_eq!(dyn_history.val_metric(name).unwrap().len(), 10);
+        for (a, b) in metrics.zip(dyn_metrics) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+}
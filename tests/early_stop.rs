@@ -0,0 +1,67 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Item, Network, derive_layers, func::*, model::*, schedule::Constant};
+
+#[derive_layers(2, init(XavierNormal, XavierNormal))]
+struct Net{}
+
+#[test]
+fn stops_after_patience() {
+    let x = SVector::from([0.5, -0.5]);
+    // validation disagrees with training, so its loss rises from the first iter on
+    let train = [Item::new(x, SVector::from([1.]))];
+    let validation = [Item::new(x, SVector::from([0.]))];
+
+    let layers = Net::<2, 3, 1>::random_with(&mut StdRng::seed_from_u64(1));
+    let config = Config {
+        learn_rate: 0.5,
+        iter_num: 100,
+        seed: Some(1),
+        ..Config::default_with_func(Sigmoid, DistanceFunc)
+    }.with_scheduler(Constant);
+
+    let mut trainer = Network::cfg(layers, config).train(&train[..]);
+    trainer.validate_with(&validation);
+    trainer.early_stop(3);
+    let (model, history) = trainer.build();
+
+    assert!(history.val_loss.windows(2).all(|w| w[0] < w[1]), "{:?}", history.val_loss);
+    assert!(history.stopped_early);
+    assert_eq!(history.epochs, 4);
+    assert_eq!(history.val_loss.len(), 4);
+    assert_eq!(history.best_epoch, Some(1));
+    assert_eq!(history.best_val_loss, Some(history.val_loss[0]));
+
+    // the weights of the best iter are returned
+    let eval = model.evaluate(&validation, DistanceFunc, &[]);
+    assert_eq!(Some(eval.loss), history.best_val_loss);
+}
+
+#[test]
+fn runs_all_iters_without_validation() {
+    let train = [Item::new(SVector::from([0.5, -0.5]), SVector::from([1.]))];
+    let layers = Net::<2, 3, 1>::random_with(&mut StdRng::seed_from_u64(1));
+    let config = Config { iter_num: 10, ..Config::default_with_func(Sigmoid, DistanceFunc) };
+
+    let mut trainer = Network::cfg(layers, config).train(&train[..]);
+    trainer.early_stop(1);
+    let (_, history) = trainer.build();
+    assert!(!history.stopped_early);
+    assert_eq!(history.epochs, 10);
+    assert_eq!(history.best_epoch, None);
+    assert_eq!(history.best_val_loss, None);
+}
+
+#[test]
+fn nothing_is_recorded_without_data() {
+    let validation = [Item::new(SVector::from([0.5, -0.5]), SVector::from([1.]))];
+    let layers = Net::<2, 3, 1>::random_with(&mut StdRng::seed_from_u64(1));
+    let config = Config { iter_num: 10, ..Config::default_with_func(Sigmoid, DistanceFunc) };
+
+    let mut trainer = Network::cfg(layers.clone(), config).train(&[]);
+    trainer.validate_with(&validation);
+    let (model, history) = trainer.build();
+    assert!(history.train_loss.is_empty() && history.val_loss.is_empty());
+    assert_eq!((history.epochs, history.best_epoch), (0, None));
+    assert_eq!(model.layers.layer_1.w, layers.layer_1.w);
+}
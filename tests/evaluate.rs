@@ -0,0 +1,67 @@
+extern crate simple_nn as nn;
+
+use nn::{Item, derive_layers, func::*, metric::*, model::*};
+
+#[derive_layers(2, activation(Tanh, _), softmax, init(XavierNormal, XavierNormal))]
+struct Classifier{}
+
+type Net = Model<Classifier<3, 5, 2>, Tanh, ClassifierCal<3, 5, 2>, 3, 2>;
+
+fn model() -> Net {
+    Model::new(Classifier::random_with(&mut StdRng::seed_from_u64(4)))
+}
+
+fn items() -> Vec<Item<3, 2>> {
+    let mut rng = StdRng::seed_from_u64(4);
+    (0..20).map(|i| {
+        let data = SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0));
+        let mut label = SVector::zeros();
+        label[i % 2] = 1.;
+        Item::new(data, label)
+    }).collect()
+}
+
+#[test]
+fn predict_batch_in_order() {
+    let model = model();
+    let items = items();
+    let outputs = model.predict_batch(items.iter().map(|item| &item.data));
+    assert_eq!(outputs.len(), items.len());
+    for (item, out) in items.iter().zip(&outputs) {
+        assert_eq!(*out, model.test(&item.data));
+    }
+    assert!(model.predict_batch(&[]).is_empty());
+}
+
+#[test]
+fn evaluate_loss_and_metrics() {
+    let model = model();
+    let items = items();
+    let eval = model.evaluate(&items, CrossEntroy, &[&Accuracy, &F1]);
+
+    let labels = items.iter().map(|item| item.label).collect::<Vec<_>>();
+    let outputs = items.iter().map(|item| model.test(&item.data)).collect::<Vec<_>>();
+    let loss = labels.iter().zip(&outputs).map(|(l, o)| CrossEntroy::f(l, o)).sum::<f64>() / 20.;
+    assert!((eval.loss - loss).abs() < 1e-12);
+    assert_eq!(eval.metrics, vec![
+        ("accuracy".to_owned(), Accuracy.compute(&labels, &outputs)),
+        ("f1".to_owned(), F1.compute(&labels, &outputs)),
+    ]);
+
+    // item weights are ignored
+    let weighted = items.into_iter().map(|item| item.with_weight(3.)).collect::<Vec<_>>();
+    assert_eq!(model.evaluate(&weighted, CrossEntroy, &[&Accuracy, &F1]), eval);
+
+    let eval = model.evaluate(&[], CrossEntroy, &[&Mse]);
+    assert_eq!((eval.loss, eval.metric("mse")), (0., Some(0.)));
+}
+
+#[test]
+fn predict_proba_is_softmax_of_logits() {
+    let model = model();
+    let x = SVector::from([0.5, -0.2, 0.9]);
+    let p = model.predict_proba(&x);
+    assert_eq!(p, softmax(&model.test(&x)));
+    assert!((p.sum() - 1.).abs() < 1e-12);
+    assert!(p.iter().all(|&p| p > 0.));
+}
@@ -0,0 +1,82 @@
+extern crate simple_nn as nn;
+
+use nn::{Item, check::gradient_check, derive_layers, func::*, model::*};
+
+mod common;
+
+use common::{EPS, assert_close, item, rng};
+
+#[derive_layers(1)]
+struct Single{}
+
+#[derive_layers(2, init(XavierNormal, XavierNormal))]
+struct Double{}
+
+#[derive_layers(3, activation(Tanh, Sigmoid, Identity), init(XavierNormal, XavierNormal, XavierNormal))]
+struct Mixed{}
+
+#[derive_layers(3, activation(GELU, Mish, Swish), init(HeNormal, HeNormal, XavierNormal))]
+struct FromInput{}
+
+#[derive_layers(3, activation(ELU, Softplus, _), softmax, init(HeNormal, HeNormal, XavierNormal))]
+struct Classifier{}
+
+#[derive_layers(2, dropout(0.5, _), init(XavierNormal, XavierNormal))]
+struct Dropout{}
+
+fn one_hot<const I: usize, const O: usize>(rng: &mut StdRng, class: usize) -> Item<I, O> {
+    let mut label = SVector::zeros();
+    label[class] = 1.;
+    Item::new(SVector::from_fn(|_, _| rng.gen_range(-1.0..1.0)), label)
+}
+
+#[test]
+fn single_layer() {
+    let mut rng = rng();
+    let layers = Single::<3, 2>::random_with(&mut rng);
+    let errors = gradient_check(&layers, &item(&mut rng), Sigmoid, DistanceFunc, EPS);
+    assert_close(errors, 1);
+}
+
+#[test]
+fn two_layers() {
+    let mut rng = rng();
+    let layers = Double::<4, 5, 3>::random_with(&mut rng);
+    let errors = gradient_check(&layers, &item(&mut rng), Tanh, DistanceFunc, EPS);
+    assert_close(errors, 2);
+}
+
+#[test]
+fn per_layer_activations() {
+    let mut rng = rng();
+    let layers = Mixed::<3, 6, 4, 2>::random_with(&mut rng);
+    let errors = gradient_check(&layers, &item(&mut rng), Sigmoid, Huber, EPS);
+    assert_close(errors, 3);
+}
+
+#[test]
+fn activations_from_input() {
+    let mut rng = rng();
+    let layers = FromInput::<3, 5, 4, 2>::random_with(&mut rng);
+    let errors = gradient_check(&layers, &item(&mut rng), Sigmoid, LogCosh, EPS);
+    assert_close(errors, 3);
+}
+
+#[test]
+fn softmax_cross_entropy() {
+    let mut rng = rng();
+    let layers = Classifier::<4, 6, 5, 3>::random_with(&mut rng);
+    for class in 0..3 {
+        let errors = gradient_check(&layers, &one_hot(&mut rng, class), Sigmoid, CrossEntroy, EPS);
+        assert_close(errors, 3);
+    }
+}
+
+/// `backprop_batch_into` applies dropout, the other passes don't
+#[test]
+fn dropout_layers() {
+    let mut rng = rng();
+    let layers = Dropout::<3, 8, 2>::random_with(&mut rng);
+    let errors = gradient_check(&layers, &item(&mut rng), Tanh, BceWithLogits, EPS);
+    assert_close(errors, 2);
+}
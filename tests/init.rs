@@ -0,0 +1,85 @@
+extern crate simple_nn as nn;
+
+use nn::{derive_layers, dynamic::DMatrix, func::*, model::*};
+
+#[derive_layers(3, init(HeNormal, Orthogonal, _))]
+struct Net{}
+
+/// mean and std of the weights of `init` for a layer of size 64 and pre layer size 256
+fn moments(init: Init) -> (f64, f64) {
+    let w = init.weights::<_, 64, 256>(&mut StdRng::seed_from_u64(1));
+    let mean = w.mean();
+    (mean, (w.map(|w| (w - mean).powi(2)).sum() / w.len() as f64).sqrt())
+}
+
+#[test]
+fn uniform_schemes_are_bounded() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for (init, limit) in [
+        (Init::Uniform, 1.),
+        (Init::XavierUniform, (6f64 / (256. + 64.)).sqrt()),
+        (Init::HeUniform, (6f64 / 256.).sqrt()),
+        (Init::LeCunUniform, (3f64 / 256.).sqrt()),
+    ] {
+        let w = init.weights::<_, 64, 256>(&mut rng);
+        assert!(w.iter().all(|w| w.abs() < limit), "{:?}", init);
+        // symmetric schemes spread over both signs
+        if init != Init::Uniform {
+            assert!(w.min() < -0.9 * limit && w.max() > 0.9 * limit, "{:?}", init);
+        }
+    }
+    assert!(Init::Uniform.weights::<_, 64, 256>(&mut rng).min() >= 0.);
+}
+
+#[test]
+fn normal_schemes_have_their_std() {
+    for (init, std) in [
+        (Init::XavierNormal, (2f64 / (256. + 64.)).sqrt()),
+        (Init::HeNormal, (2f64 / 256.).sqrt()),
+        (Init::LeCunNormal, (1f64 / 256.).sqrt()),
+    ] {
+        let (mean, actual) = moments(init);
+        assert!(mean.abs() < 0.1 * std, "{:?}: mean {}", init, mean);
+        assert!((actual / std - 1.).abs() < 0.05, "{:?}: std {} != {}", init, actual, std);
+    }
+}
+
+#[test]
+fn orthogonal_and_zeros() {
+    let mut rng = StdRng::seed_from_u64(1);
+    // rows are orthonormal when the layer is narrower than its input
+    let q = Init::Orthogonal.weights::<_, 2, 3>(&mut rng);
+    assert!((q * q.transpose() - SMatrix::<f64, 2, 2>::identity()).amax() < 1e-12);
+    // columns are when it is wider
+    let q = Init::Orthogonal.weights::<_, 5, 3>(&mut rng);
+    assert!((q.transpose() * q - SMatrix::<f64, 3, 3>::identity()).amax() < 1e-12);
+
+    assert_eq!(Init::Zeros.weights::<_, 2, 3>(&mut rng), SMatrix::<f64, 2, 3>::zeros());
+    assert_eq!(Init::Zeros.weights_dyn(2, 3, &mut rng), DMatrix::zeros(2, 3));
+}
+
+#[test]
+fn biases_are_zero() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let l = Layer::<4, 3>::init_with(Init::XavierUniform, &mut rng);
+    assert_eq!(l.b, SVector::<f64, 4>::zeros());
+    assert!(l.w.iter().all(|&w| w != 0.));
+}
+
+#[test]
+fn per_layer_schemes_of_derived_layers() {
+    let net = Net::<256, 64, 32, 2>::random_with(&mut StdRng::seed_from_u64(1));
+    assert_eq!(net.layer_1.b, SVector::<f64, 64>::zeros());
+    assert!(net.layer_1.w.min() < 0.);
+    let q = net.layer_2.w;
+    assert!((q * q.transpose() - SMatrix::<f64, 32, 32>::identity()).amax() < 1e-12);
+    // `_` keeps the uniform default
+    assert!(net.layer_3.w.iter().chain(net.layer_3.b.iter()).all(|&w| (0. ..1.).contains(&w)));
+}
+
+#[test]
+fn same_weights_as_dyn_sizes() {
+    let w = Init::HeNormal.weights::<_, 3, 4>(&mut StdRng::seed_from_u64(2));
+    let dyn_w = Init::HeNormal.weights_dyn(3, 4, &mut StdRng::seed_from_u64(2));
+    assert_eq!(w.as_slice(), dyn_w.as_slice());
+}
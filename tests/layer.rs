@@ -0,0 +1,124 @@
+extern crate simple_nn as nn;
+
+use nn::model::*;
+
+fn layer() -> Layer<2, 3> {
+    Layer {
+        w: SMatrix::from([[1., 4.], [2., 5.], [3., 6.]]),
+        b: SVector::from([0.5, -0.5]),
+    }
+}
+
+#[test]
+fn new_and_default_are_zero() {
+    let zero = Layer::<2, 3>::new();
+    assert!(zero.w.iter().chain(zero.b.iter()).all(|&v| v == 0.));
+    let default = Layer::<2, 3>::default();
+    assert_eq!(default.w, zero.w);
+    assert_eq!(default.b, zero.b);
+}
+
+#[test]
+fn calc() {
+    let out = layer().calc(&SVector::from([1., 0., -1.]));
+    assert_eq!(out, SVector::from([1. - 3. + 0.5, 4. - 6. - 0.5]));
+}
+
+#[test]
+fn batch_products_match_columns() {
+    // large enough for the blocked matrix product
+    let mut rng = StdRng::seed_from_u64(2);
+    let l = Layer::<7, 9>::random_with(&mut rng);
+    let mut buffers = Buffers::new();
+    let mut inputs = buffers.batch::<9>(10);
+    let mut delta = buffers.batch::<7>(10);
+    inputs.iter_mut().chain(delta.iter_mut()).for_each(|v| *v = rng.gen_range(-1.0..1.0));
+
+    let mut z = buffers.batch::<7>(10);
+    l.calc_batch_to(&inputs, &mut z);
+    let mut k = buffers.batch::<9>(10);
+    l.back_batch_to(&delta, &mut k);
+    let mut grad = Layer::<7, 9>::new();
+    grad.accumulate_batch(&delta, &inputs);
+
+    let mut expected = Layer::<7, 9>::new();
+    for j in 0..10 {
+        let (x, d) = (inputs.column(j).into_owned(), delta.column(j).into_owned());
+        assert!((z.column(j) - l.calc(&x)).amax() < 1e-12);
+        assert!((k.column(j) - l.w.transpose() * d).amax() < 1e-12);
+        expected.w += d * x.transpose();
+        expected.b += d;
+    }
+    assert!((grad.w - expected.w).amax() < 1e-12);
+    assert!((grad.b - expected.b).amax() < 1e-12);
+}
+
+#[test]
+fn add_and_sub_assign() {
+    let mut l = layer();
+    l += &layer();
+    assert_eq!(l.w, layer().w * 2.);
+    assert_eq!(l.b, layer().b * 2.);
+
+    l -= &layer();
+    assert_eq!(l.w, layer().w);
+    assert_eq!(l.b, layer().b);
+
+    let origin = layer();
+    l -= (&origin.w, &origin.b);
+    assert!(l.w.iter().chain(l.b.iter()).all(|&v| v == 0.));
+}
+
+#[test]
+fn mul_and_div_assign() {
+    let mut l = layer();
+    l *= 4.;
+    assert_eq!(l.w, layer().w * 4.);
+    assert_eq!(l.b, layer().b * 4.);
+
+    l /= 2.;
+    assert_eq!(l.w, layer().w * 2.);
+    assert_eq!(l.b, layer().b * 2.);
+}
+
+#[test]
+fn params_are_column_major() {
+    let l = layer();
+    assert_eq!(Layer::<2, 3>::shapes(), vec![(2, 3)]);
+    let params = l.params();
+    assert_eq!(params[0].0, &[1., 4., 2., 5., 3., 6.]);
+    assert_eq!(params[0].1, &[0.5, -0.5]);
+
+    let mut l = l;
+    l.params_mut()[0].1[1] = 7.;
+    assert_eq!(l.b[1], 7.);
+}
+
+#[test]
+fn cast_to_f32_and_back() {
+    let l: Layer<2, 3, f32> = layer().cast();
+    assert_eq!(l.w[(1, 2)], 6f32);
+    let back: Layer<2, 3> = l.cast();
+    assert_eq!(back.w, layer().w);
+}
+
+#[test]
+fn penalize_adds_penalty_gradient() {
+    let mut grad = Layer::<2, 3>::new();
+    grad.penalize(&layer(), Penalty::l2(0.5), false);
+    assert_eq!(grad.w, layer().w * 0.5);
+    assert_eq!(grad.b, SVector::<f64, 2>::zeros());
+
+    let mut grad = Layer::<2, 3>::new();
+    grad.penalize(&layer(), Penalty::l1(0.1), true);
+    assert!(grad.w.iter().all(|&g| g == 0.1));
+    assert_eq!(grad.b, SVector::from([0.1, -0.1]));
+}
+
+#[test]
+fn dropout_mask_keeps_expectation() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mask: SVector<f64, 1000> = dropout_mask(0.25, &mut rng);
+    assert!(mask.iter().all(|&m| m == 0. || (m - 1. / 0.75).abs() < 1e-12));
+    assert!((mask.mean() - 1.).abs() < 0.1);
+}
@@ -0,0 +1,134 @@
+extern crate simple_nn as nn;
+
+use nn::{func::*, model::SVector};
+
+type V = SVector<f64, 3>;
+
+/// compare `C::d` with the central difference of `C::f` at `y`
+fn check<C: LossFunc>(label: [f64; 3], y: [f64; 3]) {
+    const H: f64 = 1e-6;
+    let label = V::from(label);
+    let y = V::from(y);
+    let d = C::d(&label, &y);
+
+    for i in 0..3 {
+        let (mut plus, mut minus) = (y, y);
+        plus[i] += H;
+        minus[i] -= H;
+        let numeric = (C::f(&label, &plus) - C::f(&label, &minus)) / (2. * H);
+        assert!(
+            (numeric - d[i]).abs() <= 1e-5 * numeric.abs().max(1.),
+            "{}: d[{}] = {}, numeric {}", std::any::type_name::<C>(), i, d[i], numeric
+        );
+    }
+}
+
+#[test]
+fn distance() {
+    check::<DistanceFunc>([0.5, -1., 2.], [0.3, 0.7, -1.2]);
+}
+
+#[test]
+fn cross_entropy() {
+    check::<CrossEntroy>([0., 1., 0.], [0.3, 0.7, -1.2]);
+}
+
+#[test]
+fn mean_abs_error() {
+    check::<MeanAbsError>([0.5, -1., 2.], [0.3, 0.7, -1.2]);
+}
+
+#[test]
+fn huber() {
+    // both the quadratic and the linear part
+    check::<Huber>([0.5, -1., 2.], [0.3, 0.7, -1.2]);
+}
+
+#[test]
+fn bce_with_logits() {
+    check::<BceWithLogits>([0., 1., 1.], [0.3, -2.5, 4.]);
+}
+
+#[test]
+fn bce_with_logits_large_logits() {
+    let loss = BceWithLogits::f(&V::from([0., 1., 0.]), &V::from([-800., 800., 800.]));
+    assert!((loss - 800.).abs() < 1e-9);
+}
+
+#[test]
+fn categorical_cross_entropy() {
+    check::<CategoricalCrossEntropy>([0.2, 0.5, 0.3], [0.1, 0.6, 0.3]);
+}
+
+#[test]
+fn hinge() {
+    check::<Hinge>([1., -1., 1.], [0.3, 0.7, 1.5]);
+}
+
+#[test]
+fn squared_hinge() {
+    check::<SquaredHinge>([1., -1., 1.], [0.3, 0.7, 1.5]);
+}
+
+#[test]
+fn kl_divergence() {
+    check::<KlDivergence>([0.2, 0.5, 0.3], [0.1, 0.6, 0.3]);
+    check::<KlDivergence>([0., 1., 0.], [0.1, 0.6, 0.3]);
+}
+
+#[test]
+fn log_cosh() {
+    check::<LogCosh>([0.5, -1., 2.], [0.3, 0.7, -1.2]);
+    assert!(LogCosh::f(&V::zeros(), &V::repeat(1000.)).is_finite());
+}
+
+#[test]
+fn focal() {
+    check::<Focal>([0., 1., 1.], [0.3, 0.6, 0.9]);
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-12 * a.abs().max(b.abs()).max(1.)
+}
+
+#[test]
+fn loss_values() {
+    let label = V::from([1., 0., 0.]);
+    let y = V::from([0.5, 0.5, -1.]);
+
+    assert!(close(DistanceFunc::f(&label, &y), (0.25 + 0.25 + 1.) / 2.));
+    assert!(close(MeanAbsError::f(&label, &y), 0.5 + 0.5 + 1.));
+    assert!(close(Huber::f(&label, &y), 0.125 + 0.125 + 0.5));
+    assert!(close(LogCosh::f(&label, &y), 2. * 0.5f64.cosh().ln() + 1f64.cosh().ln()));
+    assert!(close(Hinge::f(&V::from([1., -1., 1.]), &y), 0.5 + 1.5 + 2.));
+    assert!(close(SquaredHinge::f(&V::from([1., -1., 1.]), &y), 0.25 + 2.25 + 4.));
+}
+
+#[test]
+fn probability_loss_values() {
+    let label = V::from([0., 1., 0.]);
+    let p = V::from([0.2, 0.5, 0.3]);
+
+    assert!(close(CategoricalCrossEntropy::f(&label, &p), -(0.5f64.ln())));
+    assert!(close(KlDivergence::f(&label, &p), -(0.5f64.ln())));
+    assert!(close(KlDivergence::f(&p, &p), 0.));
+    // cross entropy of logits equals the one of their softmax
+    let logits = V::from([0.1, 1.3, -0.4]);
+    assert!(close(CrossEntroy::f(&label, &logits), CategoricalCrossEntropy::f(&label, &softmax(&logits))));
+    // fused gradient
+    assert_eq!(CrossEntroy::d(&label, &logits), softmax(&logits) - label);
+}
+
+#[test]
+fn binary_loss_values() {
+    let label = V::from([1., 0., 1.]);
+    let logits = V::from([0., 2., -1.]);
+    let p = logits.map(Sigmoid::f);
+    let bce = -(label.zip_map(&p, |l, p| l * p.ln() + (1. - l) * (1. - p).ln())).sum();
+    assert!(close(BceWithLogits::f(&label, &logits), bce));
+
+    // focal loss is below bce, and vanishes on certain items
+    let focal = Focal::f(&label, &p);
+    assert!(focal > 0. && focal < bce);
+    assert!(Focal::f(&V::from([1., 0., 1.]), &V::from([1., 0., 1.])) < 1e-9);
+}
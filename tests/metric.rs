@@ -0,0 +1,101 @@
+extern crate simple_nn as nn;
+
+use nn::{metric::*, model::SVector};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-12
+}
+
+/// label classes `0, 0, 1, 1, 2, 2`, output classes `0, 1, 1, 1, 0, 2`
+fn classified() -> (Vec<SVector<f64, 3>>, Vec<SVector<f64, 3>>) {
+    let one_hot = |c: usize| SVector::from_fn(|i, _| (i == c) as u8 as f64);
+    let labels = [0, 0, 1, 1, 2, 2].iter().map(|&c| one_hot(c)).collect();
+    let outputs = [
+        [0.7, 0.2, 0.1],
+        // the label class is second
+        [0.3, 0.5, 0.2],
+        [0.1, 0.8, 0.1],
+        [0.2, 0.6, 0.2],
+        // the label class is third
+        [0.5, 0.45, 0.05],
+        [0.1, 0.1, 0.8],
+    ].iter().map(|&o| SVector::from(o)).collect();
+    (labels, outputs)
+}
+
+#[test]
+fn confusion_matrix() {
+    let (labels, outputs) = classified();
+    let m = ConfusionMatrix::new(&labels, &outputs);
+    assert_eq!(m.counts, vec![vec![1, 1, 0], vec![0, 2, 0], vec![1, 0, 1]]);
+    assert!(close(m.accuracy(), 4. / 6.));
+    assert_eq!([m.precision(0), m.precision(1), m.precision(2)], [0.5, 2. / 3., 1.]);
+    assert_eq!([m.recall(0), m.recall(1), m.recall(2)], [0.5, 1., 0.5]);
+    assert!(close(m.f1(0), 0.5));
+    assert!(close(m.f1(1), 0.8));
+    assert!(close(m.f1(2), 2. / 3.));
+}
+
+#[test]
+fn classification_metrics() {
+    let (labels, outputs) = classified();
+    assert!(close(Accuracy.compute(&labels, &outputs), 4. / 6.));
+    assert!(close(Precision.compute(&labels, &outputs), (0.5 + 2. / 3. + 1.) / 3.));
+    assert!(close(Recall.compute(&labels, &outputs), 2. / 3.));
+    assert!(close(F1.compute(&labels, &outputs), (0.5 + 0.8 + 2. / 3.) / 3.));
+}
+
+#[test]
+fn top_k() {
+    let (labels, outputs) = classified();
+    assert!(close(TopK(1).compute(&labels, &outputs), 4. / 6.));
+    assert!(close(TopK(2).compute(&labels, &outputs), 5. / 6.));
+    assert_eq!(TopK(3).compute(&labels, &outputs), 1.);
+    assert_eq!(Metric::<3>::name(&TopK(2)), "top_2_accuracy");
+}
+
+#[test]
+fn binary_classes() {
+    let labels = [0., 1., 1.].map(|l| SVector::from([l]));
+    let outputs = [0.2, 0.7, 0.4].map(|o| SVector::from([o]));
+    let m = ConfusionMatrix::new(&labels, &outputs);
+    assert_eq!(m.counts, vec![vec![1, 0], vec![1, 1]]);
+    assert!(close(Accuracy.compute(&labels, &outputs), 2. / 3.));
+    assert!(close(TopK(1).compute(&labels, &outputs), 2. / 3.));
+    assert_eq!(TopK(2).compute(&labels, &outputs), 1.);
+}
+
+#[test]
+fn regression_metrics() {
+    let labels = [[1., 2.], [3., 4.], [5., 6.]].map(SVector::from);
+    // errors 0, 0.5, -1, 0, 0, 1
+    let outputs = [[1., 2.5], [2., 4.], [5., 7.]].map(SVector::from);
+    assert!(close(Mse.compute(&labels, &outputs), 2.25 / 6.));
+    assert!(close(Mae.compute(&labels, &outputs), 2.5 / 6.));
+    // mean label [3, 4], SS_tot = 16
+    assert!(close(R2.compute(&labels, &outputs), 1. - 2.25 / 16.));
+    assert_eq!(R2.compute(&labels, &labels), 1.);
+}
+
+#[test]
+fn r2_of_constant_labels() {
+    let labels = [[2.], [2.]].map(SVector::from);
+    assert_eq!(R2.compute(&labels, &labels), 1.);
+    assert_eq!(R2.compute(&labels, &[[2.], [3.]].map(SVector::from)), 0.);
+}
+
+#[test]
+fn no_items() {
+    let empty: [SVector<f64, 2>; 0] = [];
+    assert_eq!(Mse.compute(&empty, &empty), 0.);
+    assert_eq!(Mae.compute(&empty, &empty), 0.);
+    let metrics: [&dyn Metric<2>; 6] = [&Accuracy, &TopK(1), &Precision, &Recall, &F1, &R2];
+    assert!(metrics.iter().all(|m| m.compute(&empty, &empty).is_finite()));
+}
+
+#[test]
+fn evaluation_lookup() {
+    let eval = Evaluation { loss: 0.5, metrics: vec![("mse".to_owned(), 0.25)] };
+    assert_eq!(eval.metric("mse"), Some(0.25));
+    assert_eq!(eval.metric("mae"), None);
+}
@@ -0,0 +1,59 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Network, check::gradient_check, derive_layers, func::*, model::*, norm::LayerNorm, optim::Adam};
+
+mod common;
+
+use common::{EPS, assert_close, check_module, item, random, rng};
+
+#[derive_layers(input(4), output(2))]
+struct Normed {
+    #[init(XavierNormal)]
+    hidden: Layer<6, 4, T>,
+    #[activation(Tanh)]
+    norm: LayerNorm<6, T>,
+    #[activation(Sigmoid)]
+    #[init(XavierNormal)]
+    out: Layer<2, 6, T>,
+}
+
+#[test]
+fn zero_mean_unit_variance() {
+    let norm = LayerNorm::<5>::init_with(Init::XavierNormal, &mut rng());
+    assert_eq!(norm.gamma, SVector::<f64, 5>::repeat(1.));
+    assert_eq!(norm.beta, SVector::<f64, 5>::zeros());
+
+    let x = SVector::from([1., 2., 3., 4., 10.]);
+    let out = norm.test(&x);
+    assert!(out.mean().abs() < 1e-12);
+    assert!((out.norm_squared() / 5. - 1.).abs() < 1e-5);
+    // shifting and scaling the input does not change the output
+    assert!((norm.test(&(x * 3.).add_scalar(-7.)) - out).amax() < 1e-5);
+
+    let scaled = LayerNorm { gamma: SVector::repeat(2.), beta: SVector::repeat(0.5) };
+    assert!((scaled.test(&x) - (out * 2.).add_scalar(0.5)).amax() < 1e-12);
+}
+
+#[test]
+fn gradient() {
+    let mut rng = rng();
+    let norm = LayerNorm::<5> { gamma: random(&mut rng), beta: random(&mut rng) };
+    check_module(&norm, &mut rng);
+}
+
+#[test]
+fn in_declared_network() {
+    let mut rng = rng();
+    let net = Normed::random_with(&mut rng);
+    assert_eq!(Normed::<f64>::shapes(), vec![(6, 4), (6, 1), (2, 6)]);
+    assert_close(gradient_check(&net, &item::<4, 2>(&mut rng), Tanh, DistanceFunc, EPS), 3);
+
+    let data = (0..20).map(|_| item::<4, 2>(&mut rng)).collect::<Vec<_>>();
+    let config = Config {
+        iter_num: 50,
+        seed: Some(7),
+        ..Config::default_with_func(Tanh, DistanceFunc)
+    }.with_optimizer(Adam::default());
+    let (_, history) = Network::cfg(net, config).train(&data).build();
+    assert!(history.train_loss[49] < history.train_loss[0], "{:?}", history.train_loss);
+}
@@ -0,0 +1,92 @@
+extern crate simple_nn as nn;
+
+use nn::optim::*;
+
+const RATE: f64 = 0.1;
+
+/// parameter after each step of `optimizer` from `p = 1` with `grads`, and the final moments
+fn steps<Opt: Optimizer>(optimizer: &Opt, grads: &[f64]) -> (Vec<f64>, f64, f64) {
+    let (mut p, mut m, mut v) = (1., 0., 0.);
+    let ps = grads.iter().enumerate().map(|(i, &g)| {
+        optimizer.step(i + 1, RATE, &mut p, g, &mut m, &mut v);
+        p
+    }).collect();
+    (ps, m, v)
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() < 1e-12, "step {}: {} != {}", i + 1, a, e);
+    }
+}
+
+#[test]
+fn sgd() {
+    let (ps, _, _) = steps(&Sgd, &[1., 2., -1.]);
+    assert_close(&ps, &[0.9, 0.7, 0.8]);
+}
+
+#[test]
+fn momentum() {
+    // m: 1, 0.5 + 2 = 2.5, 1.25 - 1 = 0.25
+    let (ps, m, _) = steps(&Momentum { momentum: 0.5 }, &[1., 2., -1.]);
+    assert_close(&ps, &[0.9, 0.65, 0.625]);
+    assert_close(&[m], &[0.25]);
+}
+
+#[test]
+fn nesterov() {
+    // p -= rate * (g + momentum * m) with the updated m
+    let (ps, m, _) = steps(&Nesterov { momentum: 0.5 }, &[1., 2., -1.]);
+    assert_close(&ps, &[0.85, 0.525, 0.6125]);
+    assert_close(&[m], &[0.25]);
+}
+
+#[test]
+fn rms_prop() {
+    // v: 0.5, 0.25 + 2 = 2.25
+    let (ps, _, v) = steps(&RmsProp { decay: 0.5, eps: 0. }, &[1., 2.]);
+    let p1 = 1. - RATE / 0.5f64.sqrt();
+    assert_close(&ps, &[p1, p1 - RATE * 2. / 1.5]);
+    assert_close(&[v], &[2.25]);
+}
+
+#[test]
+fn adagrad() {
+    // v: 1, 5, 6
+    let (ps, _, v) = steps(&Adagrad { eps: 0. }, &[1., 2., -1.]);
+    let p2 = 0.9 - RATE * 2. / 5f64.sqrt();
+    assert_close(&ps, &[0.9, p2, p2 + RATE / 6f64.sqrt()]);
+    assert_close(&[v], &[6.]);
+}
+
+#[test]
+fn adam_bias_correction() {
+    let adam = Adam { beta1: 0.5, beta2: 0.75, eps: 0. };
+    // m: 0.5, 1.25; v: 0.25, 0.1875 + 1 = 1.1875
+    let (ps, m, v) = steps(&adam, &[1., 2.]);
+    // corrected by 1 - beta^t: m_hat = 1.25 / 0.75, v_hat = 1.1875 / 0.4375
+    let p2 = 0.9 - RATE * (1.25 / 0.75) / (1.1875f64 / 0.4375).sqrt();
+    assert_close(&ps, &[0.9, p2]);
+    assert_close(&[m, v], &[1.25, 1.1875]);
+}
+
+#[test]
+fn first_adam_step_is_rate() {
+    // m_hat = g and v_hat = g², whatever the scale of g
+    let adam = Adam { eps: 0., ..Adam::default() };
+    assert_close(&steps(&adam, &[3.]).0, &[0.9]);
+    assert_close(&steps(&adam, &[-1e-3]).0, &[1.1]);
+}
+
+#[test]
+fn adam_w_decoupled_decay() {
+    let adam_w = AdamW { eps: 0., weight_decay: 0.5, ..AdamW::default() };
+    let adam = Adam { eps: 0., ..Adam::default() };
+    let (ps, m, v) = steps(&adam_w, &[3.]);
+    // the adam step is 1, and the decay `rate * weight_decay * p` is not part of the gradient
+    assert_close(&ps, &[1. - RATE * (1. + 0.5)]);
+    let (_, adam_m, adam_v) = steps(&adam, &[3.]);
+    assert_close(&[m, v], &[adam_m, adam_v]);
+}
@@ -0,0 +1,170 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Item, Network, derive_layers, dynamic::{Activation, DynNetwork}, func::*, model::*, persist::{self, ConfigMeta, Error, FORMAT_VERSION}};
+
+#[derive_layers(3, activation(Tanh, Tanh, Sigmoid), init(XavierNormal, XavierNormal, XavierNormal))]
+struct Net{}
+
+#[derive_layers(2)]
+struct Two{}
+
+/// `B` params in the bias of shape `(0, 0)`, which are no matrix
+struct Scalars<const B: usize> {
+    none: SVector<f64, 0>,
+    b: SVector<f64, B>,
+}
+
+impl<const B: usize> Default for Scalars<B> {
+    fn default() -> Self {
+        Self { none: SVector::zeros(), b: SVector::zeros() }
+    }
+}
+
+impl<const B: usize> Params for Scalars<B> {
+    fn shapes() -> Vec<(usize, usize)> {
+        vec![(0, 0)]
+    }
+
+    fn params(&self) -> Vec<(&[f64], &[f64])> {
+        vec![(self.none.as_slice(), self.b.as_slice())]
+    }
+
+    fn params_mut(&mut self) -> Vec<(&mut [f64], &mut [f64])> {
+        vec![(self.none.as_mut_slice(), self.b.as_mut_slice())]
+    }
+}
+
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(5)
+}
+
+fn config() -> ConfigMeta {
+    ConfigMeta {
+        learn_rate: 0.25,
+        batch_size: 4,
+        iter_num: 10,
+        actvt_func: "Tanh".to_owned(),
+        loss_func: "DistanceFunc".to_owned(),
+        optimizer: "Sgd".to_owned(),
+    }
+}
+
+#[test]
+fn binary_round_trip() {
+    let layers = Net::<3, 5, 4, 2>::random_with(&mut rng());
+    let mut buf = Vec::new();
+    persist::write_binary(&layers, Some(&config()), &mut buf).unwrap();
+
+    let saved: persist::Saved<Net<3, 5, 4, 2>> = persist::read_binary(&buf[..]).unwrap();
+    assert_eq!(saved.layers.params(), layers.params());
+    assert_eq!(saved.config, Some(config()));
+
+    // params are stored as f64 and loaded as f32
+    let saved: persist::Saved<Net<3, 5, 4, 2, f32>> = persist::read_binary(&buf[..]).unwrap();
+    assert_eq!(saved.layers.params(), layers.cast::<f32>().params());
+}
+
+#[test]
+fn json_round_trip() {
+    let layers = Net::<3, 5, 4, 2, f32>::random_with(&mut rng());
+    let json = persist::to_json(&layers, None);
+
+    let saved: persist::Saved<Net<3, 5, 4, 2, f32>> = persist::from_json(&json).unwrap();
+    assert_eq!(saved.layers.params(), layers.params());
+    assert_eq!(saved.config, None);
+
+    let json = persist::to_json(&layers, Some(&config()));
+    let saved: persist::Saved<Net<3, 5, 4, 2, f32>> = persist::from_json(&json).unwrap();
+    assert_eq!(saved.config, Some(config()));
+}
+
+#[test]
+fn model_keeps_config() {
+    let data = [Item::new(SVector::from([0., 1., 0.]), SVector::from([1., 0.]))];
+    let config = Config { iter_num: 2, ..Config::default_with_func(Tanh, DistanceFunc) };
+    let (model, _) = Network::cfg(Net::<3, 5, 4, 2>::random_with(&mut rng()), config.clone())
+        .train(&data[..])
+        .build();
+    assert_eq!(model.config, Some(ConfigMeta::from(&config)));
+
+    let path = std::env::temp_dir().join(format!("simple-nn-model-{}.json", std::process::id()));
+    model.save_json(&path).unwrap();
+    let loaded = Model::<Net<3, 5, 4, 2>, Tanh, _, 3, 2>::load_json(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.layers.params(), model.layers.params());
+    assert_eq!(loaded.config, model.config);
+}
+
+#[test]
+fn shape_mismatch() {
+    let mut buf = Vec::new();
+    persist::write_binary(&Net::<3, 5, 4, 2>::default(), None, &mut buf).unwrap();
+    match persist::read_binary::<Net<3, 5, 6, 2>, _, _>(&buf[..]) {
+        Err(Error::ShapeMismatch { layer: 2, expected: (6, 5), found: (4, 5) }) => {}
+        _ => panic!("expected a shape mismatch"),
+    }
+
+    let json = persist::to_json(&Net::<3, 5, 4, 2>::default(), None);
+    match persist::from_json::<Net<4, 5, 4, 2>, _>(&json) {
+        Err(Error::ShapeMismatch { layer: 1, expected: (5, 4), found: (5, 3) }) => {}
+        _ => panic!("expected a shape mismatch"),
+    }
+}
+
+#[test]
+fn layer_count() {
+    let mut buf = Vec::new();
+    persist::write_binary(&Net::<3, 5, 4, 2>::default(), None, &mut buf).unwrap();
+    match persist::read_binary::<Two<3, 5, 4>, _, _>(&buf[..]) {
+        Err(Error::LayerCount { expected: 2, found: 3 }) => {}
+        _ => panic!("expected a layer count error"),
+    }
+
+    let json = persist::to_json(&Two::<3, 5, 4>::default(), None);
+    match persist::from_json::<Net<3, 5, 4, 2>, _>(&json) {
+        Err(Error::LayerCount { expected: 3, found: 2 }) => {}
+        _ => panic!("expected a layer count error"),
+    }
+}
+
+#[test]
+fn bad_header() {
+    let mut buf = Vec::new();
+    persist::write_binary(&Two::<3, 5, 4>::default(), None, &mut buf).unwrap();
+
+    let mut other = buf.clone();
+    other[0] = b'X';
+    assert!(matches!(persist::read_binary::<Two<3, 5, 4>, _, _>(&other[..]), Err(Error::BadMagic)));
+
+    let mut other = buf.clone();
+    other[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        persist::read_binary::<Two<3, 5, 4>, _, _>(&other[..]),
+        Err(Error::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+    ));
+
+    // truncated params
+    assert!(matches!(persist::read_binary::<Two<3, 5, 4>, _, _>(&buf[..buf.len() - 1]), Err(Error::Io(_))));
+}
+
+#[test]
+fn params_of_other_lengths_than_shapes() {
+    let scalars = Scalars::<1> { b: SVector::from([2.5]), ..Default::default() };
+    let mut buf = Vec::new();
+    persist::write_binary(&scalars, None, &mut buf).unwrap();
+    let saved: persist::Saved<Scalars<1>> = persist::read_binary(&buf[..]).unwrap();
+    assert_eq!(saved.layers.b, scalars.b);
+    let saved: persist::Saved<Scalars<1>> = persist::from_json(&persist::to_json(&scalars, None)).unwrap();
+    assert_eq!(saved.layers.b, scalars.b);
+
+    // same shapes, other lengths
+    match persist::read_binary::<Scalars<2>, _, _>(&buf[..]) {
+        Err(Error::ParamCount { layer: 1, expected: (0, 2), found: (0, 1) }) => {}
+        _ => panic!("expected a param count error"),
+    }
+    let net = DynNetwork::<f64>::zeros(&[0, 0], &[Activation::Identity]).unwrap();
+    assert!(matches!(net.to_layers::<Scalars<1>>(), Err(Error::ParamCount { layer: 1, .. })));
+    // a dynamic network has dense layers only
+    let json = net.to_json().replace(r#""b":[]"#, r#""b":[1.0]"#);
+    assert!(matches!(DynNetwork::<f64>::from_json(&json), Err(Error::ParamCount { layer: 1, .. })));
+}
@@ -0,0 +1,55 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Network, check::gradient_check, derive_layers, func::*, model::*, optim::Adam};
+
+mod common;
+
+use common::{EPS, assert_close, check_module, item, random, rng};
+
+#[derive_layers(input(4), output(2))]
+struct Parametric {
+    #[init(HeNormal)]
+    hidden: Layer<6, 4, T>,
+    prelu: PReLU<6, T>,
+    #[activation(Sigmoid)]
+    #[init(XavierNormal)]
+    out: Layer<2, 6, T>,
+}
+
+#[test]
+fn slope_of_negative_inputs() {
+    let prelu = PReLU::<4>::init_with(Init::HeNormal, &mut rng());
+    assert_eq!(prelu.slope, SVector::<f64, 4>::repeat(0.25));
+    assert_eq!(PReLU::<4>::default().slope, SVector::<f64, 4>::zeros());
+    assert_eq!(PReLU::<4>::shapes(), vec![(4, 0)]);
+
+    let prelu = PReLU { slope: SVector::from([0.25, 0.5, 0.1, 2.]) };
+    let out = prelu.test(&SVector::from([-4., -4., 3., 0.]));
+    assert_eq!(out, SVector::from([-1., -2., 3., 0.]));
+}
+
+#[test]
+fn gradient() {
+    let mut rng = rng();
+    let prelu = PReLU::<6> { slope: random(&mut rng) };
+    check_module(&prelu, &mut rng);
+}
+
+#[test]
+fn in_declared_network() {
+    let mut rng = rng();
+    let net = Parametric::random_with(&mut rng);
+    assert_eq!(Parametric::<f64>::layers(), vec![1, 2, 3]);
+    assert_close(gradient_check(&net, &item::<4, 2>(&mut rng), Tanh, DistanceFunc, EPS), 3);
+
+    let data = (0..20).map(|_| item::<4, 2>(&mut rng)).collect::<Vec<_>>();
+    let config = Config {
+        iter_num: 50,
+        seed: Some(7),
+        ..Config::default_with_func(Tanh, DistanceFunc)
+    }.with_optimizer(Adam::default());
+    let (model, history) = Network::cfg(net, config).train(&data).build();
+    assert!(history.train_loss[49] < history.train_loss[0], "{:?}", history.train_loss);
+    // the slope is learned
+    assert!(model.layers.prelu.slope.iter().any(|&a| a != 0.25));
+}
@@ -0,0 +1,186 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, History, Item, Network, conv::*, derive_layers, func::*, model::*};
+
+mod common;
+
+use common::{item, rng};
+
+#[derive_layers(3, activation(Tanh, Tanh, Sigmoid), init(XavierNormal, XavierNormal, XavierNormal))]
+struct Dense {}
+
+#[derive_layers(input(12), output(2))]
+struct Signal {
+    #[activation(Tanh)]
+    #[init(XavierNormal)]
+    conv: Conv1d<2, 2, 6, 3, 2, 1, 12, 6, T>,
+    pool: AvgPool1d<2, 3, 2, 1, 6, 4, T>,
+    flat: Flatten<4, T>,
+    #[activation(Sigmoid)]
+    #[init(XavierNormal)]
+    out: Layer<2, 4, T>,
+}
+
+/// train `$layers` on `$data` with `$regularization` for `$iters` iters of rate `$rate`
+macro_rules! train {
+    ($layers:expr, $data:expr, $regularization:expr, $rate:expr, $iters:expr) => {{
+        let config = Config {
+            learn_rate: $rate,
+            iter_num: $iters,
+            seed: Some(5),
+            regularization: $regularization,
+            ..Config::default_with_func(Tanh, DistanceFunc)
+        };
+        let (model, history): (_, History) = Network::cfg($layers, config).train($data).build();
+        (model.layers, history)
+    }};
+}
+
+/// increase of the first training loss by `$regularization`, at a zero rate
+macro_rules! penalty {
+    ($layers:expr, $data:expr, $regularization:expr) => {{
+        let (_, plain) = train!($layers.clone(), $data, Regularization::default(), 0., 1);
+        let (_, penalized) = train!($layers.clone(), $data, $regularization, 0., 1);
+        penalized.train_loss[0] - plain.train_loss[0]
+    }};
+}
+
+/// `l2 / 2 * p²` of all `params`
+fn l2_loss(l2: f64, params: &[f64]) -> f64 {
+    params.iter().map(|p| l2 / 2. * p * p).sum()
+}
+
+/// `l1 * |p| + l2 / 2 * p²` of each entry of `params` by its own penalty
+fn expected(params: Vec<(&[f64], &[f64])>, penalties: &[Penalty], include_bias: bool) -> f64 {
+    params
+        .into_iter()
+        .zip(penalties)
+        .map(|((w, b), penalty)| {
+            let bias = if include_bias { b } else { &[] };
+            w.iter().chain(bias).map(|p| penalty.l1 * p.abs() + penalty.l2 / 2. * p * p).sum::<f64>()
+        })
+        .sum()
+}
+
+/// all params of `layers` in order
+fn flat(params: Vec<(&[f64], &[f64])>) -> Vec<f64> {
+    params.into_iter().flat_map(|(w, b)| w.iter().chain(b).copied()).collect()
+}
+
+fn data(rng: &mut StdRng) -> Vec<Item<12, 2>> {
+    (0..10).map(|_| item(rng)).collect()
+}
+
+fn assert_close(penalty: f64, expected: f64) {
+    assert!(expected > 0.);
+    assert!((penalty - expected).abs() < 1e-12, "{} != {}", penalty, expected);
+}
+
+#[test]
+fn penalty_is_in_train_loss() {
+    let mut rng = rng();
+    let data = data(&mut rng);
+    let regularization = Regularization::new(Penalty::l2(0.3));
+
+    let dense = Dense::<12, 6, 4, 2>::random_with(&mut rng);
+    let penalty = penalty!(dense, &data, regularization.clone());
+    assert_close(penalty, expected(dense.params(), &[Penalty::l2(0.3); 3], false));
+
+    let signal = Signal::random_with(&mut rng);
+    let penalty = penalty!(signal, &data, regularization);
+    assert_close(penalty, expected(signal.params(), &[Penalty::l2(0.3); 2], false));
+}
+
+#[test]
+fn per_layer_overrides() {
+    let mut rng = rng();
+    let data = data(&mut rng);
+    let (l1, l2) = (Penalty::l1(0.1), Penalty::l2(0.5));
+    let regularization = Regularization { penalty: l1, per_layer: vec![None, Some(l2)], ..Default::default() };
+
+    let dense = Dense::<12, 6, 4, 2>::random_with(&mut rng);
+    let penalty = penalty!(dense, &data, regularization);
+    assert_close(penalty, expected(dense.params(), &[l1, l2, l1], false));
+
+    // the second field is pooling, the override of the first applies to the convolution
+    let regularization = Regularization { penalty: l1, per_layer: vec![Some(l2)], ..Default::default() };
+    let signal = Signal::random_with(&mut rng);
+    let penalty = penalty!(signal, &data, regularization);
+    assert_close(penalty, expected(signal.params(), &[l2, l1], false));
+}
+
+#[test]
+fn include_bias() {
+    let mut rng = rng();
+    let data = data(&mut rng);
+    let weights = Regularization::new(Penalty::l2(0.3));
+    let all = Regularization { include_bias: true, ..weights.clone() };
+
+    let mut dense = Dense::<12, 6, 4, 2>::random_with(&mut rng);
+    // other biases start from zero
+    dense.layer_2.b = SVector::repeat(0.5);
+    let (only, both) = (penalty!(dense, &data, weights.clone()), penalty!(dense, &data, all.clone()));
+    assert_close(both, expected(dense.params(), &[Penalty::l2(0.3); 3], true));
+    assert_close(both - only, 0.3 / 2. * 0.5 * 0.5 * 4.);
+
+    let mut signal = Signal::random_with(&mut rng);
+    signal.out.b = SVector::repeat(0.5);
+    let (only, both) = (penalty!(signal, &data, weights), penalty!(signal, &data, all));
+    assert_close(both, expected(signal.params(), &[Penalty::l2(0.3); 2], true));
+    assert!(both > only);
+}
+
+#[test]
+fn elastic_net_is_l1_and_l2() {
+    let mut rng = rng();
+    let data = data(&mut rng);
+    let elastic = Regularization::new(Penalty::elastic_net(0.4, 0.25));
+    let (l1, l2) = (Regularization::new(Penalty::l1(0.1)), Regularization::new(Penalty::l2(0.3)));
+
+    let dense = Dense::<12, 6, 4, 2>::random_with(&mut rng);
+    let penalty = penalty!(dense, &data, elastic.clone());
+    assert_close(penalty, penalty!(dense, &data, l1.clone()) + penalty!(dense, &data, l2.clone()));
+    // the gradients add up as well
+    let step = |regularization| flat(train!(dense.clone(), &data, regularization, 0.1, 1).0.params());
+    let plain = step(Regularization::default());
+    let (elastic_step, l1_step, l2_step) = (step(elastic.clone()), step(l1.clone()), step(l2.clone()));
+    for i in 0..plain.len() {
+        assert!((elastic_step[i] - plain[i] - (l1_step[i] - plain[i]) - (l2_step[i] - plain[i])).abs() < 1e-12);
+    }
+
+    let signal = Signal::random_with(&mut rng);
+    let penalty = penalty!(signal, &data, elastic.clone());
+    assert_close(penalty, penalty!(signal, &data, l1.clone()) + penalty!(signal, &data, l2.clone()));
+    let step = |regularization| flat(train!(signal.clone(), &data, regularization, 0.1, 1).0.params());
+    let plain = step(Regularization::default());
+    let (elastic_step, l1_step, l2_step) = (step(elastic), step(l1), step(l2));
+    for i in 0..plain.len() {
+        assert!((elastic_step[i] - plain[i] - (l1_step[i] - plain[i]) - (l2_step[i] - plain[i])).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn per_field_penalty_of_declared_layers() {
+    let mut rng = rng();
+    let net = Signal::random_with(&mut rng);
+    let data = data(&mut rng);
+    assert_eq!(Signal::<f64>::layers(), vec![1, 4]);
+
+    // the dense layer is the fourth field, after pooling and flatten without params
+    let regularization = Regularization {
+        per_layer: vec![None, None, None, Some(Penalty::l2(0.5))],
+        ..Default::default()
+    };
+    // a zero rate keeps the weights, so the losses differ by the penalty alone
+    let (_, plain) = train!(net.clone(), &data, Regularization::default(), 0., 1);
+    let (_, penalized) = train!(net.clone(), &data, regularization.clone(), 0., 1);
+    let penalty = l2_loss(0.5, net.out.w.as_slice());
+    assert!(penalty > 0.);
+    assert!((penalized.train_loss[0] - plain.train_loss[0] - penalty).abs() < 1e-12);
+
+    // the same penalty is in the gradient, which decays the dense weights only
+    let (plain, _) = train!(net.clone(), &data, Regularization::default(), 0.1, 1);
+    let (decayed, _) = train!(net.clone(), &data, regularization, 0.1, 1);
+    assert_eq!(decayed.conv.w, plain.conv.w);
+    assert!((decayed.out.w - (plain.out.w - net.out.w * 0.1 * 0.5)).amax() < 1e-12);
+}
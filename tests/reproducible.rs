@@ -0,0 +1,118 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Item, Network, derive_layers, func::*, model::*};
+
+#[derive_layers(2)]
+struct XorLayers{}
+
+fn data() -> Vec<Item<2, 1>> {
+    [([0., 0.], 0.), ([0., 1.], 1.), ([1., 0.], 1.), ([1., 1.], 0.)]
+        .iter()
+        .map(|&(data, label)| Item::new(SVector::from(data), SVector::from([label])))
+        .collect()
+}
+
+fn train(seed: u64) -> XorLayers<2, 4, 1> {
+    let layers = XorLayers::random_with(&mut StdRng::seed_from_u64(seed));
+    let mut config = Config::default_with_func(Tanh, DistanceFunc);
+    config.batch_size = 2;
+    config.iter_num = 50;
+    config.learn_rate = 0.1;
+    config.seed = Some(seed);
+
+    let data = data();
+    let (model, _) = Network::cfg(layers, config).train(&data).build();
+    model.layers
+}
+
+fn params(layers: &XorLayers<2, 4, 1>) -> Vec<f64> {
+    layers.params()
+        .into_iter()
+        .flat_map(|(w, b)| w.iter().chain(b).copied().collect::<Vec<_>>())
+        .collect()
+}
+
+#[test]
+fn same_seed_gives_identical_model() {
+    let a = params(&train(7));
+    let b = params(&train(7));
+    assert_eq!(
+        a.iter().map(|v| v.to_bits()).collect::<Vec<_>>(),
+        b.iter().map(|v| v.to_bits()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn different_seeds_give_different_models() {
+    assert_ne!(params(&train(7)), params(&train(8)));
+}
+
+#[test]
+fn seeded_init_is_reproducible() {
+    let a = Layer::<3, 2>::random_with(&mut StdRng::seed_from_u64(1));
+    let b = Layer::<3, 2>::random_with(&mut StdRng::seed_from_u64(1));
+    assert_eq!(a.w, b.w);
+    assert_eq!(a.b, b.b);
+}
+
+/// gradients of the trainer, on multiple threads with feature `parallel`, are the
+/// average of the gradients of `Layers::backward` item by item
+#[test]
+fn trainer_matches_serial_loop() {
+    let data = data();
+    let start = XorLayers::<2, 4, 1>::random_with(&mut StdRng::seed_from_u64(3));
+    let config = Config {
+        batch_size: data.len(),
+        iter_num: 20,
+        learn_rate: 0.5,
+        seed: Some(3),
+        ..Config::default_with_func(Tanh, DistanceFunc)
+    };
+    let (model, _) = Network::cfg(start.clone(), config).train(&data).build();
+
+    let mut layers = start;
+    let mut moments = Moments::default();
+    for _ in 0..20 {
+        let grads = data.iter().map(|item| {
+            let calc = Layers::<Tanh, _, 2, 1>::forward(&layers, &item.data);
+            let out = Layers::<Tanh, _, 2, 1>::test(&layers, &item.data);
+            Layers::<Tanh, _, 2, 1>::backward(&layers, &item.data, DistanceFunc::d(&item.label, &out), calc)
+        }).collect::<Vec<_>>();
+        Layers::<Tanh, _, 2, 1>::update(&mut layers, 0.5, &nn::optim::Sgd, &mut moments, &Regularization::default(), grads);
+    }
+
+    for (a, b) in params(&model.layers).iter().zip(params(&layers).iter()) {
+        assert!((a - b).abs() < 1e-12, "{} != {}", a, b);
+    }
+}
+
+/// chunks are split the same way whatever the count of threads, so the model is bit-identical
+#[cfg(feature = "parallel")]
+#[test]
+fn same_model_on_any_thread_count() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let data = (0..37).map(|_| {
+        let (a, b) = (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+        Item::new(SVector::from([a, b]), SVector::from([(a > 0.5) as u8 as f64]))
+    }).collect::<Vec<_>>();
+    let train = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            let layers = XorLayers::<2, 4, 1>::random_with(&mut StdRng::seed_from_u64(5));
+            let config = Config {
+                batch_size: 37,
+                iter_num: 30,
+                learn_rate: 0.5,
+                seed: Some(5),
+                ..Config::default_with_func(Tanh, DistanceFunc)
+            };
+            let (model, _) = Network::cfg(layers, config).train(&data).build();
+            params(&model.layers).iter().map(|v| v.to_bits()).collect::<Vec<_>>()
+        })
+    };
+
+    let serial = train(1);
+    for threads in [2, 3, 8] {
+        assert_eq!(train(threads), serial, "{} threads", threads);
+    }
+}
@@ -0,0 +1,101 @@
+extern crate simple_nn as nn;
+
+use std::f64::consts::PI;
+
+use nn::schedule::*;
+
+const BASE: f64 = 0.8;
+
+/// rates of iters `1..=n` without validation
+fn rates<S: LrScheduler>(mut scheduler: S, n: usize) -> Vec<f64> {
+    (1..=n).map(|i| scheduler.rate(i, BASE, None)).collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() < 1e-12, "iter {}: {} != {}", i + 1, a, e);
+    }
+}
+
+#[test]
+fn step_decay_halves_as_before() {
+    // the trainer used to halve the rate after every 10_000th iter
+    let mut rate = BASE;
+    let old = (1..=30_001).map(|i| {
+        let r = rate;
+        if i % 10_000 == 0 {
+            rate /= 2.;
+        }
+        r
+    }).collect::<Vec<_>>();
+    assert_eq!(rates(StepDecay::default(), 30_001), old);
+}
+
+#[test]
+fn step_decay() {
+    let step = StepDecay { step_size: 2, gamma: 0.1 };
+    assert_close(&rates(step, 5), &[0.8, 0.8, 0.08, 0.08, 0.008]);
+}
+
+#[test]
+fn exponential_decay() {
+    assert_close(&rates(ExponentialDecay { gamma: 0.5 }, 4), &[0.8, 0.4, 0.2, 0.1]);
+}
+
+#[test]
+fn cosine_restarts() {
+    // cycles of 2 and 4 iters, each starting at `base`
+    let cosine = CosineAnnealing { period: 2, mult: 2, min_rate: 0.2 };
+    let at = |progress: f64| 0.2 + 0.6 * (1. + (PI * progress).cos()) / 2.;
+    assert_close(&rates(cosine, 7), &[0.8, 0.5, 0.8, at(0.25), 0.5, at(0.75), 0.8]);
+}
+
+#[test]
+fn warmup() {
+    let warmup = LinearWarmup { warmup: 4, then: ExponentialDecay { gamma: 0.5 } };
+    assert_close(&rates(warmup, 6), &[0.2, 0.4, 0.6, 0.8, 0.8, 0.4]);
+}
+
+#[test]
+fn one_cycle() {
+    // 3 iters up from 0.8 / 4, then 7 iters down to 0.8 / 40
+    let one_cycle = OneCycle { total: 10, pct_start: 0.3, div: 4., final_div: 10. };
+    let r = rates(one_cycle, 10);
+    assert_close(&r[..4], &[0.2, 0.2 + 0.6 * 0.25, 0.2 + 0.6 * 0.75, 0.8]);
+    assert!(r.windows(2).skip(3).all(|w| w[0] > w[1]));
+    let last = 0.02 + 0.78 * (1. + (PI * 6. / 7.).cos()) / 2.;
+    assert_close(&r[9..], &[last]);
+    assert!(r[9] > 0.02);
+}
+
+#[test]
+fn reduce_on_plateau() {
+    let mut plateau = ReduceOnPlateau::new(0.5, 2);
+    plateau.threshold = 0.1;
+    plateau.min_rate = 0.15;
+    let losses = [
+        None,
+        Some(1.),
+        // improvements under the threshold do not count
+        Some(0.95),
+        Some(0.92),
+        Some(0.5),
+        Some(0.5),
+        Some(0.5),
+        Some(0.5),
+        Some(0.5),
+        Some(0.5),
+        Some(0.5),
+    ];
+    let r = losses.iter()
+        .enumerate()
+        .map(|(i, &loss)| plateau.rate(i + 1, BASE, loss))
+        .collect::<Vec<_>>();
+    assert_close(&r, &[0.8, 0.8, 0.8, 0.4, 0.4, 0.4, 0.2, 0.2, 0.15, 0.15, 0.15]);
+}
+
+#[test]
+fn reduce_on_plateau_without_validation() {
+    assert_close(&rates(ReduceOnPlateau::new(0.5, 1), 3), &[0.8; 3]);
+}
@@ -0,0 +1,11 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, Network, derive_layers, func::*, model::*};
+
+#[derive_layers(2, activation(Tanh, Sigmoid))]
+struct Net{}
+
+fn main() {
+    let layers = Net::<2, 3, 2>::default();
+    Network::cfg(layers, Config::default_with_func(Tanh, CrossEntroy));
+}
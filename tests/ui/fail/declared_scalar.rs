@@ -0,0 +1,10 @@
+extern crate simple_nn as nn;
+
+use nn::derive_layers;
+
+#[derive_layers(input(3), output(2))]
+struct Net {
+    out: Layer<2, 3>,
+}
+
+fn main() {}
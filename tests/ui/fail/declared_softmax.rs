@@ -0,0 +1,11 @@
+extern crate simple_nn as nn;
+
+use nn::derive_layers;
+
+#[derive_layers(input(3), output(2), softmax)]
+struct Net {
+    #[activation(Sigmoid)]
+    out: Layer<2, 3, T>,
+}
+
+fn main() {}
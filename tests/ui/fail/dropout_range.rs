@@ -0,0 +1,8 @@
+extern crate simple_nn as nn;
+
+use nn::derive_layers;
+
+#[derive_layers(2, dropout(1.0, _))]
+struct Net{}
+
+fn main() {}
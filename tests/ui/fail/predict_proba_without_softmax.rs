@@ -0,0 +1,11 @@
+extern crate simple_nn as nn;
+
+use nn::{derive_layers, func::*, model::*};
+
+#[derive_layers(2, activation(Tanh, Sigmoid))]
+struct Net{}
+
+fn main() {
+    let model = Model::<_, Tanh, _, 2, 2>::new(Net::<2, 3, 2>::default());
+    model.predict_proba(&SVector::zeros());
+}
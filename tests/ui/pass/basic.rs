@@ -0,0 +1,12 @@
+extern crate simple_nn as nn;
+
+use nn::{derive_layers, func::*, model::*};
+
+#[derive_layers(2)]
+struct Net{}
+
+fn main() {
+    let net = Net::<3, 4, 2>::random();
+    let out = Layers::<Sigmoid, _, 3, 2>::test(&net, &SVector::from([0.1, 0.2, 0.3]));
+    assert_eq!(out.len(), 2);
+}
@@ -0,0 +1,20 @@
+extern crate simple_nn as nn;
+
+use nn::{conv::*, derive_layers, func::*, model::*};
+
+#[derive_layers(input(16), output(2), softmax)]
+pub struct Net {
+    #[activation(ReLU)]
+    #[init(HeNormal)]
+    conv: Conv2d<1, 2, 4, 4, 3, 1, 1, 16, 32, T>,
+    pool: AvgPool2d<2, 4, 4, 2, 2, 32, 8, T>,
+    #[dropout(0.1)]
+    flat: Flatten<8, T>,
+    out: Layer<2, 8, T>,
+}
+
+fn main() {
+    let net = Net::<f32>::random();
+    let p = SoftmaxLayers::<Tanh, _, 16, 2, f32>::predict_proba(&net, &SVector::repeat(0.5));
+    assert!((p.sum() - 1.).abs() < 1e-6);
+}
@@ -0,0 +1,13 @@
+extern crate simple_nn as nn;
+
+use nn::{derive_layers, func::*, model::*};
+
+#[derive_layers(2, activation(GELU, _))]
+struct Net{}
+
+fn main() {
+    let net = Net::<2, 3, 1, f32>::random();
+    let out = Layers::<Sigmoid, _, 2, 1, f32>::test(&net, &SVector::from([0.5f32, -0.5]));
+    assert!(out[0] > 0. && out[0] < 1.);
+    let _: Net<2, 3, 1> = net.cast();
+}
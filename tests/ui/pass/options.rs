@@ -0,0 +1,20 @@
+extern crate simple_nn as nn;
+
+use nn::{Network, derive_layers, func::*, model::*};
+
+#[derive_layers(
+    3,
+    activation(ReLU, _, _),
+    init(HeNormal, XavierUniform, _),
+    dropout(0.2, _, _),
+    softmax,
+)]
+struct Net{}
+
+fn main() {
+    let net = Net::<4, 8, 6, 3>::random();
+    let p = SoftmaxLayers::<Tanh, _, 4, 3>::predict_proba(&net, &SVector::from([1., 0., -1., 0.5]));
+    assert!((p.sum() - 1.).abs() < 1e-12);
+    // the default `CrossEntroy` takes the logits of softmax layers
+    Network::new(net);
+}
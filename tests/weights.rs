@@ -0,0 +1,68 @@
+extern crate simple_nn as nn;
+
+use nn::{Config, History, Item, Network, derive_layers, func::*, model::*, schedule::Constant};
+
+#[derive_layers(2, init(XavierNormal, XavierNormal))]
+struct Net{}
+
+fn layers() -> Net<2, 3, 2> {
+    Net::random_with(&mut StdRng::seed_from_u64(9))
+}
+
+fn train(data: &[Item<2, 2>], learn_rate: f64, class_weights: Option<Vec<f64>>) -> (Net<2, 3, 2>, History) {
+    let config = Config {
+        learn_rate,
+        iter_num: 3,
+        seed: Some(9),
+        class_weights,
+        ..Config::default_with_func(Sigmoid, DistanceFunc)
+    }.with_scheduler(Constant);
+    let (model, history) = Network::cfg(layers(), config).train(data).build();
+    (model.layers, history)
+}
+
+fn first() -> Item<2, 2> {
+    Item::new(SVector::from([0.3, -0.6]), SVector::from([1., 0.]))
+}
+
+fn second() -> Item<2, 2> {
+    Item::new(SVector::from([-0.2, 0.9]), SVector::from([0., 1.]))
+}
+
+#[test]
+fn item_weight_scales_loss_and_gradient() {
+    let (weighted, weighted_history) = train(&[first().with_weight(2.)], 0.1, None);
+    // a gradient twice as large is a step of twice the rate
+    let (plain, history) = train(&[first()], 0.2, None);
+    assert_eq!(weighted.params(), plain.params());
+    for (w, l) in weighted_history.train_loss.iter().zip(&history.train_loss) {
+        assert_eq!(*w, 2. * l);
+    }
+}
+
+#[test]
+fn class_weights_scale_loss_and_gradient() {
+    let (weighted, weighted_history) = train(&[second()], 0.1, Some(vec![1., 4.]));
+    let (plain, history) = train(&[second()], 0.4, None);
+    assert_eq!(weighted.params(), plain.params());
+    for (w, l) in weighted_history.train_loss.iter().zip(&history.train_loss) {
+        assert_eq!(*w, 4. * l);
+    }
+}
+
+#[test]
+fn class_and_item_weights_multiply() {
+    // the second class has no weight, and the first is weighted by 2 in a chunk of 2 items,
+    // which is the same as training the first item alone
+    let data = [first(), second().with_weight(3.)];
+    let (weighted, weighted_history) = train(&data, 0.1, Some(vec![2., 0.]));
+    let (plain, history) = train(&[first()], 0.1, None);
+    assert_eq!(weighted.params(), plain.params());
+    assert_eq!(weighted_history.train_loss, history.train_loss);
+
+    let config = Config::default_with_func(Sigmoid, DistanceFunc);
+    let config = Config { class_weights: Some(vec![2., 0.5]), ..config };
+    assert_eq!(config.item_weight(&first().with_weight(3.)), 6.);
+    assert_eq!(config.item_weight(&second().with_weight(3.)), 1.5);
+    assert_eq!(config.item_weight(&second()), 0.5);
+}